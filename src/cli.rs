@@ -1,30 +1,103 @@
+use crate::models::TimeGrouping;
+use crate::output::Format;
 use clap::{Parser, Subcommand};
 
+use crate::context::Target;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    /// Output format for listing commands
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+
+    /// Work on another project (name, ID or path) instead of the current one
+    #[arg(long, global = true, conflicts_with = "global")]
+    pub project: Option<String>,
+
+    /// Work on tasks and notes that belong to no project
+    #[arg(long, global = true)]
+    pub global: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl Args {
+    /// The tasks and notes `--project` and `--global` point commands at.
+    pub fn target(&self) -> Target {
+        match (&self.project, self.global) {
+            (_, true) => Target::Global,
+            (Some(project), false) => Target::Named(project.clone()),
+            (None, false) => Target::Current,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Add a new task
+    ///
+    /// The title may carry inline attributes: `!high` or `!!` for priority,
+    /// `@tag` or `+tag` for tags, `due:friday`, `project:<name or path>`,
+    /// `every:weekly_on_monday`, and a trailing natural language date such as
+    /// "tomorrow" or "next friday 9am".
+    /// Prefix a word with `\` to keep it in the title as-is.
     Add {
-        /// Task title
-        title: String,
+        /// Task title, optionally with inline attributes
+        #[arg(required = true, num_args = 1..)]
+        title: Vec<String>,
         
         /// Priority (low, medium, high, critical)
-        #[arg(short, long, default_value = "medium")]
-        priority: String,
+        #[arg(short, long)]
+        priority: Option<String>,
         
         /// Due date (natural language, e.g., "tomorrow", "in 2 days")
         #[arg(short, long)]
         due: Option<String>,
+
+        /// Tags (comma separated), added to any inline @tags
+        #[arg(short = 'g', long, value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// Make this a subtask of another task
+        #[arg(long)]
+        parent: Option<i64>,
+
+        /// Repeat the task, e.g. "weekly on monday", "every 2 weeks",
+        /// "monthly on the last friday" or an RRULE such as "FREQ=DAILY;INTERVAL=3"
+        #[arg(long)]
+        every: Option<String>,
+
+        /// Schedule each repeat from the day the task is completed instead of
+        /// its due date
+        #[arg(long)]
+        after_completion: bool,
+
+        /// Use the title exactly as given, without inline parsing
+        #[arg(long)]
+        literal: bool,
+
+        /// Show how the input was parsed without adding the task
+        #[arg(long)]
+        explain: bool,
+
+        /// Write a description in $EDITOR before adding the task
+        #[arg(short, long, conflicts_with = "explain")]
+        edit: bool,
     },
     
     /// List tasks
+    ///
+    /// Accepts an optional filter expression, e.g.
+    /// `flow ls 'priority>=high and tag:bug and due<friday and not status:done'`.
+    /// Fields: id, status, priority, tag, due, created, title, project; bare
+    /// words match the title or description. `due:none` and `due:any` match
+    /// tasks without or with a due date.
     Ls {
+        /// Filter expression
+        query: Vec<String>,
+
         /// Show all tasks (ignore context)
         #[arg(short, long)]
         all: bool,
@@ -32,26 +105,407 @@ pub enum Commands {
         /// Filter by status
         #[arg(short, long)]
         status: Option<String>,
+
+        /// Only show tasks with this tag (repeatable, all must match)
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Sort keys, comma separated: priority, due, created, status, title,
+        /// each optionally suffixed with :asc or :desc (e.g. "priority,due:desc")
+        #[arg(long, value_delimiter = ',')]
+        sort: Vec<String>,
+
+        /// Group into sections by status, priority, project, tag or due
+        /// (table output only)
+        #[arg(short, long, conflicts_with = "tree")]
+        group_by: Option<String>,
+
+        /// Show subtasks nested under their parents (table output only)
+        #[arg(long)]
+        tree: bool,
     },
     
+    /// Edit a task; without options, opens its title, tags and
+    /// description in $EDITOR
+    Edit {
+        /// Task ID
+        id: i64,
+
+        /// New title
+        #[arg(short, long)]
+        title: Option<String>,
+
+        /// New description (pass an empty string to clear it)
+        #[arg(long)]
+        description: Option<String>,
+
+        /// New status (todo, in-progress, done)
+        #[arg(short, long)]
+        status: Option<String>,
+
+        /// New priority (low, medium, high, critical)
+        #[arg(short, long)]
+        priority: Option<String>,
+
+        /// New due date (natural language, e.g., "tomorrow", "in 2 days")
+        #[arg(short, long, conflicts_with = "no_due")]
+        due: Option<String>,
+
+        /// Clear the due date
+        #[arg(long)]
+        no_due: bool,
+
+        /// Replace all tags (comma separated)
+        #[arg(short = 'g', long, value_delimiter = ',', conflicts_with_all = ["add_tag", "remove_tag"])]
+        tags: Option<Vec<String>>,
+
+        /// Add tags (comma separated)
+        #[arg(long, value_delimiter = ',')]
+        add_tag: Vec<String>,
+
+        /// Remove tags (comma separated)
+        #[arg(long, value_delimiter = ',')]
+        remove_tag: Vec<String>,
+
+        /// Move under another task as a subtask
+        #[arg(long, conflicts_with = "no_parent")]
+        parent: Option<i64>,
+
+        /// Make this a top-level task again
+        #[arg(long)]
+        no_parent: bool,
+
+        /// New repeat rule, e.g. "weekly on monday" or "every 2 weeks"
+        #[arg(long, conflicts_with = "no_every")]
+        every: Option<String>,
+
+        /// Stop repeating the task
+        #[arg(long)]
+        no_every: bool,
+
+        /// Schedule repeats from the completion date
+        #[arg(long, conflicts_with = "on_schedule")]
+        after_completion: bool,
+
+        /// Schedule repeats from the due date (the default)
+        #[arg(long)]
+        on_schedule: bool,
+
+        /// Edit the title, tags and description in $EDITOR
+        #[arg(short, long, conflicts_with_all = ["title", "description", "tags", "add_tag", "remove_tag"])]
+        edit: bool,
+    },
+
     /// Mark a task as done
     Done {
         /// Task ID
         id: i64,
     },
+
+    /// Move a finished task back to Todo
+    Reopen {
+        /// Task ID
+        id: i64,
+    },
     
+    /// Start tracking time on a task and mark it In Progress
+    ///
+    /// Only one timer runs at a time; a timer on another task is stopped.
+    Start {
+        /// Task ID
+        id: i64,
+
+        /// Also create and check out a git branch for the task, named by the
+        /// branch_template setting (default "{id}-{slug}")
+        #[arg(short, long)]
+        branch: bool,
+    },
+
+    /// Stop the running timer
+    Stop,
+
+    /// Report tracked time
+    #[command(subcommand)]
+    Time(TimeCommands),
+
+    /// Suggest a commit message from the tasks on the current branch
+    ///
+    /// Uses the tasks whose branch is checked out, with their finished and
+    /// in-progress subtasks, or else the tasks in progress in this project.
+    CommitMsg,
+
+    /// Manage git hooks that link commits to tasks
+    #[command(subcommand)]
+    Hooks(HookCommands),
+
+    /// Turn TODO, FIXME and HACK comments in tracked files into tasks
+    ///
+    /// Running it again updates the same tasks: moved comments keep their
+    /// task, and tasks whose comment is gone are marked done.
+    Scan {
+        /// Show what would change without saving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Mark a task as blocked until other tasks are done
+    Block {
+        /// Task ID
+        id: i64,
+
+        /// IDs of the tasks it waits on (comma separated)
+        #[arg(long, required = true, value_delimiter = ',')]
+        on: Vec<i64>,
+    },
+
+    /// Remove dependencies from a task
+    Unblock {
+        /// Task ID
+        id: i64,
+
+        /// Only drop these dependencies (comma separated); all if omitted
+        #[arg(long, value_delimiter = ',')]
+        on: Vec<i64>,
+    },
+
+    /// Suggest the next tasks to work on: unfinished, unblocked, most urgent first
+    Next {
+        /// Consider tasks from every project (ignore context)
+        #[arg(short, long)]
+        all: bool,
+
+        /// Number of suggestions
+        #[arg(short = 'n', long, default_value_t = 5)]
+        limit: usize,
+    },
+
     /// Remove a task
     Rm {
         /// Task ID
         id: i64,
+
+        /// Also remove the task's subtasks
+        #[arg(short, long)]
+        recursive: bool,
     },
     
     /// Open the Terminal UI
-    Ui,
+    Ui {
+        /// Filter expression, as accepted by `flow ls`
+        query: Vec<String>,
+    },
+
+    /// Search task and note text
+    Search {
+        /// Search terms; every term must match, and the last one matches as a prefix
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+
+        /// Search every project (ignore context)
+        #[arg(short, long)]
+        all: bool,
+
+        /// Only search tasks
+        #[arg(long, conflicts_with = "notes")]
+        tasks: bool,
+
+        /// Only search notes
+        #[arg(long)]
+        notes: bool,
+
+        /// Maximum number of results
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Pass the query to SQLite FTS5 unchanged (supports OR, NOT, "phrases", column:term)
+        #[arg(long)]
+        raw: bool,
+    },
+
+    /// Show repeating tasks
+    Recur {
+        #[command(subcommand)]
+        command: Option<RecurCommands>,
+    },
+
+    /// List projects and repair moved, renamed or duplicated ones
+    Project {
+        #[command(subcommand)]
+        command: Option<ProjectCommands>,
+    },
+
+    /// List and manage task tags
+    Tags {
+        #[command(subcommand)]
+        command: Option<TagCommands>,
+    },
 
     /// Manage notes
     #[command(subcommand)]
     Note(NoteCommands),
+
+    /// Show or change settings
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+    },
+
+    /// Inspect and upgrade the database schema
+    #[command(subcommand)]
+    Db(DbCommands),
+}
+
+#[derive(Subcommand)]
+pub enum TimeCommands {
+    /// Sum up tracked hours
+    Report {
+        /// Start of the period, e.g. "last monday" (default: the last 7 days)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Last day of the period (default: now)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Add up time per project, tag or task
+        #[arg(long, value_enum, default_value_t = TimeGrouping::Project)]
+        by: TimeGrouping,
+
+        /// Include every project (ignore context)
+        #[arg(short, long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum HookCommands {
+    /// Install prepare-commit-msg, commit-msg and post-commit hooks in this
+    /// repository
+    ///
+    /// prepare-commit-msg fills in a suggested message and adds
+    /// `Flow-Task: <id>` trailers; commit-msg adds trailers for tasks
+    /// referenced as "closes flow#12"; with the close_tasks_on_commit
+    /// setting, post-commit marks them done.
+    Install {
+        /// Replace existing hooks that were not installed by flow
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Remove the hooks installed by flow
+    Uninstall,
+
+    /// Run a hook; called by the installed hook scripts
+    #[command(hide = true)]
+    Run {
+        hook: String,
+
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RecurCommands {
+    /// List open repeating tasks with their rules and next due dates (default)
+    Ls {
+        /// Show repeating tasks from every project (ignore context)
+        #[arg(short, long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProjectCommands {
+    /// List projects with their task and note counts (default)
+    Ls,
+
+    /// Give a project a new name
+    Rename {
+        /// Project ID, name or path
+        #[arg(value_name = "PROJECT")]
+        key: String,
+
+        /// New name
+        name: String,
+    },
+
+    /// Point a project at the directory it was moved or cloned to
+    Move {
+        /// Project ID, name or path
+        #[arg(value_name = "PROJECT")]
+        key: String,
+
+        /// New location
+        path: String,
+    },
+
+    /// Merge one or more projects into another
+    Merge {
+        /// Projects to merge away (ID, name or path)
+        #[arg(required = true)]
+        sources: Vec<String>,
+
+        /// Project that receives their tasks and notes
+        #[arg(long)]
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// List tags with task and project counts (default)
+    Ls,
+
+    /// Rename a tag on every task
+    Rename {
+        /// Current tag name
+        old: String,
+
+        /// New tag name
+        new: String,
+    },
+
+    /// Merge one or more tags into another
+    Merge {
+        /// Tags to merge away
+        #[arg(required = true)]
+        sources: Vec<String>,
+
+        /// Tag that replaces them
+        #[arg(long)]
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// List every setting (default)
+    Ls,
+
+    /// Print one setting
+    Get {
+        key: String,
+    },
+
+    /// Change a setting
+    Set {
+        key: String,
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Show the current schema version and pending migrations
+    Status,
+
+    /// Apply pending migrations
+    Migrate {
+        /// Run the migrations in a transaction that is rolled back
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -59,7 +513,8 @@ pub enum NoteCommands {
     /// Add a new note
     Add {
         /// Note title
-        title: String,
+        #[arg(required_unless_present = "edit")]
+        title: Option<String>,
 
         /// Note content
         #[arg(short, long)]
@@ -68,6 +523,10 @@ pub enum NoteCommands {
         /// Tags (comma separated)
         #[arg(short, long, value_delimiter = ',')]
         tags: Vec<String>,
+
+        /// Write the note in $EDITOR
+        #[arg(short, long)]
+        edit: bool,
     },
 
     /// List notes
@@ -77,10 +536,14 @@ pub enum NoteCommands {
         all: bool,
     },
 
-    /// Show a note
+    /// Show a note, with its markdown rendered
     Show {
         /// Note ID
         id: i64,
+
+        /// Print the content as written, without rendering
+        #[arg(long)]
+        raw: bool,
     },
 
     /// Remove a note
@@ -89,7 +552,7 @@ pub enum NoteCommands {
         id: i64,
     },
 
-    /// Edit a note
+    /// Edit a note; without options, opens it in $EDITOR
     Edit {
         /// Note ID
         id: i64,
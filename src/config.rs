@@ -0,0 +1,124 @@
+use anyhow::{anyhow, bail, Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+/// User settings, stored as JSON in the platform config directory
+/// (e.g. `~/.config/flow/config.json`). Missing keys take their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Mark a parent task done once all of its subtasks are done.
+    pub auto_complete_parents: bool,
+    /// Name for branches made by `flow start --branch`; `{id}` and `{slug}`
+    /// are replaced by the task ID and a slug of its title.
+    pub branch_template: String,
+    /// Let the post-commit hook complete tasks referenced as "closes flow#12".
+    pub close_tasks_on_commit: bool,
+    /// How the worktrees of one repository map to projects.
+    pub worktrees: WorktreeScope,
+    /// Whether a submodule is a project of its own.
+    pub submodules: SubmoduleScope,
+    /// Work-in-progress limits for the TUI board's columns; a column holding
+    /// more cards is highlighted. `null` means no limit.
+    pub wip_todo: Option<usize>,
+    pub wip_in_progress: Option<usize>,
+    pub wip_done: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorktreeScope {
+    /// Every worktree works on the repository's tasks.
+    Shared,
+    /// Like `Shared`, but each branch only sees its own tasks and those not
+    /// tied to any branch; tasks added on a branch other than the default
+    /// one are tied to it.
+    Branch,
+    /// Each worktree is a separate project.
+    Separate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmoduleScope {
+    /// A submodule is a project of its own.
+    Own,
+    /// A submodule belongs to the project of the repository containing it.
+    Superproject,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            auto_complete_parents: false,
+            branch_template: "{id}-{slug}".to_string(),
+            close_tasks_on_commit: false,
+            worktrees: WorktreeScope::Shared,
+            submodules: SubmoduleScope::Own,
+            wip_todo: None,
+            wip_in_progress: None,
+            wip_done: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf> {
+        let project_dirs = ProjectDirs::from("com", "flow", "flow")
+            .context("Could not determine project directories")?;
+        Ok(project_dirs.config_dir().join("config.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(&path)?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// Every setting as `(key, value)` pairs, sorted by key.
+    pub fn entries(&self) -> Result<Vec<(String, Value)>> {
+        match serde_json::to_value(self)? {
+            Value::Object(map) => Ok(map.into_iter().collect()),
+            _ => unreachable!("Config serializes to an object"),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Value> {
+        self.entries()?
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .ok_or_else(|| anyhow!("Unknown config key '{}'", key))
+    }
+
+    /// Sets `key` from its command-line spelling. The value is read as JSON
+    /// when possible (`true`, `3`) and as a plain string otherwise.
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<()> {
+        let Value::Object(mut map): Value = serde_json::to_value(&*self)? else {
+            unreachable!("Config serializes to an object");
+        };
+        if !map.contains_key(key) {
+            bail!("Unknown config key '{}'", key);
+        }
+        let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+        map.insert(key.to_string(), value);
+        *self = serde_json::from_value(Value::Object(map))
+            .with_context(|| format!("Invalid value '{}' for {}", raw, key))?;
+        Ok(())
+    }
+}
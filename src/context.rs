@@ -1,26 +1,326 @@
 use anyhow::Result;
 use git2::Repository;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, SubmoduleScope, WorktreeScope};
+use crate::db::{TaskRepository, NO_PROJECT};
+use crate::filter::{Condition, Filter};
+use crate::git;
+use crate::models::Project;
+
+/// What `--project` and `--global` pointed a command at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The project of the current directory.
+    Current,
+    /// A project by name, ID or path.
+    Named(String),
+    /// Tasks and notes that belong to no project.
+    Global,
+}
+
+/// The project a command works on, as seen from the directory it ran in.
+pub struct Context {
+    /// Root of the checkout (or the plain directory) the command ran in.
+    /// Other clones and worktrees of a repository share its project, not
+    /// this directory.
+    pub root: PathBuf,
+    /// Where the project is registered if it is new: the main worktree, or
+    /// the superproject when submodules belong to it.
+    pub home: PathBuf,
+    pub identity: String,
+    /// The branch tasks are narrowed to with the `worktrees = branch` setting.
+    pub branch: Option<String>,
+    /// `None` until the first task or note is stored for this project.
+    pub project: Option<Project>,
+    /// Working on tasks and notes without a project.
+    pub global: bool,
+}
+
+impl Context {
+    /// The project filter for listing this context's tasks and notes.
+    pub fn scope(&self) -> String {
+        match &self.project {
+            _ if self.global => NO_PROJECT.to_string(),
+            Some(project) => project.path.clone(),
+            None => self.home.to_string_lossy().to_string(),
+        }
+    }
+
+    /// What a task or note stored in this context carries as `project_path`,
+    /// registering the project first if it is new.
+    pub fn project_path(&mut self, repo: &TaskRepository) -> Result<Option<String>> {
+        if self.global {
+            return Ok(None);
+        }
+        Ok(Some(self.ensure_project(repo)?.path.clone()))
+    }
+
+    /// How `flow add` reports where a task went.
+    pub fn describe(&self) -> String {
+        match &self.project {
+            _ if self.global => "(global)".to_string(),
+            Some(project) => format!("{} ({})", project.name, project.path),
+            None => self.home.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Narrows task listings to the current branch, if branches are scoped.
+    pub fn branch_filter(&self) -> Option<Filter> {
+        self.branch.clone().map(|b| Filter::Cond(Condition::Branch(b)))
+    }
+
+    /// The branch a task added here is scoped to. Tasks added on the
+    /// default branch belong to every branch.
+    pub fn scope_branch(&self) -> Option<String> {
+        let branch = self.branch.as_ref()?;
+        (git::default_branch(&self.root).as_ref() != Some(branch)).then(|| branch.clone())
+    }
+
+    /// The project, registered first if it is new. For commands that are
+    /// about to store something in it.
+    pub fn ensure_project(&mut self, repo: &TaskRepository) -> Result<&Project> {
+        if self.project.is_none() {
+            self.project = Some(repo.add_project(&self.identity, &self.home.to_string_lossy())?);
+        }
+        Ok(self.project.as_ref().expect("project was just set"))
+    }
+}
 
 pub struct ContextManager;
 
 impl ContextManager {
-    pub fn get_context() -> Result<PathBuf> {
+    pub fn get_context(repo: &TaskRepository, config: &Config) -> Result<Context> {
         let current_dir = env::current_dir()?;
-        
-        // Try to find a git repository starting from current_dir
-        match Repository::discover(&current_dir) {
-            Ok(repo) => {
-                // If found, return the workdir (root of the repo)
-                // workdir() returns Option<&Path>, usually Some for non-bare repos
-                Ok(repo.workdir().unwrap_or(&current_dir).to_path_buf())
-            }
-            Err(_) => {
-                // If not a git repo, just return the current directory
-                // Or we could return a "global" context path if we wanted
-                Ok(current_dir)
+        Self::resolve(repo, config, &current_dir)
+    }
+
+    /// The context every scoped command goes through: the current project,
+    /// the one named by `--project`, or no project at all with `--global`.
+    pub fn select(repo: &TaskRepository, config: &Config, target: &Target) -> Result<Context> {
+        match target {
+            Target::Current => Self::get_context(repo, config),
+            Target::Global => Ok(Context { global: true, branch: None, ..Self::locate(config, &env::current_dir()?) }),
+            Target::Named(key) => match repo.find_project(key) {
+                Ok(project) => {
+                    let dir = PathBuf::from(&project.path);
+                    Ok(Context {
+                        root: if dir.is_dir() { dir.clone() } else { env::current_dir()? },
+                        home: dir,
+                        identity: project.identity.clone(),
+                        branch: None,
+                        project: Some(project),
+                        global: false,
+                    })
+                }
+                // A directory flow hasn't seen yet becomes a project once
+                // something is stored in it.
+                Err(err) => match Path::new(key).canonicalize() {
+                    Ok(dir) if dir.is_dir() => Self::resolve(repo, config, &dir),
+                    _ => Err(err),
+                },
+            },
+        }
+    }
+
+    /// Resolves the project context for an arbitrary directory.
+    pub fn resolve(repo: &TaskRepository, config: &Config, dir: &Path) -> Result<Context> {
+        let mut context = Self::locate(config, dir);
+        context.project = repo.lookup_project(&context.identity, &context.home.to_string_lossy())?;
+        Ok(context)
+    }
+
+    /// Works out which project `dir` belongs to without consulting the
+    /// database. A repository is known by its origin remote or first commit;
+    /// anything else by its path.
+    pub fn locate(config: &Config, dir: &Path) -> Context {
+        let Ok(checkout) = Repository::discover(dir) else {
+            return Context {
+                root: dir.to_path_buf(),
+                home: dir.to_path_buf(),
+                identity: path_identity(dir),
+                branch: None,
+                project: None,
+                global: false,
+            };
+        };
+        // workdir() is None only for bare repositories
+        let root = checkout.workdir().unwrap_or(dir).to_path_buf();
+        let branch = match config.worktrees {
+            WorktreeScope::Branch => git::current_branch(&root),
+            _ => None,
+        };
+
+        let mut owner = checkout;
+        if config.submodules == SubmoduleScope::Superproject {
+            while let Some(parent) = git::superproject(&owner) {
+                owner = parent;
             }
         }
+        let workdir = owner.workdir().unwrap_or(&root).to_path_buf();
+        let (home, worktree) = match config.worktrees {
+            WorktreeScope::Separate => (workdir, git::worktree_name(&owner)),
+            _ => (git::main_worktree(&owner).unwrap_or(workdir), None),
+        };
+        let identity = match git::repository_identity(&owner) {
+            Some(identity) => match worktree {
+                Some(name) => format!("{}#{}", identity, name),
+                None => identity,
+            },
+            None => path_identity(&home),
+        };
+        Context { root, home, identity, branch, project: None, global: false }
+    }
+}
+
+// Directories outside of git (and repositories without commits or remotes)
+// have nothing better to go by than where they are.
+fn path_identity(dir: &Path) -> String {
+    format!("path:{}", dir.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A repository with an origin remote, a linked worktree `feature` and
+    /// a submodule `lib`, removed again on drop.
+    struct Checkouts {
+        base: PathBuf,
+    }
+
+    impl Checkouts {
+        fn new(name: &str) -> Checkouts {
+            let base = env::temp_dir().join(format!("flow-context-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&base);
+            fs::create_dir_all(&base).unwrap();
+            let base = base.canonicalize().unwrap();
+
+            let upstream = Repository::init(base.join("upstream")).unwrap();
+            commit(&upstream);
+            let main = Repository::init(base.join("main")).unwrap();
+            commit(&main);
+            main.remote("origin", "https://example.com/Team/App.git").unwrap();
+            main.worktree("feature", &base.join("feature"), None).unwrap();
+            let mut lib = main.submodule(&base.join("upstream").to_string_lossy(), Path::new("lib"), true).unwrap();
+            lib.clone(None).unwrap();
+            lib.add_finalize().unwrap();
+            Checkouts { base }
+        }
+
+        fn locate(&self, dir: &str, worktrees: WorktreeScope, submodules: SubmoduleScope) -> Context {
+            let config = Config { worktrees, submodules, ..Config::default() };
+            ContextManager::locate(&config, &self.base.join(dir))
+        }
+    }
+
+    impl Drop for Checkouts {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.base);
+        }
+    }
+
+    fn commit(repo: &Repository) {
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+    }
+
+    fn home(context: &Context) -> PathBuf {
+        context.home.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn shared_worktrees_are_one_project() {
+        let checkouts = Checkouts::new("shared");
+        let main = checkouts.locate("main", WorktreeScope::Shared, SubmoduleScope::Own);
+        let feature = checkouts.locate("feature", WorktreeScope::Shared, SubmoduleScope::Own);
+        assert_eq!(main.identity, "example.com/Team/App");
+        assert_eq!(feature.identity, main.identity);
+        assert_eq!(home(&feature), checkouts.base.join("main"));
+        assert_eq!(feature.root.canonicalize().unwrap(), checkouts.base.join("feature"));
+        assert_eq!((main.branch, feature.branch), (None, None));
+    }
+
+    #[test]
+    fn branch_scoped_worktrees_share_a_project_but_not_branches() {
+        let checkouts = Checkouts::new("branch");
+        let main = checkouts.locate("main", WorktreeScope::Branch, SubmoduleScope::Own);
+        let feature = checkouts.locate("feature", WorktreeScope::Branch, SubmoduleScope::Own);
+        assert_eq!(feature.identity, main.identity);
+        assert_eq!(feature.branch.as_deref(), Some("feature"));
+        assert_eq!(feature.scope_branch().as_deref(), Some("feature"));
+        // Tasks added on the default branch belong to every branch.
+        assert!(main.branch.is_some());
+        assert_eq!(main.scope_branch(), None);
+    }
+
+    #[test]
+    fn separate_worktrees_are_projects_of_their_own() {
+        let checkouts = Checkouts::new("separate");
+        let main = checkouts.locate("main", WorktreeScope::Separate, SubmoduleScope::Own);
+        let feature = checkouts.locate("feature", WorktreeScope::Separate, SubmoduleScope::Own);
+        assert_eq!(main.identity, "example.com/Team/App");
+        assert_eq!(feature.identity, "example.com/Team/App#feature");
+        assert_eq!(home(&feature), checkouts.base.join("feature"));
+    }
+
+    #[test]
+    fn submodules_follow_their_setting() {
+        let checkouts = Checkouts::new("submodules");
+        let own = checkouts.locate("main/lib", WorktreeScope::Shared, SubmoduleScope::Own);
+        assert!(own.identity.starts_with("commit:"), "{}", own.identity);
+        assert_eq!(home(&own), checkouts.base.join("main/lib"));
+
+        let joined = checkouts.locate("main/lib", WorktreeScope::Shared, SubmoduleScope::Superproject);
+        assert_eq!(joined.identity, "example.com/Team/App");
+        assert_eq!(home(&joined), checkouts.base.join("main"));
+        assert_eq!(joined.root.canonicalize().unwrap(), checkouts.base.join("main/lib"));
+    }
+
+    #[test]
+    fn plain_directories_are_known_by_path() {
+        let checkouts = Checkouts::new("plain");
+        fs::create_dir_all(checkouts.base.join("notes")).unwrap();
+        let context = checkouts.locate("notes", WorktreeScope::Shared, SubmoduleScope::Own);
+        assert_eq!(context.identity, format!("path:{}", checkouts.base.join("notes").display()));
+    }
+
+    #[test]
+    fn every_worktree_resolves_to_the_stored_project() {
+        let checkouts = Checkouts::new("resolve");
+        let repo = TaskRepository::in_memory().unwrap();
+        let config = Config::default();
+        let mut main = ContextManager::resolve(&repo, &config, &checkouts.base.join("main")).unwrap();
+        assert!(main.project.is_none());
+        let id = main.ensure_project(&repo).unwrap().id;
+
+        let feature = ContextManager::resolve(&repo, &config, &checkouts.base.join("feature")).unwrap();
+        assert_eq!(feature.project.and_then(|p| p.id), id);
+        let global = Context { global: true, ..ContextManager::resolve(&repo, &config, &checkouts.base.join("main")).unwrap() };
+        assert_eq!(global.scope(), NO_PROJECT);
+    }
+
+    #[test]
+    fn named_targets_find_projects_and_new_directories() {
+        let checkouts = Checkouts::new("select");
+        let repo = TaskRepository::in_memory().unwrap();
+        let config = Config::default();
+        let main = checkouts.base.join("main");
+        let project = repo.add_project("example.com/Team/App", &main.to_string_lossy()).unwrap();
+
+        for key in [project.name.clone(), project.id.unwrap().to_string(), main.to_string_lossy().to_string()] {
+            let context = ContextManager::select(&repo, &config, &Target::Named(key.clone())).unwrap();
+            assert_eq!(context.project.and_then(|p| p.id), project.id, "{}", key);
+        }
+
+        // A directory flow hasn't seen is resolved like the current one.
+        let upstream = checkouts.base.join("upstream");
+        let context = ContextManager::select(&repo, &config, &Target::Named(upstream.to_string_lossy().to_string())).unwrap();
+        assert!(context.project.is_none());
+        assert!(context.identity.starts_with("commit:"));
+        assert!(ContextManager::select(&repo, &config, &Target::Named("nope".to_string())).is_err());
     }
 }
@@ -0,0 +1,133 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+use chrono_english::{parse_date_string, Dialect};
+
+const KEYWORDS: &[&str] = &[
+    "today", "tomorrow", "yesterday", "now", "next", "last", "in",
+];
+
+const WEEKDAYS: &[&str] = &[
+    "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday",
+    "mon", "tue", "tues", "wed", "thu", "thur", "thurs", "fri", "sat", "sun",
+];
+
+const MONTHS: &[&str] = &[
+    "january", "february", "march", "april", "may", "june", "july", "august",
+    "september", "october", "november", "december",
+    "jan", "feb", "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov", "dec",
+];
+
+const UNITS: &[&str] = &[
+    "min", "mins", "minute", "minutes", "h", "hour", "hours", "d", "day", "days",
+    "w", "week", "weeks", "month", "months", "year", "years",
+];
+
+/// Parses a natural language due date such as "tomorrow", "next friday 9am"
+/// or "in 2 days".
+pub fn parse_due(phrase: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let normalized = normalize(phrase);
+    parse_date_string(&normalized, now, Dialect::Us)
+        .map_err(|e| anyhow!("Could not parse date \"{}\": {}", phrase, e))
+}
+
+/// Midnight at the start of the calendar day containing `date`.
+pub fn start_of_day(date: DateTime<Local>) -> DateTime<Local> {
+    Local
+        .from_local_datetime(&date.date_naive().and_time(NaiveTime::MIN))
+        .earliest()
+        .unwrap_or(date)
+}
+
+// chrono_english has no notion of "in 2 days" or "friday at 5pm"; both read
+// fine once the filler words are dropped.
+fn normalize(phrase: &str) -> String {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    let words = match words.first() {
+        Some(w) if w.eq_ignore_ascii_case("in") => &words[1..],
+        _ => &words[..],
+    };
+    words
+        .iter()
+        .filter(|w| !w.eq_ignore_ascii_case("at"))
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `words` read as a date phrase rather than ordinary title words.
+/// Every word must belong to the date vocabulary, and something must pin the
+/// phrase to a date: a weekday, month, time, or a number followed by a unit.
+/// "may" is a month only next to a day ("may 3", "3 may"), since a title
+/// can end in "we may".
+pub fn looks_like_date(words: &[&str]) -> bool {
+    if words.is_empty() || !words.iter().all(|w| is_date_word(w)) {
+        return false;
+    }
+    let counted_offset = words.windows(2).any(|pair| {
+        pair[0].chars().all(|c| c.is_ascii_digit()) && UNITS.contains(&pair[1].to_lowercase().as_str())
+    });
+    let is_day = |w: &str| w.parse::<u32>().is_ok_and(|day| (1..=31).contains(&day));
+    let day_in_may = words.windows(2).any(|pair| {
+        (pair[0].eq_ignore_ascii_case("may") && is_day(pair[1])) || (is_day(pair[0]) && pair[1].eq_ignore_ascii_case("may"))
+    });
+    counted_offset || day_in_may || words.iter().any(|w| is_anchor_word(w))
+}
+
+fn is_date_word(word: &str) -> bool {
+    let w = word.to_lowercase();
+    KEYWORDS.contains(&w.as_str())
+        || w == "at"
+        || MONTHS.contains(&w.as_str())
+        || is_anchor_word(&w)
+        || UNITS.contains(&w.as_str())
+        || w.chars().all(|c| c.is_ascii_digit())
+}
+
+// Bare numbers and units only make sense next to one another; these words
+// pin a phrase to a date on their own. "now" and "may" are too common at
+// the end of a title to count.
+fn is_anchor_word(word: &str) -> bool {
+    let w = word.to_lowercase();
+    if matches!(w.as_str(), "in" | "now" | "may") {
+        return false;
+    }
+    if KEYWORDS.contains(&w.as_str()) {
+        return true;
+    }
+    if WEEKDAYS.contains(&w.as_str()) || MONTHS.contains(&w.as_str()) {
+        return true;
+    }
+    is_date_shape(&w) || is_time_shape(&w) || is_offset_shape(&w)
+}
+
+// 2024-05-01, 05/01
+fn is_date_shape(w: &str) -> bool {
+    w.contains(['-', '/'])
+        && w.chars().any(|c| c.is_ascii_digit())
+        && w.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '/')
+}
+
+// 5pm, 9:30am, 17:00
+fn is_time_shape(w: &str) -> bool {
+    let body = w
+        .strip_suffix("am")
+        .or_else(|| w.strip_suffix("pm"))
+        .unwrap_or(w);
+    let has_meridiem = body.len() != w.len();
+    let (hours, minutes) = match body.split_once(':') {
+        Some((h, m)) => (h, Some(m)),
+        None => (body, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.len() <= 2 && s.chars().all(|c| c.is_ascii_digit());
+    match minutes {
+        Some(m) => digits(hours) && m.len() == 2 && digits(m),
+        None => has_meridiem && digits(hours),
+    }
+}
+
+// 2d, 1h, 30min, 2w
+fn is_offset_shape(w: &str) -> bool {
+    let split = w.find(|c: char| !c.is_ascii_digit()).unwrap_or(w.len());
+    let (num, unit) = w.split_at(split);
+    !num.is_empty() && !unit.is_empty() && UNITS.contains(&unit)
+}
@@ -1,144 +1,892 @@
-use crate::models::{Priority, Status, Task, Note};
-use anyhow::{Context, Result};
-use chrono::{DateTime, Local};
+use crate::filter::{next_day, CmpOp, Condition, DateCond, Filter};
+use crate::migrations::{self, Migration};
+use crate::sort::{SortField, SortKey};
+use crate::models::{
+    normalize_tag, CodeComment, Completion, Note, Priority, Project, ScanSummary, SearchHit, Status, TagCount, Task, TaskUpdate,
+    TimeEntry, TimeGrouping, TimeTotal,
+};
+use crate::recur::Recurrence;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveTime};
 use directories::ProjectDirs;
-use rusqlite::{params, Connection};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::Path;
+
+// Tags come from the task_tags join table rather than the legacy JSON column,
+// and the project's path from its row rather than the legacy path column.
+const TASK_COLUMNS: &str = "id, title, description, status, priority, due_date,
+    (SELECT path FROM projects WHERE id = tasks.project_id), created_at,
+    (SELECT json_group_array(tag) FROM task_tags WHERE task_id = tasks.id),
+    (SELECT json_group_array(d.depends_on) FROM task_dependencies d
+        JOIN tasks b ON b.id = d.depends_on
+        WHERE d.task_id = tasks.id AND b.status != 'Done'),
+    parent_id,
+    (SELECT COUNT(*) FROM tasks c WHERE c.parent_id = tasks.id),
+    (SELECT COUNT(*) FROM tasks c WHERE c.parent_id = tasks.id AND c.status = 'Done'),
+    recurrence, recur_from_completion, branch, completed_at, scope_branch";
+
+/// Project filter matching tasks and notes that belong to no project.
+pub const NO_PROJECT: &str = "";
+
+const PROJECT_COLUMNS: &str = "id, name, identity, path, created_at,
+    (SELECT COUNT(*) FROM tasks WHERE project_id = projects.id),
+    (SELECT COUNT(*) FROM notes WHERE project_id = projects.id)";
+
+const NOTE_COLUMNS: &str = "id, title, content, (SELECT path FROM projects WHERE id = notes.project_id), created_at, tags";
+
+const BLOCKED_SQL: &str = "EXISTS (SELECT 1 FROM task_dependencies d
+    JOIN tasks b ON b.id = d.depends_on
+    WHERE d.task_id = tasks.id AND b.status != 'Done') AND tasks.status != 'Done'";
 
 pub struct TaskRepository {
     conn: Connection,
+    auto_complete_parents: bool,
 }
 
 impl TaskRepository {
+    /// Opens the database and brings its schema up to date.
     pub fn init() -> Result<Self> {
+        let mut repo = Self::open()?;
+        migrations::migrate(&mut repo.conn)?;
+        Ok(repo)
+    }
+
+    /// Opens the database without running migrations.
+    pub fn open() -> Result<Self> {
         let project_dirs = ProjectDirs::from("com", "flow", "flow")
             .context("Could not determine project directories")?;
         let data_dir = project_dirs.data_dir();
         fs::create_dir_all(data_dir)?;
 
         let db_path = data_dir.join("flow.db");
-        let conn = Connection::open(db_path)?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY,
-                title TEXT NOT NULL,
-                description TEXT,
-                status TEXT NOT NULL,
-                priority TEXT NOT NULL,
-                due_date TEXT,
-                project_path TEXT,
-                created_at TEXT NOT NULL,
-                tags TEXT
-            )",
-            [],
-        )?;
+        Self::with_connection(Connection::open(db_path)?)
+    }
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS notes (
-                id INTEGER PRIMARY KEY,
-                title TEXT NOT NULL,
-                content TEXT,
-                project_path TEXT,
-                created_at TEXT NOT NULL,
-                tags TEXT
-            )",
-            [],
-        )?;
+    /// A migrated database that only lives as long as the repository.
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self> {
+        let mut repo = Self::with_connection(Connection::open_in_memory()?)?;
+        migrations::migrate(&mut repo.conn)?;
+        Ok(repo)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            auto_complete_parents: false,
+        })
+    }
+
+    /// Whether finishing the last open subtask also marks its parent done.
+    pub fn set_auto_complete_parents(&mut self, enabled: bool) {
+        self.auto_complete_parents = enabled;
+    }
+
+    pub fn schema_version(&self) -> Result<u32> {
+        migrations::current_version(&self.conn)
+    }
+
+    pub fn pending_migrations(&self) -> Result<Vec<&'static Migration>> {
+        migrations::pending(&self.conn)
+    }
+
+    pub fn migrate(&mut self, dry_run: bool) -> Result<Vec<&'static Migration>> {
+        if dry_run {
+            migrations::dry_run(&mut self.conn)
+        } else {
+            migrations::migrate(&mut self.conn)
+        }
     }
 
     pub fn add_task(&self, task: &Task) -> Result<i64> {
-        let tags_str = serde_json::to_string(&task.tags)?;
-        self.conn.execute(
-            "INSERT INTO tasks (title, description, status, priority, due_date, project_path, created_at, tags)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                task.title,
-                task.description,
-                task.status.to_string(),
-                task.priority.to_string(),
-                task.due_date.map(|d| d.to_rfc3339()),
-                task.project_path,
-                task.created_at.to_rfc3339(),
-                tags_str
-            ],
-        )?;
-        Ok(self.conn.last_insert_rowid())
+        let tx = self.conn.unchecked_transaction()?;
+        let id = insert_task(&tx, task)?;
+        tx.commit()?;
+        Ok(id)
     }
 
-    pub fn get_tasks(&self, project_filter: Option<&str>) -> Result<Vec<Task>> {
+    /// Lists tasks, optionally scoped to a project, narrowed by a filter
+    /// expression and ordered by `sort` (insertion order when empty).
+    pub fn get_tasks(&self, project_filter: Option<&str>, filter: Option<&Filter>, sort: &[SortKey]) -> Result<Vec<Task>> {
+        let mut values = vec![project_filter.map_or(Value::Null, |p| Value::Text(p.to_string()))];
+        let mut sql = format!(
+            "SELECT {} FROM tasks WHERE {}",
+            TASK_COLUMNS,
+            in_project("tasks", "?1")
+        );
+        if let Some(filter) = filter {
+            sql.push_str(" AND ");
+            sql.push_str(&filter_sql(filter, &mut values));
+        }
+        sql.push_str(&order_sql(sort));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let task_iter = stmt.query_map(params_from_iter(values), task_from_row)?;
+
+        let mut tasks = Vec::new();
+        for task in task_iter {
+            tasks.push(task?);
+        }
+        Ok(tasks)
+    }
+
+    pub fn get_task(&self, id: i64) -> Result<Task> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE id = ?1",
+            TASK_COLUMNS
+        ))?;
+
+        let task = stmt
+            .query_row(params![id], task_from_row)
+            .optional()?
+            .with_context(|| format!("Task {} not found", id))?;
+
+        Ok(task)
+    }
+
+    /// Applies every field of `update` in a single UPDATE statement, stopping
+    /// the task's timer if it leaves In Progress. Returns what else changed
+    /// if the update completed the task.
+    pub fn update_task(&self, id: i64, update: &TaskUpdate) -> Result<Completion> {
+        let mut sets: Vec<&str> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+
+        if let Some(title) = &update.title {
+            sets.push("title = ?");
+            values.push(Value::Text(title.clone()));
+        }
+        if let Some(description) = &update.description {
+            sets.push("description = ?");
+            values.push(description.clone().map_or(Value::Null, Value::Text));
+        }
+        if let Some(status) = &update.status {
+            sets.push("status = ?");
+            values.push(Value::Text(status.to_string()));
+            if *status != Status::Done {
+                sets.push("completed_at = NULL");
+            }
+        }
+        if let Some(priority) = &update.priority {
+            sets.push("priority = ?");
+            values.push(Value::Text(priority.to_string()));
+        }
+        if let Some(due_date) = &update.due_date {
+            sets.push("due_date = ?");
+            values.push(due_date.map_or(Value::Null, |d| Value::Text(d.to_rfc3339())));
+        }
+        if let Some(recurrence) = &update.recurrence {
+            sets.push("recurrence = ?");
+            values.push(recurrence.clone().map_or(Value::Null, Value::Text));
+        }
+        if let Some(from_completion) = update.recur_from_completion {
+            sets.push("recur_from_completion = ?");
+            values.push(Value::Integer(from_completion as i64));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let was_done = task_is_done(&tx, id)?;
+        if let Some(Some(parent_id)) = update.parent_id {
+            check_parent(&tx, id, parent_id)?;
+        }
+        if let Some(parent_id) = &update.parent_id {
+            sets.push("parent_id = ?");
+            values.push(parent_id.map_or(Value::Null, Value::Integer));
+        }
+        if !sets.is_empty() {
+            values.push(Value::Integer(id));
+            let sql = format!("UPDATE tasks SET {} WHERE id = ?", sets.join(", "));
+            tx.execute(&sql, params_from_iter(values))?;
+        }
+        if let Some(tags) = &update.tags {
+            set_task_tags(&tx, id, tags)?;
+        }
+        if update.status.as_ref().is_some_and(|s| *s != Status::InProgress) {
+            stop_task_timer(&tx, id, Local::now())?;
+        }
+        let completion = if update.status == Some(Status::Done) && !was_done {
+            self.finish(&tx, id)?
+        } else {
+            Completion::default()
+        };
+        tx.commit()?;
+        Ok(completion)
+    }
+
+    /// Every task tag with the number of tasks and projects using it.
+    pub fn get_tag_counts(&self) -> Result<Vec<TagCount>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, status, priority, due_date, project_path, created_at, tags 
-             FROM tasks 
-             WHERE ?1 IS NULL OR project_path = ?1"
+            "SELECT tt.tag, COUNT(*), COUNT(DISTINCT t.project_id)
+             FROM task_tags tt JOIN tasks t ON t.id = tt.task_id
+             GROUP BY tt.tag
+             ORDER BY COUNT(*) DESC, tt.tag",
         )?;
 
-        let task_iter = stmt.query_map(params![project_filter], |row| {
-            let status_str: String = row.get(3)?;
-            let priority_str: String = row.get(4)?;
-            let due_date_str: Option<String> = row.get(5)?;
-            let created_at_str: String = row.get(7)?;
-            let tags_str: String = row.get(8)?;
-
-            Ok(Task {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                status: Status::from(status_str),
-                priority: Priority::from(priority_str),
-                due_date: due_date_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Local))),
-                project_path: row.get(6)?,
-                created_at: DateTime::parse_from_rfc3339(&created_at_str)
-                    .map(|dt| dt.with_timezone(&Local))
-                    .unwrap_or_else(|_| Local::now()),
-                tags: serde_json::from_str(&tags_str).unwrap_or_default(),
+        let iter = stmt.query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                tasks: row.get(1)?,
+                projects: row.get(2)?,
             })
         })?;
 
+        let mut counts = Vec::new();
+        for count in iter {
+            counts.push(count?);
+        }
+        Ok(counts)
+    }
+
+    /// Folds every tag in `sources` into `target`, returning the number of
+    /// tasks that were retagged. Tasks that already carry `target` simply lose
+    /// the source tag.
+    pub fn merge_tags(&self, sources: &[String], target: &str) -> Result<usize> {
+        let target = normalize_tag(target).context("Target tag is empty")?;
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changed = 0;
+        for source in sources.iter().filter_map(|s| normalize_tag(s)) {
+            if source == target {
+                continue;
+            }
+            changed += tx.execute(
+                "INSERT OR IGNORE INTO task_tags (task_id, tag)
+                 SELECT task_id, ?2 FROM task_tags WHERE tag = ?1",
+                params![source, target],
+            )?;
+            tx.execute("DELETE FROM task_tags WHERE tag = ?1", params![source])?;
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    pub fn tag_exists(&self, tag: &str) -> Result<bool> {
+        let Some(tag) = normalize_tag(tag) else {
+            return Ok(false);
+        };
+        let exists = self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM task_tags WHERE tag = ?1)",
+            params![tag],
+            |row| row.get(0),
+        )?;
+        Ok(exists)
+    }
+
+    /// Marks a task done, completing finished parents and scheduling the
+    /// next instance of recurring tasks. Completing a task that is already
+    /// done changes nothing.
+    pub fn complete_task(&self, id: i64) -> Result<Completion> {
+        let tx = self.conn.unchecked_transaction()?;
+        if task_is_done(&tx, id)? {
+            return Ok(Completion::default());
+        }
+        tx.execute(
+            "UPDATE tasks SET status = ?1 WHERE id = ?2",
+            params![Status::Done.to_string(), id],
+        )?;
+        let completion = self.finish(&tx, id)?;
+        tx.commit()?;
+        Ok(completion)
+    }
+
+    /// Moves a task to `status`. Marking it done works like `complete_task`;
+    /// any other status reopens it, and moving it out of In Progress stops
+    /// its timer.
+    pub fn set_status(&self, id: i64, status: Status) -> Result<Completion> {
+        if status == Status::Done {
+            return self.complete_task(id);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let changed = tx.execute(
+            "UPDATE tasks SET status = ?1, completed_at = NULL WHERE id = ?2",
+            params![status.to_string(), id],
+        )?;
+        if changed == 0 {
+            bail!("Task {} not found", id);
+        }
+        if status != Status::InProgress {
+            stop_task_timer(&tx, id, Local::now())?;
+        }
+        tx.commit()?;
+        Ok(Completion::default())
+    }
+
+    // Follow-up work for a task that has just been marked done.
+    fn finish(&self, conn: &Connection, id: i64) -> Result<Completion> {
+        let now = Local::now();
+        let mut completion = Completion {
+            parents: self.complete_finished_parents(conn, id)?,
+            spawned: Vec::new(),
+        };
+        for done in std::iter::once(id).chain(completion.parents.clone()) {
+            conn.execute(
+                "UPDATE tasks SET completed_at = ?2 WHERE id = ?1",
+                params![done, now.to_rfc3339()],
+            )?;
+            stop_task_timer(conn, done, now)?;
+            if let Some(next) = spawn_next_instance(conn, done, now)? {
+                completion.spawned.push((done, next));
+            }
+        }
+        Ok(completion)
+    }
+
+    /// Starts a timer on a task and moves it to In Progress. Only one timer
+    /// runs at a time: one running on another task is stopped and returned.
+    pub fn start_timer(&self, task_id: i64, now: DateTime<Local>) -> Result<Option<TimeEntry>> {
+        let tx = self.conn.unchecked_transaction()?;
+        if task_is_done(&tx, task_id)? {
+            bail!("Task {} is already done", task_id);
+        }
+        if running_entry(&tx)?.is_some_and(|e| e.task_id == task_id) {
+            bail!("Already tracking time on task {}", task_id);
+        }
+        let stopped = stop_running_entry(&tx, now)?;
+        tx.execute(
+            "INSERT INTO time_entries (task_id, started_at) VALUES (?1, ?2)",
+            params![task_id, now.to_rfc3339()],
+        )?;
+        tx.execute(
+            "UPDATE tasks SET status = ?1 WHERE id = ?2",
+            params![Status::InProgress.to_string(), task_id],
+        )?;
+        tx.commit()?;
+        Ok(stopped)
+    }
+
+    /// Stops the running timer, if any. The task stays In Progress.
+    pub fn stop_timer(&self, now: DateTime<Local>) -> Result<Option<TimeEntry>> {
+        stop_running_entry(&self.conn, now)
+    }
+
+    pub fn running_timer(&self) -> Result<Option<TimeEntry>> {
+        running_entry(&self.conn)
+    }
+
+    /// Tracked time between `since` and `until`, most time first. Entries
+    /// straddling either bound only count the part inside the range, and a
+    /// running timer counts up to `now`. With `TimeGrouping::Tag` a task's
+    /// time is counted once for each of its tags.
+    pub fn time_report(
+        &self,
+        project_filter: Option<&str>,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+        now: DateTime<Local>,
+        by: TimeGrouping,
+    ) -> Result<Vec<TimeTotal>> {
+        let (group, join) = match by {
+            TimeGrouping::Project => (
+                "COALESCE((SELECT name FROM projects WHERE id = t.project_id), '(none)')",
+                "",
+            ),
+            TimeGrouping::Task => ("'#' || t.id || ' ' || t.title", ""),
+            TimeGrouping::Tag => (
+                "COALESCE('#' || tt.tag, '(untagged)')",
+                "LEFT JOIN task_tags tt ON tt.task_id = t.id",
+            ),
+        };
+        let scope = in_project("t", "?1");
+        let sql = format!(
+            "SELECT grp, CAST(round(SUM(span) * 86400) AS INTEGER) AS seconds FROM (
+                SELECT {group} AS grp,
+                       min(julianday(COALESCE(e.ended_at, ?4)), julianday(?3))
+                         - max(julianday(e.started_at), julianday(?2)) AS span
+                FROM time_entries e
+                JOIN tasks t ON t.id = e.task_id
+                {join}
+                WHERE {scope}
+             )
+             WHERE span > 0
+             GROUP BY grp
+             ORDER BY seconds DESC, grp"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let iter = stmt.query_map(
+            params![project_filter, since.to_rfc3339(), until.to_rfc3339(), now.to_rfc3339()],
+            |row| {
+                let seconds: i64 = row.get(1)?;
+                Ok(TimeTotal {
+                    group: row.get(0)?,
+                    seconds,
+                    hours: (seconds as f64 / 36.0).round() / 100.0,
+                })
+            },
+        )?;
+
+        let mut totals = Vec::new();
+        for total in iter {
+            totals.push(total?);
+        }
+        Ok(totals)
+    }
+
+    pub fn set_task_branch(&self, id: i64, branch: Option<&str>) -> Result<()> {
+        self.conn.execute("UPDATE tasks SET branch = ?1 WHERE id = ?2", params![branch, id])?;
+        Ok(())
+    }
+
+    /// The task a branch was created for within a project, preferring open
+    /// tasks and then the most recent one.
+    pub fn task_for_branch(&self, branch: &str, project_filter: Option<&str>) -> Result<Option<Task>> {
+        let task = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tasks
+                     WHERE branch = ?1 AND {}
+                     ORDER BY status = 'Done', id DESC
+                     LIMIT 1",
+                    TASK_COLUMNS,
+                    in_project("tasks", "?2")
+                ),
+                params![branch, project_filter],
+                task_from_row,
+            )
+            .optional()?;
+        Ok(task)
+    }
+
+    /// Brings the tasks linked to code comments in the project at `path` in
+    /// line with the comments a fresh scan found: new comments get a task,
+    /// moved ones are relinked, and tasks whose comment is gone are
+    /// completed. A project not seen before is registered under `identity`.
+    /// With `dry_run` nothing is saved.
+    pub fn sync_code_comments(&self, path: &str, identity: &str, comments: &[CodeComment], dry_run: bool) -> Result<ScanSummary> {
+        struct Linked {
+            task_id: i64,
+            file: String,
+            line: i64,
+            done: bool,
+            resolved: bool,
+            description: Option<String>,
+        }
+
+        let now = Local::now();
+        let tx = self.conn.unchecked_transaction()?;
+        let project = match query_project(&tx, "path = ?1", params![path])? {
+            Some(project) => project,
+            None => insert_project(&tx, identity, path)?,
+        };
+        let mut linked: HashMap<String, Linked> = HashMap::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT c.fingerprint, c.task_id, c.file, c.line, t.status, c.resolved_at IS NOT NULL, t.description
+                 FROM code_comments c JOIN tasks t ON t.id = c.task_id
+                 WHERE t.project_id = ?1",
+            )?;
+            let rows = stmt.query_map(params![project.id], |row| {
+                let status: String = row.get(4)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    Linked {
+                        task_id: row.get(1)?,
+                        file: row.get(2)?,
+                        line: row.get(3)?,
+                        done: Status::from(status) == Status::Done,
+                        resolved: row.get(5)?,
+                        description: row.get(6)?,
+                    },
+                ))
+            })?;
+            for row in rows {
+                let (fingerprint, link) = row?;
+                linked.insert(fingerprint, link);
+            }
+        }
+
+        let mut summary = ScanSummary::default();
+        let mut seen = HashSet::new();
+        for comment in comments {
+            seen.insert(comment.fingerprint.as_str());
+            let location = format!("{}:{}", comment.file, comment.line);
+            let Some(link) = linked.get(&comment.fingerprint) else {
+                let task = Task {
+                    id: None,
+                    title: if comment.text.is_empty() {
+                        format!("{} in {}", comment.kind, comment.file)
+                    } else {
+                        comment.text.clone()
+                    },
+                    description: Some(location),
+                    status: Status::Todo,
+                    priority: if comment.kind == "FIXME" { Priority::High } else { Priority::Medium },
+                    due_date: None,
+                    project_path: Some(project.path.clone()),
+                    created_at: now,
+                    tags: vec![comment.kind.to_lowercase()],
+                    blocked_by: Vec::new(),
+                    parent_id: None,
+                    subtasks_total: 0,
+                    subtasks_done: 0,
+                    recurrence: None,
+                    recur_from_completion: false,
+                    branch: None,
+                    completed_at: None,
+                    scope_branch: None,
+                };
+                let id = insert_task(&tx, &task)?;
+                tx.execute(
+                    "INSERT INTO code_comments (task_id, fingerprint, kind, file, line) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![id, comment.fingerprint, comment.kind, comment.file, comment.line],
+                )?;
+                summary.created.push((id, comment.clone()));
+                continue;
+            };
+
+            let moved = link.file != comment.file || link.line != comment.line;
+            if moved {
+                tx.execute(
+                    "UPDATE code_comments SET file = ?1, line = ?2 WHERE task_id = ?3",
+                    params![comment.file, comment.line, link.task_id],
+                )?;
+                // Only touch the description while it is still the location
+                // the scan wrote.
+                if link.description.as_deref() == Some(format!("{}:{}", link.file, link.line).as_str()) {
+                    tx.execute(
+                        "UPDATE tasks SET description = ?1 WHERE id = ?2",
+                        params![location, link.task_id],
+                    )?;
+                }
+                summary.moved.push(link.task_id);
+            }
+            if link.resolved {
+                tx.execute(
+                    "UPDATE code_comments SET resolved_at = NULL WHERE task_id = ?1",
+                    params![link.task_id],
+                )?;
+                tx.execute(
+                    "UPDATE tasks SET status = ?1, completed_at = NULL WHERE id = ?2",
+                    params![Status::Todo.to_string(), link.task_id],
+                )?;
+                summary.reopened.push(link.task_id);
+            } else if !moved {
+                summary.unchanged += 1;
+            }
+        }
+
+        // Tasks that were finished by hand stay as they are.
+        for (fingerprint, link) in &linked {
+            if seen.contains(fingerprint.as_str()) || link.resolved || link.done {
+                continue;
+            }
+            tx.execute(
+                "UPDATE tasks SET status = ?1 WHERE id = ?2",
+                params![Status::Done.to_string(), link.task_id],
+            )?;
+            tx.execute(
+                "UPDATE code_comments SET resolved_at = ?1 WHERE task_id = ?2",
+                params![now.to_rfc3339(), link.task_id],
+            )?;
+            self.finish(&tx, link.task_id)?;
+            summary.closed.push(link.task_id);
+        }
+        summary.closed.sort();
+
+        if !dry_run {
+            tx.commit()?;
+        }
+        Ok(summary)
+    }
+
+    /// The project known by `identity`, or else the one registered at
+    /// `path`, which then takes on that identity (its repository gained a
+    /// remote or a first commit). A project whose directory has disappeared
+    /// follows its repository to `path`. `None` for a project not seen before.
+    pub fn lookup_project(&self, identity: &str, path: &str) -> Result<Option<Project>> {
+        let tx = self.conn.unchecked_transaction()?;
+        if let Some(project) = query_project(&tx, "identity = ?1", params![identity])? {
+            let moved = project.path != path
+                && !Path::new(&project.path).exists()
+                && query_project(&tx, "path = ?1", params![path])?.is_none();
+            if !moved {
+                return Ok(Some(project));
+            }
+            relocate_project(&tx, &project, path)?;
+            tx.commit()?;
+            return Ok(Some(Project { path: path.to_string(), ..project }));
+        }
+
+        let Some(project) = query_project(&tx, "path = ?1", params![path])? else {
+            return Ok(None);
+        };
+        tx.execute(
+            "UPDATE projects SET identity = ?2 WHERE id = ?1",
+            params![project.id, identity],
+        )?;
+        tx.commit()?;
+        Ok(Some(Project { identity: identity.to_string(), ..project }))
+    }
+
+    /// Registers a new project, naming it after the last part of its path.
+    pub fn add_project(&self, identity: &str, path: &str) -> Result<Project> {
+        insert_project(&self.conn, identity, path)
+    }
+
+    pub fn get_projects(&self) -> Result<Vec<Project>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM projects ORDER BY name COLLATE NOCASE", PROJECT_COLUMNS))?;
+        let iter = stmt.query_map([], project_from_row)?;
+
+        let mut projects = Vec::new();
+        for project in iter {
+            projects.push(project?);
+        }
+        Ok(projects)
+    }
+
+    pub fn get_project(&self, id: i64) -> Result<Project> {
+        query_project(&self.conn, "id = ?1", params![id])?.with_context(|| format!("Project {} not found", id))
+    }
+
+    /// A project by ID, name or path.
+    pub fn find_project(&self, key: &str) -> Result<Project> {
+        if let Ok(id) = key.parse::<i64>() {
+            if let Some(project) = query_project(&self.conn, "id = ?1", params![id])? {
+                return Ok(project);
+            }
+        }
+        if let Some(project) = query_project(&self.conn, "name = ?1", params![key])? {
+            return Ok(project);
+        }
+        let path = fs::canonicalize(key).map_or(key.to_string(), |p| p.to_string_lossy().to_string());
+        query_project(&self.conn, "rtrim(path, '/') = rtrim(?1, '/')", params![path])?
+            .with_context(|| format!("No project named '{}' (see `flow project ls`)", key))
+    }
+
+    pub fn rename_project(&self, id: i64, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.parse::<i64>().is_ok() {
+            bail!("'{}' is not a valid project name; names can't be empty or a number", name);
+        }
+        if query_project(&self.conn, "name = ?1 AND id != ?2", params![name, id])?.is_some() {
+            bail!("A project named '{}' already exists", name);
+        }
+        self.conn.execute("UPDATE projects SET name = ?2 WHERE id = ?1", params![id, name])?;
+        Ok(())
+    }
+
+    /// Points a project at a new directory, taking its tasks and notes along.
+    pub fn move_project(&self, id: i64, path: &str) -> Result<()> {
+        let project = self.get_project(id)?;
+        if let Some(other) = query_project(&self.conn, "path = ?1 AND id != ?2", params![path, id])? {
+            bail!(
+                "{} already belongs to project '{}'; use `flow project merge {} {}` to combine them",
+                path,
+                other.name,
+                project.name,
+                other.name
+            );
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        relocate_project(&tx, &project, path)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Moves every task and note of project `from` into `into` and removes
+    /// `from`. Returns the number of tasks and notes moved.
+    pub fn merge_projects(&self, from: i64, into: i64) -> Result<(usize, usize)> {
+        if from == into {
+            bail!("Cannot merge a project into itself");
+        }
+        self.get_project(from)?;
+        self.get_project(into)?;
+        let tx = self.conn.unchecked_transaction()?;
+        let tasks = tx.execute("UPDATE tasks SET project_id = ?2 WHERE project_id = ?1", params![from, into])?;
+        let notes = tx.execute("UPDATE notes SET project_id = ?2 WHERE project_id = ?1", params![from, into])?;
+        tx.execute("DELETE FROM projects WHERE id = ?1", params![from])?;
+        tx.commit()?;
+        Ok((tasks, notes))
+    }
+
+    /// Open tasks that repeat, soonest due first.
+    pub fn get_recurring_tasks(&self, project_filter: Option<&str>) -> Result<Vec<Task>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks
+             WHERE recurrence IS NOT NULL AND status != 'Done' AND {}
+             ORDER BY due_date IS NULL, julianday(due_date), id",
+            TASK_COLUMNS,
+            in_project("tasks", "?1")
+        ))?;
+        let iter = stmt.query_map(params![project_filter], task_from_row)?;
+
         let mut tasks = Vec::new();
-        for task in task_iter {
+        for task in iter {
             tasks.push(task?);
         }
         Ok(tasks)
     }
 
-    pub fn complete_task(&self, id: i64) -> Result<()> {
-        self.conn.execute(
-            "UPDATE tasks SET status = ?1 WHERE id = ?2",
-            params![Status::Done.to_string(), id],
+    // Walks up from a task that was just completed, finishing each ancestor
+    // whose subtasks are now all done.
+    fn complete_finished_parents(&self, conn: &Connection, id: i64) -> Result<Vec<i64>> {
+        let mut completed = Vec::new();
+        if !self.auto_complete_parents {
+            return Ok(completed);
+        }
+        let mut current = id;
+        loop {
+            let parent: Option<i64> = conn
+                .query_row("SELECT parent_id FROM tasks WHERE id = ?1", params![current], |row| row.get(0))
+                .optional()?
+                .flatten();
+            let Some(parent) = parent else { break };
+            let changed = conn.execute(
+                "UPDATE tasks SET status = ?1
+                 WHERE id = ?2 AND status != ?1
+                   AND NOT EXISTS (SELECT 1 FROM tasks c WHERE c.parent_id = ?2 AND c.status != ?1)",
+                params![Status::Done.to_string(), parent],
+            )?;
+            if changed == 0 {
+                break;
+            }
+            completed.push(parent);
+            current = parent;
+        }
+        Ok(completed)
+    }
+
+    /// Records that `task_id` cannot start until `depends_on` is done.
+    pub fn add_dependency(&self, task_id: i64, depends_on: i64) -> Result<()> {
+        if task_id == depends_on {
+            bail!("A task cannot depend on itself");
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        for id in [task_id, depends_on] {
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM tasks WHERE id = ?1)",
+                params![id],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                bail!("Task {} not found", id);
+            }
+        }
+
+        // Adding task -> depends_on closes a cycle if task_id is already
+        // reachable by following dependencies from depends_on.
+        let cycle: bool = tx.query_row(
+            "WITH RECURSIVE reachable(id) AS (
+                SELECT ?2
+                UNION
+                SELECT d.depends_on FROM task_dependencies d JOIN reachable r ON d.task_id = r.id
+             )
+             SELECT EXISTS (SELECT 1 FROM reachable WHERE id = ?1)",
+            params![task_id, depends_on],
+            |row| row.get(0),
+        )?;
+        if cycle {
+            bail!("Task {} already depends on task {}; blocking it would create a cycle", depends_on, task_id);
+        }
+
+        tx.execute(
+            "INSERT OR IGNORE INTO task_dependencies (task_id, depends_on) VALUES (?1, ?2)",
+            params![task_id, depends_on],
         )?;
+        tx.commit()?;
         Ok(())
     }
 
+    /// Removes one dependency, or all of them when `depends_on` is `None`.
+    /// Returns how many were removed.
+    pub fn remove_dependency(&self, task_id: i64, depends_on: Option<i64>) -> Result<usize> {
+        let removed = self.conn.execute(
+            "DELETE FROM task_dependencies WHERE task_id = ?1 AND (?2 IS NULL OR depends_on = ?2)",
+            params![task_id, depends_on],
+        )?;
+        Ok(removed)
+    }
+
     pub fn delete_task(&self, id: i64) -> Result<()> {
         self.conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
         Ok(())
     }
 
+    /// Full-text search over task and note text, best matches first.
+    /// `query` is an FTS5 match expression; `project` scopes results to a
+    /// project root and everything below it.
+    pub fn search(&self, query: &str, project: Option<&str>, tasks: bool, notes: bool, limit: usize) -> Result<Vec<SearchHit>> {
+        let project = project.map(|p| p.trim_end_matches('/').to_string());
+        let mut hits = Vec::new();
+
+        // Titles weigh more than bodies in the ranking.
+        let sources = [
+            (tasks, "task", "SELECT t.id, t.title,
+                    snippet(tasks_fts, -1, char(2), char(3), '…', 12),
+                    bm25(tasks_fts, 10.0, 1.0), p.path
+                 FROM tasks_fts JOIN tasks t ON t.id = tasks_fts.rowid
+                 LEFT JOIN projects p ON p.id = t.project_id
+                 WHERE tasks_fts MATCH ?1"),
+            (notes, "note", "SELECT n.id, n.title,
+                    snippet(notes_fts, -1, char(2), char(3), '…', 12),
+                    bm25(notes_fts, 10.0, 1.0, 5.0), p.path
+                 FROM notes_fts JOIN notes n ON n.id = notes_fts.rowid
+                 LEFT JOIN projects p ON p.id = n.project_id
+                 WHERE notes_fts MATCH ?1"),
+        ];
+
+        for (enabled, kind, sql) in sources {
+            if !enabled {
+                continue;
+            }
+            let sql = format!(
+                "{} AND (?2 IS NULL OR rtrim(COALESCE(p.path, ''), '/') = ?2
+                     OR (?2 != '' AND substr(p.path, 1, length(?2) + 1) = ?2 || '/'))
+                 ORDER BY 4 LIMIT ?3",
+                sql
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let iter = stmt.query_map(params![query, project, limit as i64], |row| {
+                Ok(SearchHit {
+                    kind: kind.to_string(),
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    snippet: row.get(2)?,
+                    rank: row.get(3)?,
+                    project_path: row.get(4)?,
+                })
+            })?;
+            for hit in iter {
+                hits.push(hit?);
+            }
+        }
+
+        hits.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
     pub fn add_note(&self, note: &Note) -> Result<i64> {
         let tags_str = serde_json::to_string(&note.tags)?;
-        self.conn.execute(
-            "INSERT INTO notes (title, content, project_path, created_at, tags)
+        let tx = self.conn.unchecked_transaction()?;
+        let project_id = project_id(&tx, note.project_path.as_deref())?;
+        tx.execute(
+            "INSERT INTO notes (title, content, project_id, created_at, tags)
              VALUES (?1, ?2, ?3, ?4, ?5)",
             params![
                 note.title,
                 note.content,
-                note.project_path,
+                project_id,
                 note.created_at.to_rfc3339(),
                 tags_str
             ],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        tx.commit()?;
+        Ok(id)
     }
 
     pub fn get_notes(&self, project_filter: Option<&str>) -> Result<Vec<Note>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, project_path, created_at, tags 
-             FROM notes 
-             WHERE ?1 IS NULL OR project_path = ?1"
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM notes WHERE {}",
+            NOTE_COLUMNS,
+            in_project("notes", "?1")
+        ))?;
 
         let note_iter = stmt.query_map(params![project_filter], |row| {
             let created_at_str: String = row.get(4)?;
@@ -164,11 +912,7 @@ impl TaskRepository {
     }
 
     pub fn get_note(&self, id: i64) -> Result<Note> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, project_path, created_at, tags 
-             FROM notes 
-             WHERE id = ?1"
-        )?;
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM notes WHERE id = ?1", NOTE_COLUMNS))?;
 
         let note = stmt.query_row(params![id], |row| {
             let created_at_str: String = row.get(4)?;
@@ -194,7 +938,8 @@ impl TaskRepository {
         Ok(())
     }
 
-    pub fn update_note(&self, id: i64, title: Option<String>, content: Option<String>, tags: Option<Vec<String>>) -> Result<()> {
+    /// Changes the given fields of a note; `Some(None)` clears its content.
+    pub fn update_note(&self, id: i64, title: Option<String>, content: Option<Option<String>>, tags: Option<Vec<String>>) -> Result<()> {
         if let Some(t) = title {
             self.conn.execute("UPDATE notes SET title = ?1 WHERE id = ?2", params![t, id])?;
         }
@@ -208,3 +953,861 @@ impl TaskRepository {
         Ok(())
     }
 }
+
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    let status_str: String = row.get(3)?;
+    let priority_str: String = row.get(4)?;
+    let due_date_str: Option<String> = row.get(5)?;
+    let created_at_str: String = row.get(7)?;
+    let tags_str: String = row.get(8)?;
+    let mut tags: Vec<String> = serde_json::from_str(&tags_str).unwrap_or_default();
+    tags.sort();
+    let blocked_by_str: String = row.get(9)?;
+    let mut blocked_by: Vec<i64> = serde_json::from_str(&blocked_by_str).unwrap_or_default();
+    blocked_by.sort();
+
+    Ok(Task {
+        id: Some(row.get(0)?),
+        title: row.get(1)?,
+        description: row.get(2)?,
+        status: Status::from(status_str),
+        priority: Priority::from(priority_str),
+        due_date: due_date_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Local))),
+        project_path: row.get(6)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Local))
+            .unwrap_or_else(|_| Local::now()),
+        tags,
+        blocked_by,
+        parent_id: row.get(10)?,
+        subtasks_total: row.get(11)?,
+        subtasks_done: row.get(12)?,
+        recurrence: row.get(13)?,
+        recur_from_completion: row.get(14)?,
+        branch: row.get(15)?,
+        completed_at: row
+            .get::<_, Option<String>>(16)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Local))),
+        scope_branch: row.get(17)?,
+    })
+}
+
+fn insert_task(conn: &Connection, task: &Task) -> Result<i64> {
+    let project_id = project_id(conn, task.project_path.as_deref())?;
+    conn.execute(
+        "INSERT INTO tasks (title, description, status, priority, due_date, project_id, created_at, parent_id,
+                            recurrence, recur_from_completion, branch, completed_at, scope_branch)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            task.title,
+            task.description,
+            task.status.to_string(),
+            task.priority.to_string(),
+            task.due_date.map(|d| d.to_rfc3339()),
+            project_id,
+            task.created_at.to_rfc3339(),
+            task.parent_id,
+            task.recurrence,
+            task.recur_from_completion,
+            task.branch,
+            task.completed_at.map(|d| d.to_rfc3339()),
+            task.scope_branch,
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    set_task_tags(conn, id, &task.tags)?;
+    Ok(id)
+}
+
+fn project_from_row(row: &Row) -> rusqlite::Result<Project> {
+    let created_at_str: String = row.get(4)?;
+    Ok(Project {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        identity: row.get(2)?,
+        path: row.get(3)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Local))
+            .unwrap_or_else(|_| Local::now()),
+        tasks: row.get(5)?,
+        notes: row.get(6)?,
+    })
+}
+
+fn query_project<P: rusqlite::Params>(conn: &Connection, condition: &str, params: P) -> Result<Option<Project>> {
+    let sql = format!("SELECT {} FROM projects WHERE {}", PROJECT_COLUMNS, condition);
+    Ok(conn.query_row(&sql, params, project_from_row).optional()?)
+}
+
+/// SQL for "`table`'s row belongs to the project at path `param`", where a
+/// `NO_PROJECT` path matches rows without a project and NULL matches all.
+fn in_project(table: &str, param: &str) -> String {
+    format!("({param} IS NULL OR COALESCE((SELECT path FROM projects WHERE id = {table}.project_id), '') = {param})")
+}
+
+/// The ID of the project registered at `path`. Tasks and notes are only
+/// stored in projects that exist.
+fn project_id(conn: &Connection, path: Option<&str>) -> Result<Option<i64>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let id = conn
+        .query_row("SELECT id FROM projects WHERE path = ?1", params![path], |row| row.get(0))
+        .optional()?
+        .with_context(|| format!("No project is registered at {}", path))?;
+    Ok(Some(id))
+}
+
+/// Registers a new project, naming it after the last part of its path.
+fn insert_project(conn: &Connection, identity: &str, path: &str) -> Result<Project> {
+    let name = unused_project_name(conn, path)?;
+    conn.execute(
+        "INSERT INTO projects (name, identity, path, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![name, identity, path, Local::now().to_rfc3339()],
+    )?;
+    query_project(conn, "id = ?1", params![conn.last_insert_rowid()])?.context("Project was just inserted")
+}
+
+// Tasks and notes refer to the project by ID, so they move with it. A project
+// known only by its path takes the new path as its identity too.
+fn relocate_project(conn: &Connection, project: &Project, path: &str) -> Result<()> {
+    let identity = match project.identity.strip_prefix("path:") {
+        Some(_) => format!("path:{}", path),
+        None => project.identity.clone(),
+    };
+    conn.execute(
+        "UPDATE projects SET path = ?2, identity = ?3 WHERE id = ?1",
+        params![project.id, path, identity],
+    )?;
+    Ok(())
+}
+
+/// The last component of `path`, made unique among project names by a
+/// numeric suffix.
+fn unused_project_name(conn: &Connection, path: &str) -> rusqlite::Result<String> {
+    let base = path.rsplit(['/', '\\']).find(|part| !part.is_empty()).unwrap_or("project");
+    // Numbers are taken for project IDs.
+    let base = if base.parse::<i64>().is_ok() { format!("project-{}", base) } else { base.to_string() };
+
+    let mut name = base.clone();
+    let mut n = 1;
+    loop {
+        let taken: bool =
+            conn.query_row("SELECT EXISTS (SELECT 1 FROM projects WHERE name = ?1)", params![name], |row| row.get(0))?;
+        if !taken {
+            return Ok(name);
+        }
+        n += 1;
+        name = format!("{}-{}", base, n);
+    }
+}
+
+fn time_entry_from_row(row: &Row) -> rusqlite::Result<TimeEntry> {
+    let parse = |s: String| DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Local)).ok();
+    let started_at: String = row.get(2)?;
+    let ended_at: Option<String> = row.get(3)?;
+    Ok(TimeEntry {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        started_at: parse(started_at).unwrap_or_else(Local::now),
+        ended_at: ended_at.and_then(parse),
+    })
+}
+
+fn running_entry(conn: &Connection) -> Result<Option<TimeEntry>> {
+    let entry = conn
+        .query_row(
+            "SELECT id, task_id, started_at, ended_at FROM time_entries WHERE ended_at IS NULL",
+            [],
+            time_entry_from_row,
+        )
+        .optional()?;
+    Ok(entry)
+}
+
+fn stop_running_entry(conn: &Connection, now: DateTime<Local>) -> Result<Option<TimeEntry>> {
+    let Some(mut entry) = running_entry(conn)? else {
+        return Ok(None);
+    };
+    conn.execute(
+        "UPDATE time_entries SET ended_at = ?1 WHERE id = ?2",
+        params![now.to_rfc3339(), entry.id],
+    )?;
+    entry.ended_at = Some(now);
+    Ok(Some(entry))
+}
+
+// Ends the timer running on task `id`, if it has one. Leaving In Progress
+// stops the clock.
+fn stop_task_timer(conn: &Connection, id: i64, now: DateTime<Local>) -> Result<()> {
+    conn.execute(
+        "UPDATE time_entries SET ended_at = ?2 WHERE task_id = ?1 AND ended_at IS NULL",
+        params![id, now.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+fn task_is_done(conn: &Connection, id: i64) -> Result<bool> {
+    let status: Option<String> = conn
+        .query_row("SELECT status FROM tasks WHERE id = ?1", params![id], |row| row.get(0))
+        .optional()?;
+    match status {
+        Some(status) => Ok(Status::from(status) == Status::Done),
+        None => bail!("Task {} not found", id),
+    }
+}
+
+// Creates the next instance of a recurring task that was just completed and
+// hands the rule over to it, so finishing the old instance again (after a
+// reopen) can't spawn a duplicate. Fixed schedules advance from the old due
+// date, skipping occurrences that are already past; the rest count from now.
+fn spawn_next_instance(conn: &Connection, id: i64, now: DateTime<Local>) -> Result<Option<i64>> {
+    let task = conn.query_row(&format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS), params![id], task_from_row)?;
+    let Some(rule) = task.recurrence.as_deref() else {
+        return Ok(None);
+    };
+    let recurrence: Recurrence = rule
+        .parse()
+        .with_context(|| format!("Task {} has an invalid repeat rule", id))?;
+
+    let time = task.due_date.map_or(NaiveTime::MIN, |d| d.time());
+    let due = match task.due_date {
+        Some(due) if !task.recur_from_completion => {
+            let mut next = recurrence.next_after(due, time)?;
+            while next <= now {
+                next = recurrence.next_after(next, time)?;
+            }
+            next
+        }
+        _ => recurrence.next_after(now, time)?,
+    };
+
+    let next_id = insert_task(
+        conn,
+        &Task {
+            status: Status::Todo,
+            due_date: Some(due),
+            created_at: now,
+            branch: None,
+            completed_at: None,
+            ..task
+        },
+    )?;
+    conn.execute("UPDATE tasks SET recurrence = NULL WHERE id = ?1", params![id])?;
+    Ok(Some(next_id))
+}
+
+// A task can't become a subtask of itself or of one of its own descendants.
+fn check_parent(conn: &Connection, id: i64, parent_id: i64) -> Result<()> {
+    if id == parent_id {
+        bail!("A task cannot be its own parent");
+    }
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM tasks WHERE id = ?1)",
+        params![parent_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        bail!("Parent task {} not found", parent_id);
+    }
+    let descendant: bool = conn.query_row(
+        "WITH RECURSIVE descendants(id) AS (
+            SELECT id FROM tasks WHERE parent_id = ?1
+            UNION
+            SELECT t.id FROM tasks t JOIN descendants d ON t.parent_id = d.id
+         )
+         SELECT EXISTS (SELECT 1 FROM descendants WHERE id = ?2)",
+        params![id, parent_id],
+        |row| row.get(0),
+    )?;
+    if descendant {
+        bail!("Task {} is a subtask of task {}; it cannot become its parent", parent_id, id);
+    }
+    Ok(())
+}
+
+const STATUS_RANK: &str = "CASE status WHEN 'Todo' THEN 0 WHEN 'In Progress' THEN 1 WHEN 'Done' THEN 2 END";
+const PRIORITY_RANK: &str = "CASE priority WHEN 'Low' THEN 0 WHEN 'Medium' THEN 1 WHEN 'High' THEN 2 WHEN 'Critical' THEN 3 END";
+
+/// Compiles a filter into a SQL boolean expression over `tasks`, pushing its
+/// parameters onto `values`. Placeholders are numbered so the expression can
+/// follow other parameters.
+fn filter_sql(filter: &Filter, values: &mut Vec<Value>) -> String {
+    fn param(values: &mut Vec<Value>, value: Value) -> String {
+        values.push(value);
+        format!("?{}", values.len())
+    }
+
+    match filter {
+        Filter::And(a, b) => format!("({} AND {})", filter_sql(a, values), filter_sql(b, values)),
+        Filter::Or(a, b) => format!("({} OR {})", filter_sql(a, values), filter_sql(b, values)),
+        // Comparisons against NULL columns are NULL, and NOT NULL is still
+        // NULL; treat them as false so `not due<friday` keeps undated tasks.
+        Filter::Not(inner) => format!("NOT COALESCE({}, 0)", filter_sql(inner, values)),
+        Filter::Cond(cond) => match cond {
+            Condition::Id(op, id) => format!("id {} {}", op.sql(), param(values, Value::Integer(*id))),
+            Condition::Status(op, status) => {
+                format!("{} {} {}", STATUS_RANK, op.sql(), param(values, Value::Integer(status.rank())))
+            }
+            Condition::Priority(op, priority) => {
+                format!("{} {} {}", PRIORITY_RANK, op.sql(), param(values, Value::Integer(priority.rank())))
+            }
+            Condition::Blocked => BLOCKED_SQL.to_string(),
+            Condition::Tag(tag) => format!(
+                "EXISTS (SELECT 1 FROM task_tags WHERE task_id = tasks.id AND tag = {})",
+                param(values, Value::Text(tag.clone()))
+            ),
+            Condition::Due(date) => date_sql("due_date", date, values),
+            Condition::Created(date) => date_sql("created_at", date, values),
+            Condition::Completed(date) => date_sql("completed_at", date, values),
+            Condition::Title(text) => format!(
+                "instr(lower(title), lower({})) > 0",
+                param(values, Value::Text(text.clone()))
+            ),
+            Condition::Project(text) => {
+                let p = param(values, Value::Text(text.clone()));
+                format!(
+                    "EXISTS (SELECT 1 FROM projects WHERE id = tasks.project_id
+                        AND (instr(lower(path), lower({p})) > 0 OR lower(name) = lower({p})))"
+                )
+            }
+            Condition::Branch(branch) => format!(
+                "(scope_branch IS NULL OR scope_branch = {})",
+                param(values, Value::Text(branch.clone()))
+            ),
+            Condition::Text(text) => {
+                let p = param(values, Value::Text(text.clone()));
+                format!(
+                    "(instr(lower(title), lower({p})) > 0 OR instr(lower(COALESCE(description, '')), lower({p})) > 0)"
+                )
+            }
+        },
+    }
+}
+
+fn order_sql(sort: &[SortKey]) -> String {
+    let mut terms: Vec<String> = Vec::new();
+    for key in sort {
+        let dir = if key.descending { "DESC" } else { "ASC" };
+        match key.field {
+            SortField::Priority => terms.push(format!("{} {}", PRIORITY_RANK, dir)),
+            SortField::Status => terms.push(format!("{} {}", STATUS_RANK, dir)),
+            // Undated tasks go last in either direction.
+            SortField::Due => {
+                terms.push("due_date IS NULL".to_string());
+                terms.push(format!("julianday(due_date) {}", dir));
+            }
+            SortField::Created => terms.push(format!("julianday(created_at) {}", dir)),
+            // Open tasks go last in either direction.
+            SortField::Completed => {
+                terms.push("completed_at IS NULL".to_string());
+                terms.push(format!("julianday(completed_at) {}", dir));
+            }
+            SortField::Title => terms.push(format!("title COLLATE NOCASE {}", dir)),
+        }
+    }
+    terms.push("id".to_string());
+    format!(" ORDER BY {}", terms.join(", "))
+}
+
+// Dates are stored as RFC 3339 strings with the local offset at the time of
+// writing, so compare through julianday() rather than as text.
+fn date_sql(column: &str, date: &DateCond, values: &mut Vec<Value>) -> String {
+    let mut param = |day: DateTime<Local>| {
+        values.push(Value::Text(day.to_rfc3339()));
+        format!("julianday(?{})", values.len())
+    };
+    let col = format!("julianday({})", column);
+
+    match date {
+        DateCond::None => format!("{} IS NULL", column),
+        DateCond::Any => format!("{} IS NOT NULL", column),
+        DateCond::Cmp(op, day) => match op {
+            CmpOp::Eq => format!("({col} >= {} AND {col} < {})", param(*day), param(next_day(*day))),
+            CmpOp::Ne => format!(
+                "({column} IS NULL OR {col} < {} OR {col} >= {})",
+                param(*day),
+                param(next_day(*day))
+            ),
+            CmpOp::Lt => format!("{col} < {}", param(*day)),
+            CmpOp::Le => format!("{col} < {}", param(next_day(*day))),
+            CmpOp::Gt => format!("{col} >= {}", param(next_day(*day))),
+            CmpOp::Ge => format!("{col} >= {}", param(*day)),
+        },
+    }
+}
+
+fn set_task_tags(conn: &Connection, task_id: i64, tags: &[String]) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM task_tags WHERE task_id = ?1", params![task_id])?;
+    let mut stmt = conn.prepare("INSERT OR IGNORE INTO task_tags (task_id, tag) VALUES (?1, ?2)")?;
+    for tag in tags.iter().filter_map(|t| normalize_tag(t)) {
+        stmt.execute(params![task_id, tag])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn task(title: &str, project_path: Option<&str>) -> Task {
+        Task {
+            id: None,
+            title: title.to_string(),
+            description: None,
+            status: Status::Todo,
+            priority: Priority::Medium,
+            due_date: None,
+            project_path: project_path.map(str::to_string),
+            created_at: Local::now(),
+            tags: Vec::new(),
+            blocked_by: Vec::new(),
+            parent_id: None,
+            subtasks_total: 0,
+            subtasks_done: 0,
+            recurrence: None,
+            recur_from_completion: false,
+            branch: None,
+            completed_at: None,
+            scope_branch: None,
+        }
+    }
+
+    fn note(title: &str, project_path: Option<&str>) -> Note {
+        Note {
+            id: None,
+            title: title.to_string(),
+            content: None,
+            project_path: project_path.map(str::to_string),
+            created_at: Local::now(),
+            tags: Vec::new(),
+        }
+    }
+
+    fn titles(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.title.as_str()).collect()
+    }
+
+    #[test]
+    fn tasks_and_notes_move_with_their_project() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let project = repo.add_project("github.com/me/app", "/src/app").unwrap();
+        repo.add_task(&task("Ship it", Some("/src/app"))).unwrap();
+        repo.add_note(&note("Design", Some("/src/app"))).unwrap();
+        repo.add_task(&task("Global", None)).unwrap();
+
+        repo.move_project(project.id.unwrap(), "/work/app").unwrap();
+        assert!(repo.get_tasks(Some("/src/app"), None, &[]).unwrap().is_empty());
+        let tasks = repo.get_tasks(Some("/work/app"), None, &[]).unwrap();
+        assert_eq!(titles(&tasks), ["Ship it"]);
+        assert_eq!(tasks[0].project_path.as_deref(), Some("/work/app"));
+        assert_eq!(repo.get_notes(Some("/work/app")).unwrap()[0].project_path.as_deref(), Some("/work/app"));
+        assert_eq!(titles(&repo.get_tasks(Some(NO_PROJECT), None, &[]).unwrap()), ["Global"]);
+
+        let project = repo.get_project(project.id.unwrap()).unwrap();
+        assert_eq!((project.tasks, project.notes), (1, 1));
+    }
+
+    #[test]
+    fn merging_projects_moves_everything_into_one() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let old = repo.add_project("path:/old", "/old").unwrap().id.unwrap();
+        let new = repo.add_project("path:/new", "/new").unwrap().id.unwrap();
+        repo.add_task(&task("Old task", Some("/old"))).unwrap();
+        repo.add_note(&note("Old note", Some("/old"))).unwrap();
+        repo.add_task(&task("New task", Some("/new"))).unwrap();
+
+        assert_eq!(repo.merge_projects(old, new).unwrap(), (1, 1));
+        assert!(repo.get_project(old).is_err());
+        assert_eq!(titles(&repo.get_tasks(Some("/new"), None, &[]).unwrap()), ["Old task", "New task"]);
+        assert_eq!(repo.get_notes(Some("/new")).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn tasks_only_go_into_registered_projects() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let error = repo.add_task(&task("Lost", Some("/nowhere"))).unwrap_err();
+        assert_eq!(error.to_string(), "No project is registered at /nowhere");
+        assert!(repo.add_note(&note("Lost", Some("/nowhere"))).is_err());
+        assert!(repo.get_tasks(None, None, &[]).unwrap().is_empty());
+
+        // The database itself refuses to drop a project still in use.
+        let project = repo.add_project("path:/src/app", "/src/app").unwrap();
+        repo.add_task(&task("Kept", Some("/src/app"))).unwrap();
+        assert!(repo.conn.execute("DELETE FROM projects WHERE id = ?1", params![project.id]).is_err());
+    }
+
+    #[test]
+    fn leaving_in_progress_stops_the_timer() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let id = repo.add_task(&task("Write docs", None)).unwrap();
+        let now = Local::now();
+
+        repo.start_timer(id, now).unwrap();
+        repo.update_task(id, &TaskUpdate { status: Some(Status::InProgress), ..Default::default() }).unwrap();
+        assert!(repo.running_timer().unwrap().is_some());
+        repo.update_task(id, &TaskUpdate { status: Some(Status::Todo), ..Default::default() }).unwrap();
+        assert!(repo.running_timer().unwrap().is_none());
+
+        repo.start_timer(id, now).unwrap();
+        repo.set_status(id, Status::Todo).unwrap();
+        assert!(repo.running_timer().unwrap().is_none());
+    }
+
+    #[test]
+    fn status_changes_record_and_clear_completion() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let id = repo.add_task(&task("Write docs", None)).unwrap();
+
+        repo.set_status(id, Status::InProgress).unwrap();
+        let started = repo.get_task(id).unwrap();
+        assert_eq!((started.status, started.completed_at), (Status::InProgress, None));
+
+        repo.set_status(id, Status::Done).unwrap();
+        let done = repo.get_task(id).unwrap();
+        assert_eq!(done.status, Status::Done);
+        assert!(done.completed_at.is_some());
+
+        repo.set_status(id, Status::Todo).unwrap();
+        let reopened = repo.get_task(id).unwrap();
+        assert_eq!((reopened.status, reopened.completed_at), (Status::Todo, None));
+
+        assert!(repo.set_status(id + 1, Status::Todo).is_err());
+    }
+
+    #[test]
+    fn clearing_a_note_body_stores_null() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let id = repo.add_note(&Note { content: Some("Draft".to_string()), ..note("Plan", None) }).unwrap();
+        repo.update_note(id, None, Some(None), None).unwrap();
+        assert_eq!(repo.get_note(id).unwrap().content, None);
+    }
+
+    #[test]
+    fn partial_update_leaves_other_fields_alone() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let id = repo
+            .add_task(&Task { description: Some("Draft".to_string()), tags: vec!["docs".to_string()], ..task("Write", None) })
+            .unwrap();
+
+        let update = TaskUpdate { title: Some("Write docs".to_string()), priority: Some(Priority::High), ..Default::default() };
+        repo.update_task(id, &update).unwrap();
+        let updated = repo.get_task(id).unwrap();
+        assert_eq!(updated.title, "Write docs");
+        assert_eq!(updated.priority, Priority::High);
+        assert_eq!(updated.description.as_deref(), Some("Draft"));
+        assert_eq!(updated.tags, ["docs"]);
+        assert_eq!(updated.status, Status::Todo);
+
+        repo.update_task(id, &TaskUpdate { description: Some(None), ..Default::default() }).unwrap();
+        assert_eq!(repo.get_task(id).unwrap().description, None);
+    }
+
+    #[test]
+    fn parent_cycles_are_rejected() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let top = repo.add_task(&task("Top", None)).unwrap();
+        let middle = repo.add_task(&Task { parent_id: Some(top), ..task("Middle", None) }).unwrap();
+        let bottom = repo.add_task(&Task { parent_id: Some(middle), ..task("Bottom", None) }).unwrap();
+
+        let update = TaskUpdate { title: Some("Renamed".to_string()), parent_id: Some(Some(bottom)), ..Default::default() };
+        let error = repo.update_task(top, &update).unwrap_err();
+        assert_eq!(error.to_string(), format!("Task {} is a subtask of task {}; it cannot become its parent", bottom, top));
+        // Nothing of a rejected update is applied.
+        assert_eq!(repo.get_task(top).unwrap().title, "Top");
+
+        let update = TaskUpdate { parent_id: Some(Some(top)), ..Default::default() };
+        assert_eq!(repo.update_task(top, &update).unwrap_err().to_string(), "A task cannot be its own parent");
+        let update = TaskUpdate { parent_id: Some(Some(99)), ..Default::default() };
+        assert_eq!(repo.update_task(top, &update).unwrap_err().to_string(), "Parent task 99 not found");
+    }
+
+    fn tags(repo: &TaskRepository, id: i64) -> Vec<String> {
+        repo.get_task(id).unwrap().tags
+    }
+
+    #[test]
+    fn tags_are_normalized_and_counted() {
+        let repo = TaskRepository::in_memory().unwrap();
+        repo.add_project("path:/src/app", "/src/app").unwrap();
+        let raw = ["Work", "#work", "@Home", "  "].map(str::to_string).to_vec();
+        let first = repo.add_task(&Task { tags: raw, ..task("First", None) }).unwrap();
+        repo.add_task(&Task { tags: vec!["work".to_string()], ..task("Second", Some("/src/app")) }).unwrap();
+        assert_eq!(tags(&repo, first), ["home", "work"]);
+
+        let counts: Vec<(String, i64, i64)> =
+            repo.get_tag_counts().unwrap().into_iter().map(|c| (c.tag, c.tasks, c.projects)).collect();
+        assert_eq!(counts, [("work".to_string(), 2, 1), ("home".to_string(), 1, 0)]);
+
+        let filter = Filter::parse("tag:work not tag:home", Local::now()).unwrap();
+        assert_eq!(titles(&repo.get_tasks(None, Some(&filter), &[]).unwrap()), ["Second"]);
+        assert!(repo.tag_exists("#HOME").unwrap());
+        assert!(!repo.tag_exists("play").unwrap());
+    }
+
+    #[test]
+    fn merging_tags_keeps_one_of_each() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let both = repo.add_task(&Task { tags: vec!["bug".to_string(), "defect".to_string()], ..task("Both", None) }).unwrap();
+        let old = repo.add_task(&Task { tags: vec!["Defect".to_string()], ..task("Old", None) }).unwrap();
+
+        assert_eq!(repo.merge_tags(&["defect".to_string()], "#Bug").unwrap(), 1);
+        assert_eq!(tags(&repo, both), ["bug"]);
+        assert_eq!(tags(&repo, old), ["bug"]);
+        assert!(repo.merge_tags(&["bug".to_string()], " ").is_err());
+    }
+
+    #[test]
+    fn sorting_puts_undated_tasks_last() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let due = |days| Some(Local::now() + chrono::Duration::days(days));
+        repo.add_task(&Task { priority: Priority::Low, ..task("Undated", None) }).unwrap();
+        repo.add_task(&Task { due_date: due(3), ..task("Later", None) }).unwrap();
+        repo.add_task(&Task { due_date: due(1), priority: Priority::High, ..task("Soon", None) }).unwrap();
+
+        let sort = |spec: &str| -> Vec<String> {
+            let keys: Vec<SortKey> = spec.split(',').map(|k| SortKey::parse(k).unwrap()).collect();
+            repo.get_tasks(None, None, &keys).unwrap().into_iter().map(|t| t.title).collect()
+        };
+        assert_eq!(sort("due"), ["Soon", "Later", "Undated"]);
+        assert_eq!(sort("due:desc"), ["Later", "Soon", "Undated"]);
+        assert_eq!(sort("priority"), ["Soon", "Later", "Undated"]);
+        assert_eq!(sort("priority:asc,title"), ["Undated", "Later", "Soon"]);
+    }
+
+    fn search(repo: &TaskRepository, query: &str, project: Option<&str>) -> Vec<(String, i64)> {
+        repo.search(query, project, true, true, 10).unwrap().into_iter().map(|h| (h.kind, h.id)).collect()
+    }
+
+    #[test]
+    fn search_ranks_titles_first_and_follows_edits() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let body = repo
+            .add_task(&Task { description: Some("check the deploy script".to_string()), ..task("Release", None) })
+            .unwrap();
+        let title = repo.add_task(&task("Deploy to staging", None)).unwrap();
+        let note = repo.add_note(&Note { content: Some("Deploy steps".to_string()), ..note("Runbook", None) }).unwrap();
+
+        let hits = search(&repo, "deploy", None);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0], ("task".to_string(), title));
+        assert!(hits.contains(&("task".to_string(), body)) && hits.contains(&("note".to_string(), note)));
+
+        repo.update_task(title, &TaskUpdate { title: Some("Ship to staging".to_string()), ..Default::default() }).unwrap();
+        repo.delete_note(note).unwrap();
+        assert_eq!(search(&repo, "deploy", None), [("task".to_string(), body)]);
+        assert_eq!(search(&repo, "ship", None), [("task".to_string(), title)]);
+    }
+
+    #[test]
+    fn search_scopes_to_a_project_and_below() {
+        let repo = TaskRepository::in_memory().unwrap();
+        for path in ["/src/app", "/src/app/web", "/src/app2"] {
+            repo.add_project(&format!("path:{}", path), path).unwrap();
+        }
+        let app = repo.add_task(&task("Fix login", Some("/src/app"))).unwrap();
+        let web = repo.add_task(&task("Fix login page", Some("/src/app/web"))).unwrap();
+        repo.add_task(&task("Fix login too", Some("/src/app2"))).unwrap();
+        let global = repo.add_task(&task("Fix login globally", None)).unwrap();
+
+        let ids = |project| search(&repo, "login", project).into_iter().map(|(_, id)| id).collect::<Vec<_>>();
+        let mut scoped = ids(Some("/src/app/"));
+        scoped.sort();
+        assert_eq!(scoped, [app, web]);
+        assert_eq!(ids(Some(NO_PROJECT)), [global]);
+        assert_eq!(ids(None).len(), 4);
+    }
+
+    #[test]
+    fn dependency_cycles_are_rejected() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let [a, b, c] = ["A", "B", "C"].map(|t| repo.add_task(&task(t, None)).unwrap());
+        repo.add_dependency(a, b).unwrap();
+        repo.add_dependency(b, c).unwrap();
+
+        let error = repo.add_dependency(c, a).unwrap_err();
+        assert_eq!(error.to_string(), format!("Task {} already depends on task {}; blocking it would create a cycle", a, c));
+        assert_eq!(repo.add_dependency(b, a).unwrap_err().to_string(), format!("Task {} already depends on task {}; blocking it would create a cycle", a, b));
+        assert_eq!(repo.add_dependency(a, a).unwrap_err().to_string(), "A task cannot depend on itself");
+        assert_eq!(repo.add_dependency(a, 99).unwrap_err().to_string(), "Task 99 not found");
+        // Adding an existing dependency again is harmless.
+        repo.add_dependency(a, b).unwrap();
+        assert_eq!(repo.get_task(a).unwrap().blocked_by, [b]);
+    }
+
+    #[test]
+    fn only_open_dependencies_block() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let [task_id, first, second] = ["Release", "Tests", "Docs"].map(|t| repo.add_task(&task(t, None)).unwrap());
+        repo.add_dependency(task_id, first).unwrap();
+        repo.add_dependency(task_id, second).unwrap();
+        let blocked = Filter::Cond(Condition::Blocked);
+        assert_eq!(titles(&repo.get_tasks(None, Some(&blocked), &[]).unwrap()), ["Release"]);
+
+        repo.complete_task(first).unwrap();
+        assert_eq!(repo.get_task(task_id).unwrap().blocked_by, [second]);
+        assert_eq!(repo.remove_dependency(task_id, None).unwrap(), 2);
+        assert!(!repo.get_task(task_id).unwrap().is_blocked());
+        assert!(repo.get_tasks(None, Some(&blocked), &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn finishing_the_last_subtask_completes_its_parents() {
+        let mut repo = TaskRepository::in_memory().unwrap();
+        repo.set_auto_complete_parents(true);
+        let top = repo.add_task(&task("Release", None)).unwrap();
+        let middle = repo.add_task(&Task { parent_id: Some(top), ..task("Docs", None) }).unwrap();
+        let first = repo.add_task(&Task { parent_id: Some(middle), ..task("API docs", None) }).unwrap();
+        let second = repo.add_task(&Task { parent_id: Some(middle), ..task("Guide", None) }).unwrap();
+
+        assert!(repo.complete_task(first).unwrap().parents.is_empty());
+        let parent = repo.get_task(middle).unwrap();
+        assert_eq!((parent.subtasks_done, parent.subtasks_total), (1, 2));
+
+        assert_eq!(repo.complete_task(second).unwrap().parents, [middle, top]);
+        assert_eq!(repo.get_task(top).unwrap().status, Status::Done);
+        assert!(repo.get_task(top).unwrap().completed_at.is_some());
+    }
+
+    #[test]
+    fn parents_stay_open_unless_asked() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let parent = repo.add_task(&task("Release", None)).unwrap();
+        let child = repo.add_task(&Task { parent_id: Some(parent), ..task("Docs", None) }).unwrap();
+        assert!(repo.complete_task(child).unwrap().parents.is_empty());
+        assert_eq!(repo.get_task(parent).unwrap().status, Status::Todo);
+    }
+
+    #[test]
+    fn starting_a_timer_stops_the_running_one() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let [first, second] = ["First", "Second"].map(|t| repo.add_task(&task(t, None)).unwrap());
+        let start = Local::now() - chrono::Duration::hours(1);
+
+        assert!(repo.start_timer(first, start).unwrap().is_none());
+        assert_eq!(repo.get_task(first).unwrap().status, Status::InProgress);
+        assert_eq!(repo.start_timer(first, start).unwrap_err().to_string(), format!("Already tracking time on task {}", first));
+        let stopped = repo.start_timer(second, start + chrono::Duration::minutes(20)).unwrap().unwrap();
+        assert_eq!(stopped.task_id, first);
+        assert_eq!(repo.running_timer().unwrap().unwrap().task_id, second);
+
+        repo.complete_task(second).unwrap();
+        assert!(repo.running_timer().unwrap().is_none());
+        assert_eq!(repo.start_timer(second, Local::now()).unwrap_err().to_string(), format!("Task {} is already done", second));
+    }
+
+    #[test]
+    fn time_report_clips_entries_to_the_range() {
+        let repo = TaskRepository::in_memory().unwrap();
+        repo.add_project("path:/src/app", "/src/app").unwrap();
+        let app = repo.add_task(&Task { tags: vec!["dev".to_string()], ..task("Code", Some("/src/app")) }).unwrap();
+        let other = repo.add_task(&task("Email", None)).unwrap();
+        let at = |h: u32, m: u32| Local.with_ymd_and_hms(2026, 3, 10, h, m, 0).unwrap();
+
+        repo.start_timer(app, at(9, 0)).unwrap();
+        repo.start_timer(other, at(10, 0)).unwrap();
+        repo.stop_timer(at(10, 30)).unwrap();
+        repo.start_timer(app, at(11, 0)).unwrap();
+
+        // 9:30-10:00 and a timer still running at 11:15.
+        let report = |project, by| -> Vec<(String, i64)> {
+            repo.time_report(project, at(9, 30), at(12, 0), at(11, 15), by)
+                .unwrap()
+                .into_iter()
+                .map(|t| (t.group, t.seconds))
+                .collect()
+        };
+        let totals = |groups: [&str; 2]| vec![(groups[0].to_string(), 2700), (groups[1].to_string(), 1800)];
+        assert_eq!(report(None, TimeGrouping::Task), totals([&format!("#{} Code", app), &format!("#{} Email", other)]));
+        assert_eq!(report(None, TimeGrouping::Project), totals(["app", "(none)"]));
+        assert_eq!(report(None, TimeGrouping::Tag), totals(["#dev", "(untagged)"]));
+        assert_eq!(report(Some("/src/app"), TimeGrouping::Project), [("app".to_string(), 2700)]);
+        assert_eq!(repo.time_report(None, at(9, 30), at(12, 0), at(11, 15), TimeGrouping::Task).unwrap()[0].hours, 0.75);
+    }
+
+    #[test]
+    fn a_branch_leads_to_its_open_task_first() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let old = repo.add_task(&task("Old", None)).unwrap();
+        let open = repo.add_task(&task("Open", None)).unwrap();
+        let newer = repo.add_task(&task("Newer", None)).unwrap();
+        for id in [old, open, newer] {
+            repo.set_task_branch(id, Some("12-fix-login")).unwrap();
+        }
+        repo.complete_task(newer).unwrap();
+
+        let found = |project| repo.task_for_branch("12-fix-login", project).unwrap().and_then(|t| t.id);
+        assert_eq!(found(None), Some(open));
+        repo.complete_task(open).unwrap();
+        repo.complete_task(old).unwrap();
+        assert_eq!(found(None), Some(newer));
+        assert_eq!(found(Some("/src/app")), None);
+        assert!(repo.task_for_branch("main", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn projects_are_found_by_identity_then_path() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let project = repo.add_project("path:/src/app", "/src/app").unwrap();
+        assert!(repo.lookup_project("github.com/me/app", "/elsewhere").unwrap().is_none());
+
+        // The repository gained a remote: the project at its path adopts it.
+        let found = repo.lookup_project("github.com/me/app", "/src/app").unwrap().unwrap();
+        assert_eq!((found.id, found.identity.as_str()), (project.id, "github.com/me/app"));
+
+        // A clone elsewhere finds the same project while the original exists...
+        let here = std::env::temp_dir().to_string_lossy().to_string();
+        repo.move_project(project.id.unwrap(), &here).unwrap();
+        let found = repo.lookup_project("github.com/me/app", "/clone/app").unwrap().unwrap();
+        assert_eq!(found.path, here);
+        // ...and the project follows it once the original directory is gone.
+        repo.move_project(project.id.unwrap(), "/gone/app").unwrap();
+        let found = repo.lookup_project("github.com/me/app", "/clone/app").unwrap().unwrap();
+        assert_eq!(found.path, "/clone/app");
+        assert_eq!(repo.get_project(project.id.unwrap()).unwrap().path, "/clone/app");
+    }
+
+    #[test]
+    fn project_names_are_unique_and_not_numbers() {
+        let repo = TaskRepository::in_memory().unwrap();
+        let first = repo.add_project("path:/a/app", "/a/app").unwrap();
+        let second = repo.add_project("path:/b/app", "/b/app").unwrap();
+        let numbered = repo.add_project("path:/src/2024", "/src/2024/").unwrap();
+        assert_eq!([first.name.as_str(), second.name.as_str(), numbered.name.as_str()], ["app", "app-2", "project-2024"]);
+
+        assert_eq!(repo.find_project("app-2").unwrap().id, second.id);
+        assert_eq!(repo.find_project(&first.id.unwrap().to_string()).unwrap().id, first.id);
+        assert_eq!(repo.find_project("/src/2024").unwrap().id, numbered.id);
+        assert!(repo.find_project("missing").is_err());
+
+        let id = first.id.unwrap();
+        assert!(repo.rename_project(id, "app-2").is_err());
+        assert!(repo.rename_project(id, "42").is_err());
+        assert!(repo.rename_project(id, " ").is_err());
+        repo.rename_project(id, " web ").unwrap();
+        assert_eq!(repo.get_project(id).unwrap().name, "web");
+        assert!(repo.move_project(id, "/b/app").is_err());
+    }
+
+    #[test]
+    fn notes_are_scoped_like_tasks() {
+        let repo = TaskRepository::in_memory().unwrap();
+        repo.add_project("path:/src/app", "/src/app").unwrap();
+        repo.add_project("path:/src/lib", "/src/lib").unwrap();
+        for (title, project) in [("App", Some("/src/app")), ("Lib", Some("/src/lib")), ("Global", None)] {
+            repo.add_note(&note(title, project)).unwrap();
+            repo.add_task(&task(title, project)).unwrap();
+        }
+
+        let notes = |project| repo.get_notes(project).unwrap().into_iter().map(|n| n.title).collect::<Vec<_>>();
+        for project in [Some("/src/app"), Some(NO_PROJECT), None] {
+            assert_eq!(notes(project), titles(&repo.get_tasks(project, None, &[]).unwrap()), "{:?}", project);
+        }
+        assert_eq!(notes(Some("/src/app")), ["App"]);
+        assert_eq!(notes(Some(NO_PROJECT)), ["Global"]);
+        assert_eq!(notes(None).len(), 3);
+        // A directory that isn't a project yet has nothing in it.
+        assert!(notes(Some("/src/new")).is_empty());
+    }
+}
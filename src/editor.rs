@@ -0,0 +1,197 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FENCE: &str = "---";
+
+/// A note or task as written in `$EDITOR`: a front-matter block with the
+/// title and tags, followed by the markdown body.
+///
+/// ```text
+/// ---
+/// title: Deploy checklist
+/// tags: ops, release
+/// ---
+///
+/// 1. Bump the version
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    pub title: String,
+    pub tags: Vec<String>,
+    pub body: String,
+}
+
+impl Document {
+    pub fn render(&self) -> String {
+        let mut text = format!("{FENCE}\ntitle: {}\ntags: {}\n{FENCE}\n\n", self.title, self.tags.join(", "));
+        if !self.body.is_empty() {
+            text.push_str(&self.body);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Reads a document back. Without front matter the whole text is the
+    /// body and the title is left empty.
+    pub fn parse(text: &str) -> Result<Document> {
+        let mut doc = Document::default();
+        let mut lines = text.lines();
+        let rest = match text.lines().next() {
+            Some(first) if first.trim_end() == FENCE => {
+                lines.next();
+                let mut closed = false;
+                for line in lines.by_ref() {
+                    if line.trim_end() == FENCE {
+                        closed = true;
+                        break;
+                    }
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let (key, value) = line
+                        .split_once(':')
+                        .with_context(|| format!("Expected `key: value` in the header, found \"{}\"", line))?;
+                    let value = value.trim();
+                    match key.trim().to_lowercase().as_str() {
+                        "title" => doc.title = value.to_string(),
+                        "tags" => {
+                            doc.tags = value
+                                .trim_start_matches('[')
+                                .trim_end_matches(']')
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|t| !t.is_empty())
+                                .map(str::to_string)
+                                .collect()
+                        }
+                        other => bail!("Unknown header field '{}' (expected title or tags)", other),
+                    }
+                }
+                if !closed {
+                    bail!("The header is missing its closing `{}` line", FENCE);
+                }
+                lines.collect::<Vec<_>>().join("\n")
+            }
+            _ => text.to_string(),
+        };
+        doc.body = rest.trim_matches('\n').trim_end().to_string();
+        Ok(doc)
+    }
+}
+
+/// Opens `doc` in the user's editor and reads it back. Returns `None` when
+/// the buffer is saved empty or unchanged, so callers can abort. If the
+/// result can't be used, the file is kept and named in the error.
+pub fn edit(doc: &Document) -> Result<Option<Document>> {
+    let original = doc.render();
+    let path = create_temp_file(&original)?;
+
+    let outcome = run_editor(&path).and_then(|_| Ok(fs::read_to_string(&path)?));
+    let text = match outcome {
+        Ok(text) => text,
+        Err(err) => {
+            let _ = fs::remove_file(&path);
+            return Err(err);
+        }
+    };
+    if text.trim().is_empty() || text == original {
+        let _ = fs::remove_file(&path);
+        return Ok(None);
+    }
+    let edited = Document::parse(&text).and_then(|doc| {
+        if doc.title.is_empty() {
+            bail!("A title is required");
+        }
+        Ok(doc)
+    });
+    let edited = edited.with_context(|| format!("Your text was kept in {}", path.display()))?;
+    let _ = fs::remove_file(&path);
+    Ok(Some(edited))
+}
+
+/// `$VISUAL`, then `$EDITOR`, then vi, like git.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+// The editor setting may carry arguments ("code --wait"), so hand it to the
+// shell the way git does.
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = editor();
+    let status = if cfg!(windows) {
+        let mut parts = editor.split_whitespace();
+        let program = parts.next().unwrap_or("notepad");
+        Command::new(program).args(parts).arg(path).status()
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$@\"", editor))
+            .arg(&editor)
+            .arg(path)
+            .status()
+    }
+    .with_context(|| format!("Could not start editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}; nothing was saved", editor, status);
+    }
+    Ok(())
+}
+
+// A new file only the user can read: the temp directory is shared, and
+// someone else may have put a file or link at a predictable name first.
+fn create_temp_file(contents: &str) -> Result<PathBuf> {
+    let path = temp_path();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path).with_context(|| format!("Could not create {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    Ok(path)
+}
+
+fn temp_path() -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    env::temp_dir().join(format!("flow-{}-{}.md", std::process::id(), nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_file_is_private() {
+        let path = create_temp_file("secret").unwrap();
+        let contents = fs::read_to_string(&path);
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            fs::metadata(&path).map(|m| m.permissions().mode() & 0o777)
+        };
+        fs::remove_file(&path).unwrap();
+        assert_eq!(contents.unwrap(), "secret");
+        #[cfg(unix)]
+        assert_eq!(mode.unwrap(), 0o600);
+    }
+
+    #[test]
+    fn document_round_trips() {
+        let doc = Document { title: "Deploy".to_string(), tags: vec!["ops".to_string()], body: "1. Bump".to_string() };
+        assert_eq!(Document::parse(&doc.render()).unwrap(), doc);
+        let empty = Document { body: String::new(), ..doc };
+        assert_eq!(Document::parse(&empty.render()).unwrap().body, "");
+    }
+}
@@ -0,0 +1,421 @@
+use crate::dates;
+use crate::models::{normalize_tag, Priority, Status};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+
+/// A parsed task filter such as
+/// `priority>=high and tag:bug and due<friday and not status:done`.
+///
+/// Terms are `field<op>value` with `:`, `=`, `!=`, `<`, `<=`, `>`, `>=`;
+/// bare words match the title or description. Terms combine with `and`
+/// (also implied by juxtaposition), `or`, `not` and parentheses. Values with
+/// spaces are quoted: `due<"next friday"`.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Cond(Condition),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    pub fn sql(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+}
+
+/// A date bound, resolved to the start of a calendar day so that
+/// `due<friday` means "before friday" no matter what time of day the date
+/// phrase produced.
+#[derive(Debug, Clone)]
+pub enum DateCond {
+    None,
+    Any,
+    Cmp(CmpOp, DateTime<Local>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Condition {
+    Id(CmpOp, i64),
+    Status(CmpOp, Status),
+    Priority(CmpOp, Priority),
+    /// Not done and waiting on at least one unfinished dependency.
+    Blocked,
+    Tag(String),
+    Due(DateCond),
+    Created(DateCond),
+    Completed(DateCond),
+    Title(String),
+    Project(String),
+    Text(String),
+    /// Added on this branch or on none; used for per-branch scoping rather
+    /// than parsed from filter expressions.
+    Branch(String),
+}
+
+impl Filter {
+    pub fn parse(input: &str, now: DateTime<Local>) -> Result<Filter> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            bail!("Empty filter");
+        }
+        let mut parser = Parser { tokens, pos: 0, now };
+        let filter = parser.or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {} in filter", token.describe());
+        }
+        Ok(filter)
+    }
+
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    /// Combines optional filters with `and`.
+    pub fn all(filters: impl IntoIterator<Item = Filter>) -> Option<Filter> {
+        filters.into_iter().reduce(Filter::and)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term { field: Option<String>, op: CmpOp, value: String },
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::And => "'and'".to_string(),
+            Token::Or => "'or'".to_string(),
+            Token::Not => "'not'".to_string(),
+            Token::Term { value, .. } => format!("'{}'", value),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+            continue;
+        }
+        if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+            continue;
+        }
+
+        // A term is `[field op] value`, where the value may be quoted.
+        let start = i;
+        while i < chars.len() && is_word_char(chars[i]) {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+
+        let op = match (chars.get(i), chars.get(i + 1)) {
+            (Some('<'), Some('=')) => Some((CmpOp::Le, 2)),
+            (Some('>'), Some('=')) => Some((CmpOp::Ge, 2)),
+            (Some('!'), Some('=')) => Some((CmpOp::Ne, 2)),
+            (Some('<'), _) => Some((CmpOp::Lt, 1)),
+            (Some('>'), _) => Some((CmpOp::Gt, 1)),
+            (Some('='), _) | (Some(':'), _) => Some((CmpOp::Eq, 1)),
+            _ => None,
+        };
+
+        let (field, op) = match op {
+            Some((op, len)) if !word.is_empty() => {
+                i += len;
+                (Some(word.to_lowercase()), op)
+            }
+            _ => {
+                if !word.is_empty() {
+                    tokens.push(match word.to_lowercase().as_str() {
+                        "and" => Token::And,
+                        "or" => Token::Or,
+                        "not" => Token::Not,
+                        _ => Token::Term { field: None, op: CmpOp::Eq, value: word },
+                    });
+                    continue;
+                }
+                (None, CmpOp::Eq)
+            }
+        };
+
+        let value = if chars.get(i) == Some(&'"') {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&c| c == '"')
+                .ok_or_else(|| anyhow!("Unterminated quote in filter"))?;
+            let value: String = chars[i + 1..i + 1 + end].iter().collect();
+            i += end + 2;
+            value
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+                i += 1;
+            }
+            chars[start..i].iter().collect()
+        };
+
+        if value.is_empty() {
+            match field {
+                Some(f) => bail!("Missing value for '{}' in filter", f),
+                None => bail!("Unexpected character '{}' in filter", chars[i.min(chars.len() - 1)]),
+            }
+        }
+        tokens.push(Token::Term { field, op, value });
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | ':' | '=' | '<' | '>' | '!' | '"')
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    now: DateTime<Local>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.and()?;
+            left = Filter::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+        let mut left = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                Some(Token::Not) | Some(Token::LParen) | Some(Token::Term { .. }) => {}
+                _ => break,
+            }
+            let right = self.unary()?;
+            left = left.and(right);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Filter> {
+        match self.next() {
+            Some(Token::Not) => Ok(Filter::Not(Box::new(self.unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => bail!("Missing ')' in filter"),
+                }
+            }
+            Some(Token::Term { field, op, value }) => {
+                Ok(Filter::Cond(condition(field.as_deref(), op, &value, self.now)?))
+            }
+            Some(token) => bail!("Unexpected {} in filter", token.describe()),
+            None => bail!("Filter ends unexpectedly"),
+        }
+    }
+}
+
+fn condition(field: Option<&str>, op: CmpOp, value: &str, now: DateTime<Local>) -> Result<Condition> {
+    let Some(field) = field else {
+        return Ok(Condition::Text(value.to_string()));
+    };
+    let equality_only = |name: &str| -> Result<()> {
+        if op != CmpOp::Eq {
+            bail!("'{}' only supports ':' in filters", name);
+        }
+        Ok(())
+    };
+
+    let cond = match field {
+        "id" => Condition::Id(op, value.parse().map_err(|_| anyhow!("Invalid id '{}'", value))?),
+        "status" | "is" if value.eq_ignore_ascii_case("blocked") => {
+            equality_only("blocked")?;
+            Condition::Blocked
+        }
+        "status" | "is" => Condition::Status(op, value.parse().map_err(|e: String| anyhow!(e))?),
+        "priority" | "pri" | "p" => Condition::Priority(op, value.parse().map_err(|e: String| anyhow!(e))?),
+        "tag" | "tags" => {
+            equality_only("tag")?;
+            Condition::Tag(normalize_tag(value).ok_or_else(|| anyhow!("Empty tag in filter"))?)
+        }
+        "due" => Condition::Due(date_condition(op, value, now)?),
+        "created" => Condition::Created(date_condition(op, value, now)?),
+        "completed" => Condition::Completed(date_condition(op, value, now)?),
+        "title" => {
+            equality_only("title")?;
+            Condition::Title(value.to_string())
+        }
+        "project" => {
+            equality_only("project")?;
+            Condition::Project(value.to_string())
+        }
+        _ => bail!("Unknown filter field '{}'", field),
+    };
+    Ok(cond)
+}
+
+fn date_condition(op: CmpOp, value: &str, now: DateTime<Local>) -> Result<DateCond> {
+    match (op, value.to_lowercase().as_str()) {
+        (CmpOp::Eq, "none") | (CmpOp::Ne, "any") => return Ok(DateCond::None),
+        (CmpOp::Eq, "any") | (CmpOp::Ne, "none") => return Ok(DateCond::Any),
+        _ => {}
+    }
+    let day = dates::start_of_day(dates::parse_due(value, now)?);
+    Ok(DateCond::Cmp(op, day))
+}
+
+/// The first instant after the calendar day that starts at `day`.
+pub fn next_day(day: DateTime<Local>) -> DateTime<Local> {
+    let next = day.date_naive() + Duration::days(1);
+    Local
+        .from_local_datetime(&next.and_time(NaiveTime::MIN))
+        .earliest()
+        .unwrap_or(day + Duration::days(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap()
+    }
+
+    // The parsed tree as an s-expression, e.g. `(or A (and B C))`.
+    fn tree(input: &str) -> String {
+        fn show(filter: &Filter) -> String {
+            match filter {
+                Filter::And(a, b) => format!("(and {} {})", show(a), show(b)),
+                Filter::Or(a, b) => format!("(or {} {})", show(a), show(b)),
+                Filter::Not(a) => format!("(not {})", show(a)),
+                Filter::Cond(cond) => format!("{:?}", cond),
+            }
+        }
+        show(&Filter::parse(input, now()).unwrap())
+    }
+
+    fn error(input: &str) -> String {
+        Filter::parse(input, now()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(tree("a or b c"), r#"(or Text("a") (and Text("b") Text("c")))"#);
+        assert_eq!(tree("a b or c"), r#"(or (and Text("a") Text("b")) Text("c"))"#);
+        assert_eq!(tree("a and b or c and d"), r#"(or (and Text("a") Text("b")) (and Text("c") Text("d")))"#);
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(tree("(a or b) c"), r#"(and (or Text("a") Text("b")) Text("c"))"#);
+        assert_eq!(tree("a (b or c)"), r#"(and Text("a") (or Text("b") Text("c")))"#);
+    }
+
+    #[test]
+    fn not_applies_to_the_next_term() {
+        assert_eq!(tree("not a b"), r#"(and (not Text("a")) Text("b"))"#);
+        assert_eq!(tree("not (a or b)"), r#"(not (or Text("a") Text("b")))"#);
+        assert_eq!(tree("not not a"), r#"(not (not Text("a")))"#);
+    }
+
+    #[test]
+    fn fields_and_operators() {
+        assert_eq!(tree("status:todo"), "Status(Eq, Todo)");
+        assert_eq!(tree("priority>=high"), "Priority(Ge, High)");
+        assert_eq!(tree("p!=low"), "Priority(Ne, Low)");
+        assert_eq!(tree("id<10"), "Id(Lt, 10)");
+        assert_eq!(tree("TAG:Bug"), r#"Tag("bug")"#);
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces() {
+        assert_eq!(tree(r#"title:"login page""#), r#"Title("login page")"#);
+        assert_eq!(tree(r#""two words""#), r#"Text("two words")"#);
+        assert_eq!(tree(r#"title:"a or b" c"#), r#"(and Title("a or b") Text("c"))"#);
+    }
+
+    #[test]
+    fn special_values() {
+        assert_eq!(tree("status:blocked"), "Blocked");
+        assert_eq!(tree("is:blocked"), "Blocked");
+        assert_eq!(tree("due:none"), "Due(None)");
+        assert_eq!(tree("due!=none"), "Due(Any)");
+        assert_eq!(tree("due:any"), "Due(Any)");
+        assert_eq!(tree("completed!=any"), "Completed(None)");
+    }
+
+    #[test]
+    fn dates_resolve_to_the_start_of_the_day() {
+        let expected = Local.with_ymd_and_hms(2026, 3, 11, 0, 0, 0).unwrap();
+        match Filter::parse("due<tomorrow", now()).unwrap() {
+            Filter::Cond(Condition::Due(DateCond::Cmp(CmpOp::Lt, day))) => assert_eq!(day, expected),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(r#"title:"unfinished"#), "Unterminated quote in filter");
+        assert_eq!(error("(a or b"), "Missing ')' in filter");
+        assert_eq!(error("a)"), "Unexpected ')' in filter");
+        assert_eq!(error("colour:red"), "Unknown filter field 'colour'");
+        assert_eq!(error("due:"), "Missing value for 'due' in filter");
+        assert_eq!(error("a or"), "Filter ends unexpectedly");
+        assert_eq!(error(""), "Empty filter");
+        assert_eq!(error("status>blocked"), "'blocked' only supports ':' in filters");
+        assert_eq!(error("tag>x"), "'tag' only supports ':' in filters");
+        assert_eq!(error("id:abc"), "Invalid id 'abc'");
+    }
+}
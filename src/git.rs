@@ -0,0 +1,306 @@
+use anyhow::{bail, Context, Result};
+use git2::{build::CheckoutBuilder, Branch, BranchType, Repository, Worktree};
+use std::path::{Path, PathBuf};
+
+use crate::models::Task;
+
+/// Longest slug taken from a task title, in characters.
+const MAX_SLUG_LEN: usize = 40;
+
+/// Lowercase ASCII words of `title` joined by dashes, e.g.
+/// "Fix login bug (SSO)" becomes "fix-login-bug-sso".
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    if slug.len() > MAX_SLUG_LEN {
+        // Cut at a word boundary when there is one.
+        slug.truncate(MAX_SLUG_LEN);
+        if let Some(dash) = slug.rfind('-') {
+            slug.truncate(dash);
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Fills in a branch template such as `{id}-{slug}` for `task`.
+pub fn branch_name(template: &str, task: &Task) -> Result<String> {
+    let name = template
+        .replace("{id}", &task.id.unwrap_or(0).to_string())
+        .replace("{slug}", &slugify(&task.title));
+    if !Branch::name_is_valid(&name)? {
+        bail!("'{}' is not a valid branch name (check the branch_template setting)", name);
+    }
+    Ok(name)
+}
+
+/// Checks out `name` in the repository containing `dir`, creating it from
+/// HEAD first if needed. Returns whether the branch was created.
+pub fn checkout_branch(dir: &Path, name: &str) -> Result<bool> {
+    let repo = Repository::discover(dir).context("Not inside a git repository")?;
+    let (branch, created) = match repo.find_branch(name, BranchType::Local) {
+        Ok(branch) => (branch, false),
+        Err(_) => {
+            let head = repo
+                .head()
+                .and_then(|h| h.peel_to_commit())
+                .context("Cannot create a branch before the first commit")?;
+            (repo.branch(name, &head, false)?, true)
+        }
+    };
+
+    let reference = branch.into_reference();
+    let target = reference.peel(git2::ObjectType::Commit)?;
+    // A safe checkout refuses to overwrite local changes that conflict with
+    // the target instead of discarding them.
+    repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+        .with_context(|| format!("Could not check out '{}'", name))?;
+    let refname = reference.name().context("Branch name is not valid UTF-8")?;
+    repo.set_head(refname)?;
+    Ok(created)
+}
+
+/// The branch checked out in the repository containing `dir`, if any.
+pub fn current_branch(dir: &Path) -> Option<String> {
+    let repo = Repository::discover(dir).ok()?;
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    head.shorthand().map(str::to_string)
+}
+
+/// The message of the commit checked out in the repository containing `dir`.
+pub fn head_message(dir: &Path) -> Result<String> {
+    let repo = Repository::discover(dir).context("Not inside a git repository")?;
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.message().context("Commit message is not valid UTF-8")?.to_string())
+}
+
+/// The default branch of the repository containing `dir`: the one
+/// `origin/HEAD` points to, else a local `main` or `master`, else
+/// `init.defaultBranch`.
+pub fn default_branch(dir: &Path) -> Option<String> {
+    let repo = Repository::discover(dir).ok()?;
+    if let Ok(head) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Some(name) = head.symbolic_target().and_then(|t| t.strip_prefix("refs/remotes/origin/")) {
+            return Some(name.to_string());
+        }
+    }
+    if let Some(name) = ["main", "master"].into_iter().find(|n| repo.find_branch(n, BranchType::Local).is_ok()) {
+        return Some(name.to_string());
+    }
+    repo.config().ok()?.get_string("init.defaultBranch").ok()
+}
+
+/// Where the repository containing `dir` looks for hooks, honouring
+/// `core.hooksPath`. Linked worktrees share the hooks of the main one.
+pub fn hooks_dir(dir: &Path) -> Result<PathBuf> {
+    let repo = Repository::discover(dir).context("Not inside a git repository")?;
+    if let Ok(path) = repo.config()?.get_path("core.hooksPath") {
+        let base = repo.workdir().unwrap_or_else(|| repo.path());
+        return Ok(base.join(path));
+    }
+    Ok(common_dir(&repo).join("hooks"))
+}
+
+/// The `.git` directory shared by all worktrees of a repository. Linked
+/// worktrees record it, relative to their own git directory, in a
+/// `commondir` file.
+fn common_dir(repo: &Repository) -> PathBuf {
+    if !repo.is_worktree() {
+        return repo.path().to_path_buf();
+    }
+    match std::fs::read_to_string(repo.path().join("commondir")) {
+        Ok(relative) => {
+            let dir = repo.path().join(relative.trim());
+            dir.canonicalize().unwrap_or(dir)
+        }
+        Err(_) => repo.path().to_path_buf(),
+    }
+}
+
+/// The main worktree of a repository opened from one of its linked
+/// worktrees; `None` when `repo` is the main worktree.
+pub fn main_worktree(repo: &Repository) -> Option<PathBuf> {
+    if !repo.is_worktree() {
+        return None;
+    }
+    let main = Repository::open(common_dir(repo)).ok()?;
+    main.workdir().map(Path::to_path_buf)
+}
+
+/// The name git gave a linked worktree (the last part of its path unless
+/// that was taken).
+pub fn worktree_name(repo: &Repository) -> Option<String> {
+    if !repo.is_worktree() {
+        return None;
+    }
+    let worktree = Worktree::open_from_repository(repo).ok()?;
+    worktree.name().map(str::to_string)
+}
+
+/// The repository that has `repo` checked out as one of its submodules.
+pub fn superproject(repo: &Repository) -> Option<Repository> {
+    let workdir = repo.workdir()?;
+    let parent = Repository::discover(workdir.parent()?).ok()?;
+    let relative = workdir.strip_prefix(parent.workdir()?).ok()?;
+    let is_submodule = parent.submodules().ok()?.iter().any(|s| s.path() == relative);
+    is_submodule.then_some(parent)
+}
+
+/// A name for the repository that survives moving or re-cloning it: the
+/// normalized URL of its origin (or only) remote, or else the hash of its
+/// first commit. `None` before the first commit of a repository without
+/// remotes.
+pub fn repository_identity(repo: &Repository) -> Option<String> {
+    let remote = repo.find_remote("origin").ok().or_else(|| {
+        let names = repo.remotes().ok()?;
+        let name = names.iter().flatten().next()?;
+        repo.find_remote(name).ok()
+    });
+    if let Some(url) = remote.as_ref().and_then(|r| r.url()).and_then(normalize_remote_url) {
+        return Some(url);
+    }
+
+    // Following first parents finds the same root in every clone.
+    let mut commit = repo.head().ok()?.peel_to_commit().ok()?;
+    while let Ok(parent) = commit.parent(0) {
+        commit = parent;
+    }
+    Some(format!("commit:{}", commit.id()))
+}
+
+/// Reduces the spellings of a remote to one: "git@GitHub.com:User/Repo.git",
+/// "ssh://git@github.com:22/User/Repo" and "https://token@github.com/User/Repo/"
+/// all become "github.com/User/Repo". Only the host is case-insensitive; many
+/// servers tell paths apart by case. `None` for remotes on the local disk,
+/// whose path says no more about the repository than a clone's does.
+pub fn normalize_remote_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+    // Like git, treat "host:path" as scp-style ssh unless a slash comes first.
+    let scp_like = scheme.is_none() && rest.find(':').is_some_and(|colon| !rest[..colon].contains('/'));
+    if scheme.is_some_and(|s| s.eq_ignore_ascii_case("file")) || (scheme.is_none() && !scp_like) {
+        return None;
+    }
+
+    let (host, path) = if scp_like {
+        rest.split_once(':').unwrap_or((rest, ""))
+    } else {
+        rest.split_once('/').unwrap_or((rest, ""))
+    };
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split(':').next().unwrap_or(host);
+    let normalized = format!("{}/{}", host.to_lowercase(), path.trim_start_matches('/'));
+    let trimmed = normalized.trim_end_matches('/');
+    Some(trimmed.strip_suffix(".git").unwrap_or(trimmed).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, Status};
+    use chrono::Local;
+
+    fn task(id: i64, title: &str) -> Task {
+        Task {
+            id: Some(id),
+            title: title.to_string(),
+            description: None,
+            status: Status::Todo,
+            priority: Priority::Medium,
+            due_date: None,
+            project_path: None,
+            created_at: Local::now(),
+            tags: Vec::new(),
+            blocked_by: Vec::new(),
+            parent_id: None,
+            subtasks_total: 0,
+            subtasks_done: 0,
+            recurrence: None,
+            recur_from_completion: false,
+            branch: None,
+            completed_at: None,
+            scope_branch: None,
+        }
+    }
+
+    #[test]
+    fn slugs_are_lowercase_words() {
+        assert_eq!(slugify("Fix login bug (SSO)"), "fix-login-bug-sso");
+        assert_eq!(slugify("  --Ünïcode & more!  "), "n-code-more");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn long_slugs_are_cut_at_a_word() {
+        let slug = slugify("Rewrite the configuration loader to support layered overrides");
+        assert_eq!(slug, "rewrite-the-configuration-loader-to");
+        assert!(slug.len() <= MAX_SLUG_LEN);
+        assert_eq!(slugify(&"x".repeat(50)).len(), MAX_SLUG_LEN);
+    }
+
+    #[test]
+    fn branch_names_fill_in_the_template() {
+        let task = task(12, "Fix login bug");
+        assert_eq!(branch_name("{id}-{slug}", &task).unwrap(), "12-fix-login-bug");
+        assert_eq!(branch_name("feature/{slug}", &task).unwrap(), "feature/fix-login-bug");
+        let error = branch_name("{slug}..{id}", &task).unwrap_err().to_string();
+        assert!(error.starts_with("'fix-login-bug..12' is not a valid branch name"), "{}", error);
+    }
+
+    #[test]
+    fn checking_out_creates_the_branch_once() {
+        let dir = std::env::temp_dir().join(format!("flow-git-checkout-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        assert!(checkout_branch(&dir, "1-first").is_err(), "no commit to branch from yet");
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+
+        let created = checkout_branch(&dir, "1-first");
+        let branch = current_branch(&dir);
+        let again = checkout_branch(&dir, "1-first");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(created.unwrap());
+        assert_eq!(branch.as_deref(), Some("1-first"));
+        assert!(!again.unwrap());
+    }
+
+    #[test]
+    fn remote_spellings_normalize_alike() {
+        for url in [
+            "git@GitHub.com:User/Repo.git",
+            "ssh://git@github.com:22/User/Repo",
+            "https://token@GITHUB.COM/User/Repo/",
+            "https://github.com/User/Repo.git",
+            "HTTPS://github.com/User/Repo",
+        ] {
+            assert_eq!(normalize_remote_url(url).as_deref(), Some("github.com/User/Repo"), "{}", url);
+        }
+    }
+
+    #[test]
+    fn remote_paths_keep_their_case() {
+        assert_ne!(normalize_remote_url("git@host:Team/app"), normalize_remote_url("git@host:team/app"));
+    }
+
+    #[test]
+    fn local_remotes_have_no_identity() {
+        assert_eq!(normalize_remote_url("/srv/git/app.git"), None);
+        assert_eq!(normalize_remote_url("../app"), None);
+        assert_eq!(normalize_remote_url("file:///srv/git/app.git"), None);
+        assert_eq!(normalize_remote_url("FILE:///srv/git/app.git"), None);
+        assert_eq!(normalize_remote_url("./dir:with/colon"), None);
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::models::{Status, Task};
+
+/// Hooks installed by `flow hooks install`.
+pub const HOOKS: &[&str] = &["prepare-commit-msg", "commit-msg", "post-commit"];
+
+/// First comment line of every hook script flow writes, used to recognize
+/// them again so foreign hooks are never overwritten or removed.
+const MARKER: &str = "# Installed by flow (flow hooks install)";
+
+pub const TRAILER: &str = "Flow-Task";
+
+/// Words that turn a following `flow#<id>` into a request to complete it.
+const CLOSING_WORDS: &[&str] = &["close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallOutcome {
+    Installed,
+    Updated,
+    /// Another tool's hook is in the way.
+    Skipped,
+}
+
+/// Writes a hook script that hands over to `exe hooks run <name>`.
+pub fn install(hooks_dir: &Path, name: &str, exe: &Path, force: bool) -> Result<InstallOutcome> {
+    let path = hooks_dir.join(name);
+    let outcome = match fs::read_to_string(&path) {
+        Ok(existing) if existing.contains(MARKER) => InstallOutcome::Updated,
+        Ok(_) if !force => return Ok(InstallOutcome::Skipped),
+        _ => InstallOutcome::Installed,
+    };
+
+    let script = format!(
+        "#!/bin/sh\n{}\nexec '{}' hooks run {} \"$@\"\n",
+        MARKER,
+        exe.to_string_lossy().replace('\'', "'\\''"),
+        name
+    );
+    fs::create_dir_all(hooks_dir)?;
+    fs::write(&path, script).with_context(|| format!("Could not write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(outcome)
+}
+
+/// Removes a hook if flow installed it. Returns whether it was removed.
+pub fn uninstall(hooks_dir: &Path, name: &str) -> Result<bool> {
+    let path = hooks_dir.join(name);
+    match fs::read_to_string(&path) {
+        Ok(existing) if existing.contains(MARKER) => {
+            fs::remove_file(&path)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// A commit message for `tasks`: the first one gives the subject, the rest
+/// are listed in the body, and every task gets a `Flow-Task` trailer.
+pub fn suggest_message(tasks: &[Task]) -> Option<String> {
+    let (first, rest) = tasks.split_first()?;
+    let mut message = first.title.clone();
+    if !rest.is_empty() {
+        message.push_str("\n\n");
+        let lines: Vec<String> = rest
+            .iter()
+            .map(|t| match t.status {
+                Status::Done => format!("- {} (done)", t.title),
+                Status::InProgress => format!("- {} (in progress)", t.title),
+                Status::Todo => format!("- {}", t.title),
+            })
+            .collect();
+        message.push_str(&lines.join("\n"));
+    }
+    let ids: Vec<i64> = tasks.iter().filter_map(|t| t.id).collect();
+    Some(insert_trailers(&message, &ids))
+}
+
+/// True when the message has nothing but comments and blank lines.
+pub fn is_blank(message: &str) -> bool {
+    message.lines().all(|l| l.trim().is_empty() || l.starts_with('#'))
+}
+
+/// Adds `Flow-Task: <id>` trailers that the message doesn't already carry.
+/// Trailers join an existing trailer block at the end of the message, and
+/// git's comment lines stay below them.
+pub fn insert_trailers(message: &str, ids: &[i64]) -> String {
+    let lines: Vec<&str> = message.lines().collect();
+    let split = lines.iter().position(|l| l.starts_with('#')).unwrap_or(lines.len());
+    let (body, comments) = lines.split_at(split);
+    let mut body: Vec<String> = body.iter().map(|l| l.to_string()).collect();
+    while body.last().is_some_and(|l| l.trim().is_empty()) {
+        body.pop();
+    }
+
+    let existing = task_trailers(message);
+    let new: Vec<String> = ids
+        .iter()
+        .filter(|id| !existing.contains(id))
+        .map(|id| format!("{}: {}", TRAILER, id))
+        .collect();
+    if new.is_empty() {
+        return message.to_string();
+    }
+
+    let paragraph_start = body.iter().rposition(|l| l.trim().is_empty()).map_or(0, |i| i + 1);
+    let ends_with_trailers = paragraph_start > 0 && body[paragraph_start..].iter().all(|l| is_trailer(l));
+    if !body.is_empty() && !ends_with_trailers {
+        body.push(String::new());
+    }
+    body.extend(new);
+
+    let mut out = body.join("\n");
+    out.push('\n');
+    if !comments.is_empty() {
+        out.push('\n');
+        out.push_str(&comments.join("\n"));
+        out.push('\n');
+    }
+    out
+}
+
+/// IDs from `Flow-Task` trailers in a message.
+pub fn task_trailers(message: &str) -> Vec<i64> {
+    message
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case(TRAILER))
+        .filter_map(|(_, value)| value.trim().parse().ok())
+        .collect()
+}
+
+/// Task IDs referenced as "closes flow#12" (or fixes/resolves) outside of
+/// comment lines.
+pub fn closed_tasks(message: &str) -> Vec<i64> {
+    let words: Vec<String> = message
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .flat_map(|l| l.split_whitespace())
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '#').to_lowercase())
+        .collect();
+
+    let mut ids = Vec::new();
+    for pair in words.windows(2) {
+        if !CLOSING_WORDS.contains(&pair[0].as_str()) {
+            continue;
+        }
+        if let Some(id) = pair[1].strip_prefix("flow#").and_then(|n| n.parse().ok()) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+fn is_trailer(line: &str) -> bool {
+    match line.split_once(": ") {
+        Some((key, _)) => !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Priority;
+    use chrono::Local;
+
+    fn task(id: i64, title: &str, status: Status) -> Task {
+        Task {
+            id: Some(id),
+            title: title.to_string(),
+            description: None,
+            status,
+            priority: Priority::Medium,
+            due_date: None,
+            project_path: None,
+            created_at: Local::now(),
+            tags: Vec::new(),
+            blocked_by: Vec::new(),
+            parent_id: None,
+            subtasks_total: 0,
+            subtasks_done: 0,
+            recurrence: None,
+            recur_from_completion: false,
+            branch: None,
+            completed_at: None,
+            scope_branch: None,
+        }
+    }
+
+    #[test]
+    fn trailers_start_a_new_paragraph() {
+        assert_eq!(insert_trailers("Fix login\n", &[12]), "Fix login\n\nFlow-Task: 12\n");
+        assert_eq!(insert_trailers("Fix login\n\nBody text.\n\n\n", &[3, 4]), "Fix login\n\nBody text.\n\nFlow-Task: 3\nFlow-Task: 4\n");
+        assert_eq!(insert_trailers("", &[1]), "Flow-Task: 1\n");
+    }
+
+    #[test]
+    fn trailers_stay_above_comment_lines() {
+        let message = "Fix login\n\n# Please enter the commit message for your changes.\n# On branch main\n";
+        assert_eq!(
+            insert_trailers(message, &[12]),
+            "Fix login\n\nFlow-Task: 12\n\n# Please enter the commit message for your changes.\n# On branch main\n"
+        );
+    }
+
+    #[test]
+    fn trailers_join_an_existing_trailer_block() {
+        let message = "Fix login\n\nSigned-off-by: Dev <dev@example.com>\nFlow-Task: 3\n";
+        assert_eq!(
+            insert_trailers(message, &[3, 12]),
+            "Fix login\n\nSigned-off-by: Dev <dev@example.com>\nFlow-Task: 3\nFlow-Task: 12\n"
+        );
+        // Nothing new leaves the message byte for byte as it was.
+        assert_eq!(insert_trailers(message, &[3]), message);
+        // A subject that merely looks like a trailer is not a trailer block.
+        assert_eq!(insert_trailers("Docs: fix typo\n", &[1]), "Docs: fix typo\n\nFlow-Task: 1\n");
+    }
+
+    #[test]
+    fn trailers_and_closing_words_are_read_back() {
+        let message = "Fix login, closes flow#12 and Fixes FLOW#3.\n\nAlso see flow#4.\n# resolves flow#9\n\nflow-task: 7\n";
+        assert_eq!(task_trailers(message), [7]);
+        assert_eq!(closed_tasks(message), [12, 3]);
+        assert!(closed_tasks("closes flow#abc, close #5").is_empty());
+    }
+
+    #[test]
+    fn suggested_message_lists_every_task() {
+        let tasks = [task(1, "Fix login", Status::InProgress), task(2, "Add tests", Status::Done), task(3, "Update docs", Status::Todo)];
+        assert_eq!(
+            suggest_message(&tasks).unwrap(),
+            "Fix login\n\n- Add tests (done)\n- Update docs\n\nFlow-Task: 1\nFlow-Task: 2\nFlow-Task: 3\n"
+        );
+        assert_eq!(suggest_message(&[]), None);
+    }
+
+    #[test]
+    fn messages_of_only_comments_are_blank() {
+        assert!(is_blank("\n# Please enter the commit message\n#\n"));
+        assert!(!is_blank("Fix login\n# comment\n"));
+    }
+
+    #[test]
+    fn install_leaves_foreign_hooks_alone() {
+        let dir = std::env::temp_dir().join(format!("flow-hooks-install-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("commit-msg"), "#!/bin/sh\nexit 0\n").unwrap();
+        let exe = Path::new("/usr/local/bin/flow");
+
+        let outcomes = [
+            install(&dir, "post-commit", exe, false).unwrap(),
+            install(&dir, "post-commit", exe, false).unwrap(),
+            install(&dir, "commit-msg", exe, false).unwrap(),
+        ];
+        let foreign = fs::read_to_string(dir.join("commit-msg")).unwrap();
+        let script = fs::read_to_string(dir.join("post-commit")).unwrap();
+        let removed = [uninstall(&dir, "commit-msg").unwrap(), uninstall(&dir, "post-commit").unwrap()];
+        let forced = install(&dir, "commit-msg", exe, true).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(outcomes, [InstallOutcome::Installed, InstallOutcome::Updated, InstallOutcome::Skipped]);
+        assert_eq!(foreign, "#!/bin/sh\nexit 0\n");
+        assert!(script.contains(MARKER) && script.contains("exec '/usr/local/bin/flow' hooks run post-commit \"$@\""));
+        assert_eq!(removed, [false, true]);
+        assert_eq!(forced, InstallOutcome::Installed);
+    }
+}
@@ -0,0 +1,39 @@
+/// Length of the string literal at the start of `text`, up to and including
+/// its closing quote (or the end of the line if it doesn't close). A
+/// backslash escapes the character after it.
+pub fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_ends_at_its_closing_quote() {
+        assert_eq!(string_len(r#""abc" + x"#, '"'), 5);
+        assert_eq!(string_len("'a' 'b'", '\''), 3);
+        assert_eq!(string_len(r#""" rest"#, '"'), 2);
+    }
+
+    #[test]
+    fn escaped_quotes_do_not_close_a_string() {
+        assert_eq!(string_len(r#""say \"hi\"" x"#, '"'), 12);
+        assert_eq!(string_len(r#""ends in \\" x"#, '"'), 12);
+    }
+
+    #[test]
+    fn unclosed_string_runs_to_the_end() {
+        assert_eq!(string_len("\"never closed", '"'), 13);
+        assert_eq!(string_len("'é", '\''), 3);
+    }
+}
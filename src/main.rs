@@ -1,7 +1,22 @@
 mod cli;
+mod config;
 mod context;
+mod dates;
 mod db;
+mod editor;
+mod markdown;
+mod filter;
+mod git;
+mod hooks;
+mod lexer;
+mod migrations;
 mod models;
+mod output;
+mod quickadd;
+mod recur;
+mod scan;
+mod sort;
+mod tree;
 mod tui;
 
 use clap::Parser;
@@ -9,131 +24,771 @@ use colored::*;
 use anyhow::Result;
 use crate::cli::{Args, Commands};
 use crate::db::TaskRepository;
-use crate::models::{Task, Priority, Status};
-use crate::context::ContextManager;
+use crate::models::{normalize_tag, Completion, Task, TimeEntry, TimeGrouping, TaskUpdate, Priority, Status, SEARCH_MATCH_END, SEARCH_MATCH_START};
+use crate::config::Config;
+use crate::context::{ContextManager, Target};
+use crate::filter::{CmpOp, Condition, Filter};
+use crate::output::{outln, Format};
+use crate::quickadd::{Meaning, QuickAdd};
+use crate::recur::Recurrence;
+use crate::sort::{group_tasks, GroupBy, SortField, SortKey};
+use crate::tree::build_tree;
+use anyhow::Context;
 use chrono::Local;
-use chrono_english::{parse_date_string, Dialect};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io::{self, IsTerminal};
 
 fn main() -> Result<()> {
+    match run() {
+        // A reader such as `head` that stops early closes the pipe; the
+        // output it wanted has been written, so that's not a failure.
+        Err(err) if is_broken_pipe(&err) => Ok(()),
+        result => result,
+    }
+}
+
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|e| e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe))
+}
+
+fn run() -> Result<()> {
     let args = Args::parse();
-    let repo = TaskRepository::init()?;
+    let format = args.format;
+
+    // Colors only make sense on a terminal; keep piped output clean.
+    if !io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    // A broken hook must never stand in the way of a commit, so hooks load
+    // the config and database themselves and only ever report failures.
+    if let Some(Commands::Hooks(cli::HookCommands::Run { hook, args })) = &args.command {
+        if let Err(err) = run_hook(hook, args) {
+            eprintln!("flow: {} hook failed: {:#}", hook, err);
+        }
+        return Ok(());
+    }
+
+    // Schema commands must see the database as it is on disk, before any
+    // migration has been applied.
+    if let Some(Commands::Db(db_cmd)) = args.command {
+        let mut repo = TaskRepository::open()?;
+        return run_db_command(&mut repo, db_cmd);
+    }
+
+    let config = Config::load()?;
+    let mut repo = TaskRepository::init()?;
+    repo.set_auto_complete_parents(config.auto_complete_parents);
+    let target = args.target();
 
     match args.command {
-        Some(Commands::Add { title, priority, due }) => {
-            let context_path = ContextManager::get_context()?;
-            let project_path = context_path.to_string_lossy().to_string();
-            
-            let due_date = if let Some(d) = due {
-                Some(parse_date_string(&d, Local::now(), Dialect::Us)?)
+        Some(Commands::Add { title, priority, due, tags, parent, every, after_completion, literal, explain, edit }) => {
+            let input = title.join(" ");
+            let now = Local::now();
+            let parsed = if literal {
+                QuickAdd::literal(&input)
             } else {
-                None
+                quickadd::parse(&input, now)?
             };
 
+            if explain {
+                print_explanation(&parsed)?;
+                return Ok(());
+            }
+
+            // An inline project: wins over --project and --global, and
+            // subtasks live in their parent's project unless told otherwise.
+            let target = match &parsed.project {
+                Some(p) => Target::Named(p.clone()),
+                None => target,
+            };
+            let parent_project = match parent {
+                Some(p) => Some(repo.get_task(p).with_context(|| format!("Parent task {} not found", p))?.project_path),
+                None => None,
+            };
+            let (project_path, scope_branch, location) = match parent_project {
+                Some(parent_project) if target == Target::Current => {
+                    let location = parent_project.clone().unwrap_or_else(|| "(global)".to_string());
+                    (parent_project, None, location)
+                }
+                _ => {
+                    let mut context = ContextManager::select(&repo, &config, &target)?;
+                    let project_path = context.project_path(&repo)?;
+                    (project_path, context.scope_branch(), context.describe())
+                }
+            };
+            
+            // Explicit flags win over inline attributes.
+            let due_date = match due {
+                Some(d) => Some(dates::parse_due(&d, now)?),
+                None => parsed.due,
+            };
+            let priority = match priority {
+                Some(p) => p.parse::<Priority>().map_err(|e| anyhow::anyhow!(e))?,
+                None => parsed.priority.unwrap_or(Priority::Medium),
+            };
+
+            let recurrence = match every.or(parsed.every) {
+                Some(phrase) => {
+                    let (rule, after) = Recurrence::parse(&phrase)?;
+                    Some((rule.anchored(due_date.unwrap_or(now).date_naive()), after))
+                }
+                None => None,
+            };
+
+            let mut task_tags = parsed.tags;
+            for tag in tags.iter().filter_map(|t| normalize_tag(t)) {
+                if !task_tags.contains(&tag) {
+                    task_tags.push(tag);
+                }
+            }
+
+            let mut title = parsed.title;
+            let mut description = None;
+            if edit {
+                let doc = editor::Document { title, tags: task_tags, body: String::new() };
+                let Some(edited) = editor::edit(&doc)? else {
+                    outln!("Nothing was written; no task added.")?;
+                    return Ok(());
+                };
+                title = edited.title;
+                task_tags = edited.tags.iter().filter_map(|t| normalize_tag(t)).collect();
+                description = Some(edited.body).filter(|b| !b.is_empty());
+            }
+
             let task = Task {
                 id: None,
                 title,
-                description: None,
+                description,
                 status: Status::Todo,
-                priority: Priority::from(priority),
+                priority,
                 due_date,
-                project_path: Some(project_path.clone()),
-                created_at: Local::now(),
-                tags: Vec::new(),
+                project_path,
+                created_at: now,
+                tags: task_tags,
+                blocked_by: Vec::new(),
+                parent_id: parent,
+                subtasks_total: 0,
+                subtasks_done: 0,
+                recurrence: recurrence.as_ref().map(|(rule, _)| rule.to_string()),
+                recur_from_completion: after_completion || recurrence.as_ref().is_some_and(|(_, after)| *after),
+                branch: None,
+                completed_at: None,
+                scope_branch,
             };
 
             let id = repo.add_task(&task)?;
-            println!("{} Task added with ID: {}", "✔".green(), id);
-            println!("   Context: {}", project_path.dimmed());
+            outln!("{} Task added with ID: {}", "✔".green(), id)?;
+            outln!("   Context: {}", location.dimmed())?;
+            if let Some((rule, _)) = &recurrence {
+                outln!("   Repeats: {}{}", rule.describe(), if task.recur_from_completion { " after completion" } else { "" })?;
+            }
         }
-        Some(Commands::Ls { all, status }) => {
-            let context_path = if all {
+        Some(Commands::Ls { query, all, status, tag, sort, group_by, tree }) => {
+            let filter = build_filter(&query, status.as_deref(), &tag)?;
+            let sort = sort.iter().map(|s| SortKey::parse(s)).collect::<Result<Vec<_>>>()?;
+            let group_by = group_by.map(|g| g.parse::<GroupBy>()).transpose()?;
+            let context = if all {
                 None
             } else {
-                Some(ContextManager::get_context()?)
+                Some(ContextManager::select(&repo, &config, &target)?)
             };
             
-            let filter_path = context_path.as_ref().map(|p| p.to_string_lossy());
-            let tasks = repo.get_tasks(filter_path.as_deref())?;
+            let filter_path = context.as_ref().map(|c| c.scope());
+            let filter = Filter::all(filter.into_iter().chain(context.and_then(|c| c.branch_filter())));
+            let tasks = repo.get_tasks(filter_path.as_deref(), filter.as_ref(), &sort)?;
+
+            if format != Format::Table {
+                return output::print_records(format, &tasks, output::TASK_FIELDS);
+            }
 
             if tasks.is_empty() {
-                println!("No tasks found.");
+                outln!("No tasks found.")?;
                 return Ok(());
             }
 
-            println!("{:<4} {:<30} {:<10} {:<10} {:<20}", "ID", "Title", "Status", "Priority", "Due");
-            println!("{}", "-".repeat(80));
+            if let Some((branch, task)) = branch_task(&repo, &config)? {
+                outln!("{} On branch {} for task {}: {}", "⎇".cyan(), branch.bold(), task.id.unwrap_or(0), task.title)?;
+                outln!()?;
+            }
+            print_task_header()?;
+            if tree {
+                for row in build_tree(&tasks, &HashSet::new()) {
+                    print_task_row(row.task, &row.prefix)?;
+                }
+                return Ok(());
+            }
+            match group_by {
+                Some(group_by) => {
+                    for (label, members) in group_tasks(&tasks, group_by, Local::now()) {
+                        outln!()?;
+                        outln!("{} {}", label.bold(), format!("({})", members.len()).dimmed())?;
+                        for task in &members {
+                            print_task_row(task, "")?;
+                        }
+                    }
+                }
+                None => {
+                    for task in &tasks {
+                        print_task_row(task, "")?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Edit {
+            id,
+            title,
+            description,
+            status,
+            priority,
+            due,
+            no_due,
+            tags,
+            add_tag,
+            remove_tag,
+            parent,
+            no_parent,
+            every,
+            no_every,
+            after_completion,
+            on_schedule,
+            edit,
+        }) => {
+            let due_date = if no_due {
+                Some(None)
+            } else if let Some(d) = due {
+                Some(Some(dates::parse_due(&d, Local::now())?))
+            } else {
+                None
+            };
 
-            for task in tasks {
-                if let Some(s) = &status {
-                    if task.status.to_string().to_lowercase() != s.to_lowercase() {
-                        continue;
+            let tags = if !add_tag.is_empty() || !remove_tag.is_empty() {
+                let mut current = repo.get_task(id)?.tags;
+                let removed: Vec<String> = remove_tag.iter().filter_map(|t| normalize_tag(t)).collect();
+                current.retain(|t| !removed.contains(t));
+                for tag in add_tag.iter().filter_map(|t| normalize_tag(t)) {
+                    if !current.contains(&tag) {
+                        current.push(tag);
                     }
                 }
+                Some(current)
+            } else {
+                tags
+            };
 
-                let title = if task.status == Status::Done {
-                    task.title.strikethrough()
-                } else {
-                    task.title.normal()
+            // A rule phrase ending in "after completion" sets the mode too,
+            // unless a flag says otherwise.
+            let (recurrence, phrase_after) = if no_every {
+                (Some(None), None)
+            } else if let Some(phrase) = every {
+                let (rule, after) = Recurrence::parse(&phrase)?;
+                let start = match due_date {
+                    Some(due) => due,
+                    None => repo.get_task(id)?.due_date,
                 };
+                let rule = rule.anchored(start.unwrap_or_else(Local::now).date_naive());
+                (Some(Some(rule.to_string())), Some(after))
+            } else {
+                (None, None)
+            };
+            let recur_from_completion = if after_completion {
+                Some(true)
+            } else if on_schedule {
+                Some(false)
+            } else {
+                phrase_after
+            };
 
-                let priority = match task.priority {
-                    Priority::High | Priority::Critical => task.priority.to_string().red(),
-                    Priority::Medium => task.priority.to_string().yellow(),
-                    Priority::Low => task.priority.to_string().green(),
-                };
+            let mut update = TaskUpdate {
+                title,
+                description: description.map(|d| if d.is_empty() { None } else { Some(d) }),
+                status: status.map(|s| s.parse::<Status>()).transpose().map_err(|e| anyhow::anyhow!(e))?,
+                priority: priority.map(|p| p.parse::<Priority>()).transpose().map_err(|e| anyhow::anyhow!(e))?,
+                due_date,
+                tags,
+                parent_id: if no_parent { Some(None) } else { parent.map(Some) },
+                recurrence,
+                recur_from_completion,
+            };
 
-                let due = match task.due_date {
-                    Some(d) => d.format("%Y-%m-%d %H:%M").to_string(),
-                    None => "-".to_string(),
+            // With nothing else to change, an interactive `flow edit <id>`
+            // opens the task in the editor.
+            if edit || (update.is_empty() && io::stdin().is_terminal() && io::stdout().is_terminal()) {
+                let task = repo.get_task(id)?;
+                let doc = editor::Document {
+                    title: task.title.clone(),
+                    tags: task.tags.clone(),
+                    body: task.description.clone().unwrap_or_default(),
                 };
+                let Some(edited) = editor::edit(&doc)? else {
+                    outln!("No changes; task {} left as it was.", id)?;
+                    return Ok(());
+                };
+                let tags: Vec<String> = edited.tags.iter().filter_map(|t| normalize_tag(t)).collect();
+                update.title = Some(edited.title).filter(|t| *t != task.title);
+                update.tags = Some(tags).filter(|t| *t != task.tags);
+                update.description = Some(Some(edited.body).filter(|b| !b.is_empty())).filter(|d| *d != task.description);
+            }
 
-                println!("{:<4} {:<30} {:<10} {:<10} {:<20}", 
-                    task.id.unwrap_or(0), 
-                    title, 
-                    task.status, 
-                    priority, 
-                    due
-                );
+            if update.is_empty() {
+                outln!("Nothing to update.")?;
+                return Ok(());
             }
+
+            let completion = repo.update_task(id, &update)?;
+            let task = repo.get_task(id)?;
+            outln!("{} Task {} updated: {}", "✔".green(), id, task.title)?;
+            print_completion(&repo, &completion)?;
         }
         Some(Commands::Done { id }) => {
-            repo.complete_task(id)?;
-            println!("{} Task {} marked as done.", "✔".green(), id);
+            let completion = repo.complete_task(id)?;
+            outln!("{} Task {} marked as done.", "✔".green(), id)?;
+            print_completion(&repo, &completion)?;
+        }
+        Some(Commands::Reopen { id }) => {
+            let task = repo.get_task(id)?;
+            if task.status != Status::Done {
+                anyhow::bail!("Task {} is not done", id);
+            }
+            repo.set_status(id, Status::Todo)?;
+            outln!("{} Task {} reopened: {}", "↺".yellow(), id, task.title)?;
+        }
+        Some(Commands::Start { id, branch }) => {
+            let task = repo.get_task(id)?;
+            if task.status == Status::Done {
+                anyhow::bail!("Task {} is already done", id);
+            }
+            if branch {
+                // Branch in the task's own repository, which need not be the
+                // one we are standing in. When we are in another clone of it,
+                // use this one.
+                let context = ContextManager::get_context(&repo, &config)?;
+                let dir = match &task.project_path {
+                    Some(p) if *p == context.scope() => context.root,
+                    Some(p) if Path::new(p).is_dir() => PathBuf::from(p),
+                    _ => context.root,
+                };
+                let name = match &task.branch {
+                    Some(name) => name.clone(),
+                    None => git::branch_name(&config.branch_template, &task)?,
+                };
+                let created = git::checkout_branch(&dir, &name)?;
+                repo.set_task_branch(id, Some(&name))?;
+                outln!("{} {} branch {}", "⎇".cyan(), if created { "Created" } else { "Switched to" }, name.bold())?;
+            }
+            let now = Local::now();
+            if repo.running_timer()?.is_some_and(|e| e.task_id == id) {
+                outln!("{} Already tracking task {}: {}", "▶".green(), id, task.title)?;
+                return Ok(());
+            }
+            if let Some(stopped) = repo.start_timer(id, now)? {
+                print_stopped(&repo, &stopped, now)?;
+            }
+            outln!("{} Started task {}: {}", "▶".green(), id, task.title)?;
+        }
+        Some(Commands::Stop) => {
+            let now = Local::now();
+            match repo.stop_timer(now)? {
+                Some(stopped) => print_stopped(&repo, &stopped, now)?,
+                None => outln!("No timer is running.")?,
+            }
+        }
+        Some(Commands::Time(cli::TimeCommands::Report { since, until, by, all })) => {
+            let now = Local::now();
+            let since = match since {
+                Some(s) => dates::start_of_day(dates::parse_due(&s, now)?),
+                None => dates::start_of_day(now - chrono::Duration::days(6)),
+            };
+            let until = match until {
+                Some(u) => filter::next_day(dates::start_of_day(dates::parse_due(&u, now)?)),
+                None => now,
+            };
+            let context_path = if all {
+                None
+            } else {
+                Some(ContextManager::select(&repo, &config, &target)?.scope())
+            };
+            let filter_path = context_path.as_deref();
+            let totals = repo.time_report(filter_path, since, until, now, by)?;
+
+            if format != Format::Table {
+                return output::print_records(format, &totals, output::TIME_FIELDS);
+            }
+            outln!(
+                "Tracked time from {} to {}",
+                since.format("%Y-%m-%d %H:%M"),
+                until.format("%Y-%m-%d %H:%M")
+            )?;
+            if totals.is_empty() {
+                outln!("Nothing tracked.")?;
+            } else {
+                outln!()?;
+                for total in &totals {
+                    outln!("{:<50} {:>10}", total.group, format_duration(total.seconds))?;
+                }
+                outln!("{}", "-".repeat(61))?;
+                let sum: i64 = match by {
+                    // A task with several tags shows up under each of them.
+                    TimeGrouping::Tag => {
+                        repo.time_report(filter_path, since, until, now, TimeGrouping::Task)?
+                            .iter()
+                            .map(|t| t.seconds)
+                            .sum()
+                    }
+                    _ => totals.iter().map(|t| t.seconds).sum(),
+                };
+                outln!("{:<50} {:>10}", "Total".bold(), format_duration(sum))?;
+            }
+            if let Some(running) = repo.running_timer()? {
+                let task = repo.get_task(running.task_id)?;
+                outln!()?;
+                outln!(
+                    "{} Task {} ({}) running for {}",
+                    "▶".green(),
+                    task.id.unwrap_or(0),
+                    task.title,
+                    format_duration(running.duration(now).num_seconds())
+                )?;
+            }
+        }
+        Some(Commands::CommitMsg) => {
+            let tasks = commit_tasks(&repo, &config)?;
+            match hooks::suggest_message(&tasks) {
+                Some(message) => print!("{}", message),
+                None => anyhow::bail!("No tasks on this branch and none in progress"),
+            }
+        }
+        Some(Commands::Hooks(hook_cmd)) => match hook_cmd {
+            cli::HookCommands::Install { force } => {
+                let dir = git::hooks_dir(&std::env::current_dir()?)?;
+                let exe = std::env::current_exe()?;
+                for name in hooks::HOOKS {
+                    match hooks::install(&dir, name, &exe, force)? {
+                        hooks::InstallOutcome::Installed => outln!("{} Installed {}", "✔".green(), name)?,
+                        hooks::InstallOutcome::Updated => outln!("{} Updated {}", "✔".green(), name)?,
+                        hooks::InstallOutcome::Skipped => outln!(
+                            "{} Skipped {}: a hook not installed by flow is in the way (use --force to replace it)",
+                            "!".yellow(),
+                            name
+                        )?,
+                    }
+                }
+                if !config.close_tasks_on_commit {
+                    outln!("   Run `flow config set close_tasks_on_commit true` to complete tasks on \"closes flow#<id>\".")?;
+                }
+            }
+            cli::HookCommands::Uninstall => {
+                let dir = git::hooks_dir(&std::env::current_dir()?)?;
+                for name in hooks::HOOKS {
+                    if hooks::uninstall(&dir, name)? {
+                        outln!("{} Removed {}", "✔".green(), name)?;
+                    }
+                }
+            }
+            cli::HookCommands::Run { .. } => unreachable!("handled before the database is opened"),
+        },
+        Some(Commands::Scan { dry_run }) => {
+            let (root, files, comments) = scan::scan_repository(&std::env::current_dir()?)?;
+            let context = ContextManager::resolve(&repo, &config, &root)?;
+            let summary = repo.sync_code_comments(&context.scope(), &context.identity, &comments, dry_run)?;
+
+            for (id, comment) in &summary.created {
+                let location = format!("{}:{}", comment.file, comment.line);
+                outln!("  {:<9} {:<5} {}: {} {}", "new", format!("#{}", id), comment.kind, comment.text, location.dimmed())?;
+            }
+            for (label, ids) in [("reopened", &summary.reopened), ("closed", &summary.closed)] {
+                for id in ids {
+                    let task = repo.get_task(*id)?;
+                    let location = task.description.unwrap_or_default();
+                    outln!("  {:<9} {:<5} {} {}", label, format!("#{}", id), task.title, location.dimmed())?;
+                }
+            }
+            outln!(
+                "{} Scanned {} file(s): {} new, {} moved, {} reopened, {} closed, {} unchanged.{}",
+                "✔".green(),
+                files,
+                summary.created.len(),
+                summary.moved.len(),
+                summary.reopened.len(),
+                summary.closed.len(),
+                summary.unchanged,
+                if dry_run { " (dry run, nothing saved)" } else { "" }
+            )?;
+        }
+        Some(Commands::Block { id, on }) => {
+            for dep in &on {
+                repo.add_dependency(id, *dep)?;
+            }
+            let deps = on.iter().map(|d| format!("#{}", d)).collect::<Vec<_>>().join(", ");
+            outln!("{} Task {} is now blocked on {}.", "✔".green(), id, deps)?;
+        }
+        Some(Commands::Unblock { id, on }) => {
+            let removed = if on.is_empty() {
+                repo.remove_dependency(id, None)?
+            } else {
+                let mut removed = 0;
+                for dep in on {
+                    removed += repo.remove_dependency(id, Some(dep))?;
+                }
+                removed
+            };
+            outln!("{} Removed {} dependenc{} from task {}.", "✔".green(), removed, if removed == 1 { "y" } else { "ies" }, id)?;
+        }
+        Some(Commands::Next { all, limit }) => {
+            let context = if all {
+                None
+            } else {
+                Some(ContextManager::select(&repo, &config, &target)?)
+            };
+            let filter_path = context.as_ref().map(|c| c.scope());
+
+            let mut actionable = Filter::Not(Box::new(Filter::Cond(Condition::Status(CmpOp::Eq, Status::Done))))
+                .and(Filter::Not(Box::new(Filter::Cond(Condition::Blocked))));
+            if let Some(branch) = context.and_then(|c| c.branch_filter()) {
+                actionable = actionable.and(branch);
+            }
+            let sort = [
+                SortKey { field: SortField::Priority, descending: true },
+                SortKey { field: SortField::Due, descending: false },
+                SortKey { field: SortField::Created, descending: false },
+            ];
+            let mut tasks = repo.get_tasks(filter_path.as_deref(), Some(&actionable), &sort)?;
+            tasks.truncate(limit);
+
+            if format != Format::Table {
+                return output::print_records(format, &tasks, output::TASK_FIELDS);
+            }
+            if tasks.is_empty() {
+                outln!("Nothing to do right now.")?;
+                return Ok(());
+            }
+            print_task_header()?;
+            for task in &tasks {
+                print_task_row(task, "")?;
+            }
         }
-        Some(Commands::Rm { id }) => {
+        Some(Commands::Rm { id, recursive }) => {
+            let task = repo.get_task(id)?;
+            if task.subtasks_total > 0 && !recursive {
+                anyhow::bail!(
+                    "Task {} has {} subtask(s); use --recursive to remove them too",
+                    id,
+                    task.subtasks_total
+                );
+            }
             repo.delete_task(id)?;
-            println!("{} Task {} removed.", "✔".green(), id);
+            outln!("{} Task {} removed.", "✔".green(), id)?;
+        }
+        Some(Commands::Search { query, all, tasks, notes, limit, raw }) => {
+            let context_path = if all {
+                None
+            } else {
+                Some(ContextManager::select(&repo, &config, &target)?.scope())
+            };
+            let project = context_path.as_deref();
+            let expression = if raw { query.join(" ") } else { fts_query(&query) };
+
+            let mut hits = repo
+                .search(&expression, project, !notes, !tasks, limit)
+                .with_context(|| format!("Search for \"{}\" failed", query.join(" ")))?;
+
+            if format != Format::Table {
+                for hit in &mut hits {
+                    hit.snippet = hit.snippet.replace([SEARCH_MATCH_START, SEARCH_MATCH_END], "");
+                }
+                return output::print_records(format, &hits, output::SEARCH_FIELDS);
+            }
+
+            if hits.is_empty() {
+                outln!("No matches found.")?;
+                return Ok(());
+            }
+
+            for hit in hits {
+                outln!("{} {}", format!("[{} {}]", hit.kind, hit.id).dimmed(), hit.title.bold())?;
+                outln!("    {}", highlight_snippet(&hit.snippet))?;
+            }
         }
+        Some(Commands::Recur { command }) => match command.unwrap_or(cli::RecurCommands::Ls { all: false }) {
+            cli::RecurCommands::Ls { all } => {
+                let context_path = if all {
+                    None
+                } else {
+                    Some(ContextManager::select(&repo, &config, &target)?.scope())
+                };
+                let filter_path = context_path.as_deref();
+                let tasks = repo.get_recurring_tasks(filter_path)?;
+
+                if format != Format::Table {
+                    return output::print_records(format, &tasks, output::TASK_FIELDS);
+                }
+                if tasks.is_empty() {
+                    outln!("No repeating tasks.")?;
+                    return Ok(());
+                }
+                outln!("{:<4} {:<30} {:<32} {:<11} Next due", "ID", "Title", "Repeats", "From")?;
+                outln!("{}", "-".repeat(96))?;
+                for task in &tasks {
+                    let repeats = task
+                        .recurrence
+                        .as_deref()
+                        .map(|r| r.parse::<Recurrence>().map_or_else(|_| r.to_string(), |r| r.describe()))
+                        .unwrap_or_default();
+                    let from = if task.recur_from_completion { "completion" } else { "due date" };
+                    let due = match task.due_date {
+                        Some(d) => d.format("%Y-%m-%d %H:%M").to_string(),
+                        None => "-".to_string(),
+                    };
+                    outln!("{:<4} {:<30} {:<32} {:<11} {}", task.id.unwrap_or(0), task.title, repeats.cyan(), from, due)?;
+                }
+            }
+        },
+        Some(Commands::Project { command }) => match command.unwrap_or(cli::ProjectCommands::Ls) {
+            cli::ProjectCommands::Ls => {
+                let projects = repo.get_projects()?;
+                if format != Format::Table {
+                    return output::print_records(format, &projects, output::PROJECT_FIELDS);
+                }
+                if projects.is_empty() {
+                    outln!("No projects found.")?;
+                    return Ok(());
+                }
+                outln!("{:<4} {:<20} {:>6} {:>6}  Path", "ID", "Name", "Tasks", "Notes")?;
+                outln!("{}", "-".repeat(60))?;
+                for project in projects {
+                    let mut location = project.path.clone();
+                    if !Path::new(&project.path).exists() {
+                        location = format!("{} {}", location, "(missing)".red());
+                    }
+                    if !project.identity.starts_with("path:") {
+                        location = format!("{} {}", location, project.identity.dimmed());
+                    }
+                    outln!(
+                        "{:<4} {:<20} {:>6} {:>6}  {}",
+                        project.id.unwrap_or(0),
+                        project.name.cyan(),
+                        project.tasks,
+                        project.notes,
+                        location
+                    )?;
+                }
+            }
+            cli::ProjectCommands::Rename { key, name } => {
+                let project = repo.find_project(&key)?;
+                repo.rename_project(project.id.unwrap_or(0), &name)?;
+                outln!("{} Renamed project '{}' to '{}'.", "✔".green(), project.name, name.trim())?;
+            }
+            cli::ProjectCommands::Move { key, path } => {
+                let project = repo.find_project(&key)?;
+                let dir = fs::canonicalize(&path).with_context(|| format!("Path {} does not exist", path))?;
+                // Projects live at the root of their repository.
+                let root = ContextManager::locate(&config, &dir).home.to_string_lossy().to_string();
+                repo.move_project(project.id.unwrap_or(0), &root)?;
+                outln!(
+                    "{} Moved project '{}' to {} ({} task(s), {} note(s)).",
+                    "✔".green(),
+                    project.name,
+                    root,
+                    project.tasks,
+                    project.notes
+                )?;
+            }
+            cli::ProjectCommands::Merge { sources, into } => {
+                let target = repo.find_project(&into)?;
+                for source in &sources {
+                    let source = repo.find_project(source)?;
+                    let (tasks, notes) = repo.merge_projects(source.id.unwrap_or(0), target.id.unwrap_or(0))?;
+                    outln!(
+                        "{} Merged '{}' into '{}' ({} task(s), {} note(s) moved).",
+                        "✔".green(),
+                        source.name,
+                        target.name,
+                        tasks,
+                        notes
+                    )?;
+                }
+            }
+        },
+        Some(Commands::Tags { command }) => match command.unwrap_or(cli::TagCommands::Ls) {
+            cli::TagCommands::Ls => {
+                let counts = repo.get_tag_counts()?;
+                if format != Format::Table {
+                    return output::print_records(format, &counts, output::TAG_FIELDS);
+                }
+                if counts.is_empty() {
+                    outln!("No tags found.")?;
+                    return Ok(());
+                }
+                outln!("{:<20} {:>6} {:>9}", "Tag", "Tasks", "Projects")?;
+                outln!("{}", "-".repeat(37))?;
+                for count in counts {
+                    outln!("{:<20} {:>6} {:>9}", format!("#{}", count.tag).cyan(), count.tasks, count.projects)?;
+                }
+            }
+            cli::TagCommands::Rename { old, new } => {
+                if repo.tag_exists(&new)? {
+                    anyhow::bail!("Tag '{}' already exists; use `flow tags merge {} --into {}` to combine them", new, old, new);
+                }
+                let changed = repo.merge_tags(std::slice::from_ref(&old), &new)?;
+                outln!("{} Renamed '{}' to '{}' on {} task(s).", "✔".green(), old, new, changed)?;
+            }
+            cli::TagCommands::Merge { sources, into } => {
+                let changed = repo.merge_tags(&sources, &into)?;
+                outln!("{} Merged {} into '{}' ({} task(s) retagged).", "✔".green(), sources.join(", "), into, changed)?;
+            }
+        },
         Some(Commands::Note(note_cmd)) => match note_cmd {
             cli::NoteCommands::Add {
                 title,
                 content,
                 tags,
+                edit,
             } => {
-                let project_path = std::env::current_dir()?.to_string_lossy().to_string();
+                let (title, content, tags) = if edit {
+                    let doc = editor::Document {
+                        title: title.unwrap_or_default(),
+                        tags,
+                        body: content.unwrap_or_default(),
+                    };
+                    let Some(edited) = editor::edit(&doc)? else {
+                        outln!("Nothing was written; no note added.")?;
+                        return Ok(());
+                    };
+                    (edited.title, Some(edited.body).filter(|b| !b.is_empty()), edited.tags)
+                } else {
+                    (title.expect("clap requires a title without --edit"), content, tags)
+                };
+                let mut context = ContextManager::select(&repo, &config, &target)?;
                 let note = models::Note {
                     id: None,
                     title,
                     content,
-                    project_path: Some(project_path),
+                    project_path: context.project_path(&repo)?,
                     created_at: Local::now(),
                     tags,
                 };
                 let id = repo.add_note(&note)?;
-                println!("Note added with ID: {}", id);
+                outln!("Note added with ID: {}", id)?;
+                outln!("   Context: {}", context.describe().dimmed())?;
             }
             cli::NoteCommands::Ls { all } => {
                 let project_path = if all {
                     None
                 } else {
-                    Some(std::env::current_dir()?.to_string_lossy().to_string())
+                    Some(ContextManager::select(&repo, &config, &target)?.scope())
                 };
                 let notes = repo.get_notes(project_path.as_deref())?;
+                if format != Format::Table {
+                    return output::print_records(format, &notes, output::NOTE_FIELDS);
+                }
                 if notes.is_empty() {
-                    println!("No notes found.");
+                    outln!("No notes found.")?;
                 } else {
-                    println!("{:<4} {:<20} {:<30} {:<20}", "ID", "Title", "Content", "Tags");
-                    println!("{:-<4} {:-<20} {:-<30} {:-<20}", "", "", "", "");
+                    outln!("{:<4} {:<20} {:<30} {:<20}", "ID", "Title", "Content", "Tags")?;
+                    outln!("{:-<4} {:-<20} {:-<30} {:-<20}", "", "", "", "")?;
                     for note in notes {
                         let content_preview = note.content.unwrap_or_default();
                         let content_preview = if content_preview.len() > 30 {
@@ -141,28 +796,40 @@ fn main() -> Result<()> {
                         } else {
                             content_preview
                         };
-                        println!(
+                        outln!(
                             "{:<4} {:<20} {:<30} {:<20}",
                             note.id.unwrap_or(0),
                             note.title,
                             content_preview,
                             note.tags.join(", ")
-                        );
+                        )?;
                     }
                 }
             }
-            cli::NoteCommands::Show { id } => {
+            cli::NoteCommands::Show { id, raw } => {
                 let note = repo.get_note(id)?;
-                println!("ID: {}", note.id.unwrap_or(0));
-                println!("Title: {}", note.title);
-                println!("Created: {}", note.created_at.format("%Y-%m-%d %H:%M"));
-                println!("Tags: {}", note.tags.join(", "));
-                println!("----------------------------------------");
-                println!("{}", note.content.unwrap_or_default());
+                if format != Format::Table {
+                    return output::print_record(format, &note, output::NOTE_FIELDS);
+                }
+                let content = note.content.unwrap_or_default();
+                let mut text = format!(
+                    "ID: {}\nTitle: {}\nCreated: {}\nTags: {}\n----------------------------------------\n",
+                    note.id.unwrap_or(0),
+                    note.title,
+                    note.created_at.format("%Y-%m-%d %H:%M"),
+                    note.tags.join(", ")
+                );
+                if raw {
+                    text.push_str(&content);
+                    text.push('\n');
+                } else {
+                    text.push_str(&markdown::to_ansi(&markdown::render(&content)));
+                }
+                output::page(&text)?;
             }
             cli::NoteCommands::Rm { id } => {
                 repo.delete_note(id)?;
-                println!("Note {} deleted.", id);
+                outln!("Note {} deleted.", id)?;
             }
             cli::NoteCommands::Edit {
                 id,
@@ -170,18 +837,368 @@ fn main() -> Result<()> {
                 content,
                 tags,
             } => {
-                repo.update_note(id, title, content, tags)?;
-                println!("Note {} updated.", id);
+                if title.is_none() && content.is_none() && tags.is_none() {
+                    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+                        outln!("Nothing to update.")?;
+                        return Ok(());
+                    }
+                    let note = repo.get_note(id)?;
+                    let doc = editor::Document {
+                        title: note.title,
+                        tags: note.tags,
+                        body: note.content.unwrap_or_default(),
+                    };
+                    let Some(edited) = editor::edit(&doc)? else {
+                        outln!("No changes; note {} left as it was.", id)?;
+                        return Ok(());
+                    };
+                    let body = Some(edited.body).filter(|b| !b.is_empty());
+                    repo.update_note(id, Some(edited.title), Some(body), Some(edited.tags))?;
+                } else {
+                    let content = content.map(|c| Some(c).filter(|c| !c.is_empty()));
+                    repo.update_note(id, title, content, tags)?;
+                }
+                outln!("Note {} updated.", id)?;
             }
         },
-        Some(Commands::Ui) => {
-            tui::run(&repo)?;
+        Some(Commands::Config { command }) => {
+            let mut config = config;
+            match command.unwrap_or(cli::ConfigCommands::Ls) {
+                cli::ConfigCommands::Ls => {
+                    for (key, value) in config.entries()? {
+                        outln!("{} = {}", key, value)?;
+                    }
+                }
+                cli::ConfigCommands::Get { key } => outln!("{}", config.get(&key)?)?,
+                cli::ConfigCommands::Set { key, value } => {
+                    config.set(&key, &value)?;
+                    config.save()?;
+                    outln!("{} {} = {}", "✔".green(), key, config.get(&key)?)?;
+                }
+            }
+        }
+        Some(Commands::Db(_)) => unreachable!("handled before migrations run"),
+        Some(Commands::Ui { query }) => {
+            let filter = build_filter(&query, None, &[])?;
+            tui::run(&repo, &config, &target, filter.map(|f| (query.join(" "), f)))?;
         }
         None => {
             // Default to TUI if no command
-            tui::run(&repo)?;
+            tui::run(&repo, &config, &target, None)?;
         }
     }
 
     Ok(())
 }
+
+fn print_task_header() -> Result<()> {
+    outln!("{:<4} {:<30} {:<12} {:<10} {:<20} Tags", "ID", "Title", "Status", "Priority", "Due")?;
+    outln!("{}", "-".repeat(84))?;
+    Ok(())
+}
+
+fn print_completion(repo: &TaskRepository, completion: &Completion) -> Result<()> {
+    for id in &completion.parents {
+        outln!("{} Task {} completed: all of its subtasks are done.", "✔".green(), id)?;
+    }
+    for (done, next) in &completion.spawned {
+        let due = match repo.get_task(*next)?.due_date {
+            Some(d) => d.format("%Y-%m-%d %H:%M").to_string(),
+            None => "-".to_string(),
+        };
+        outln!("{} Task {} repeats: next up is task {}, due {}.", "↻".cyan(), done, next, due)?;
+    }
+    Ok(())
+}
+
+/// The checked-out branch of the current repository and the task it was
+/// created for, if there is one.
+fn branch_task(repo: &TaskRepository, config: &Config) -> Result<Option<(String, Task)>> {
+    let context = ContextManager::get_context(repo, config)?;
+    let Some(branch) = git::current_branch(&context.root) else {
+        return Ok(None);
+    };
+    let task = repo.task_for_branch(&branch, Some(&context.scope()))?;
+    Ok(task.map(|t| (branch, t)))
+}
+
+/// Tasks a commit on the current branch is about: the ones the branch was
+/// created for plus their finished or in-progress subtasks, or else every
+/// task in progress in this project.
+fn commit_tasks(repo: &TaskRepository, config: &Config) -> Result<Vec<Task>> {
+    let context = ContextManager::get_context(repo, config)?;
+    let tasks = repo.get_tasks(Some(&context.scope()), None, &[])?;
+    let branch = git::current_branch(&context.root);
+
+    let on_branch: Vec<i64> = tasks
+        .iter()
+        .filter(|t| branch.is_some() && t.branch == branch)
+        .filter_map(|t| t.id)
+        .collect();
+    if on_branch.is_empty() {
+        return Ok(tasks.into_iter().filter(|t| t.status == Status::InProgress).collect());
+    }
+
+    let (mut primary, rest): (Vec<Task>, Vec<Task>) =
+        tasks.into_iter().partition(|t| t.id.is_some_and(|id| on_branch.contains(&id)));
+    primary.extend(rest.into_iter().filter(|t| {
+        t.status != Status::Todo && t.parent_id.is_some_and(|p| on_branch.contains(&p))
+    }));
+    Ok(primary)
+}
+
+fn run_hook(hook: &str, args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let mut repo = TaskRepository::init()?;
+    repo.set_auto_complete_parents(config.auto_complete_parents);
+
+    // Tasks are only completed once the commit exists: after commit-msg,
+    // another hook could still reject it.
+    if hook == "post-commit" {
+        if !config.close_tasks_on_commit {
+            return Ok(());
+        }
+        let message = git::head_message(&std::env::current_dir()?)?;
+        for id in hooks::closed_tasks(&message) {
+            // commit-msg has already warned about tasks that don't exist.
+            if !repo.get_task(id).is_ok_and(|t| t.status != Status::Done) {
+                continue;
+            }
+            let completion = repo.complete_task(id)?;
+            outln!("{} Task {} marked as done.", "✔".green(), id)?;
+            print_completion(&repo, &completion)?;
+        }
+        return Ok(());
+    }
+
+    let file = args.first().context("Missing commit message file")?;
+    let message = fs::read_to_string(file)?;
+
+    let updated = match hook {
+        "prepare-commit-msg" => {
+            // The second argument says where the message came from; leave
+            // merges and squashes alone.
+            let source = args.get(1).map(String::as_str).unwrap_or("");
+            if matches!(source, "merge" | "squash") {
+                return Ok(());
+            }
+            let tasks = commit_tasks(&repo, &config)?;
+            match hooks::suggest_message(&tasks) {
+                Some(suggestion) if source.is_empty() && hooks::is_blank(&message) => {
+                    format!("{}{}", suggestion, message)
+                }
+                _ => {
+                    let ids: Vec<i64> = tasks.iter().filter_map(|t| t.id).collect();
+                    hooks::insert_trailers(&message, &ids)
+                }
+            }
+        }
+        "commit-msg" => {
+            let mut closed = hooks::closed_tasks(&message);
+            closed.retain(|id| match repo.get_task(*id) {
+                Ok(_) => true,
+                Err(_) => {
+                    eprintln!("flow: ignoring flow#{}: no such task", id);
+                    false
+                }
+            });
+            hooks::insert_trailers(&message, &closed)
+        }
+        other => anyhow::bail!("Unknown hook '{}'", other),
+    };
+
+    if updated != message {
+        fs::write(file, updated)?;
+    }
+    Ok(())
+}
+
+fn print_stopped(repo: &TaskRepository, entry: &TimeEntry, now: chrono::DateTime<Local>) -> Result<()> {
+    let task = repo.get_task(entry.task_id)?;
+    outln!(
+        "{} Stopped task {} ({}) after {}",
+        "■".yellow(),
+        entry.task_id,
+        task.title,
+        format_duration(entry.duration(now).num_seconds())
+    )?;
+    Ok(())
+}
+
+/// "2h 05m", "25m" or "40s".
+fn format_duration(seconds: i64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds.max(0))
+    }
+}
+
+/// Prints one table row; `prefix` is the tree indentation, if any.
+fn print_task_row(task: &Task, prefix: &str) -> Result<()> {
+    let progress = if task.subtasks_total > 0 {
+        format!(" [{}/{}]", task.subtasks_done, task.subtasks_total)
+    } else {
+        String::new()
+    };
+    let text = format!("{}{}", task.title, progress);
+    let title = if task.status == Status::Done {
+        text.strikethrough()
+    } else {
+        text.normal()
+    };
+
+    let priority = match task.priority {
+        Priority::High | Priority::Critical => task.priority.to_string().red(),
+        Priority::Medium => task.priority.to_string().yellow(),
+        Priority::Low => task.priority.to_string().green(),
+    };
+
+    let due = match task.due_date {
+        Some(d) => d.format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    };
+
+    let tags = task.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ");
+
+    let (status, blocked) = if task.is_blocked() {
+        let ids = task.blocked_by.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(",");
+        ("Blocked".red(), format!(" blocked by {}", ids).dimmed())
+    } else {
+        (task.status.to_string().normal(), "".normal())
+    };
+
+    let width = 30usize.saturating_sub(prefix.chars().count());
+    outln!("{:<4} {}{:<width$} {:<12} {:<10} {:<20} {}{}", 
+        task.id.unwrap_or(0), 
+        prefix.dimmed(),
+        title, 
+        status, 
+        priority, 
+        due,
+        tags.cyan(),
+        blocked
+    )?;
+    Ok(())
+}
+
+/// Combines a filter expression with the `--status`/`--tag` shorthand flags.
+fn build_filter(query: &[String], status: Option<&str>, tags: &[String]) -> Result<Option<Filter>> {
+    let mut filters = Vec::new();
+    if !query.is_empty() {
+        filters.push(Filter::parse(&query.join(" "), Local::now())?);
+    }
+    if let Some(s) = status {
+        let status = s.parse::<Status>().map_err(|e| anyhow::anyhow!(e))?;
+        filters.push(Filter::Cond(Condition::Status(CmpOp::Eq, status)));
+    }
+    for tag in tags.iter().filter_map(|t| normalize_tag(t)) {
+        filters.push(Filter::Cond(Condition::Tag(tag)));
+    }
+    Ok(Filter::all(filters))
+}
+
+/// Turns plain search words into an FTS5 expression: every word is quoted so
+/// punctuation can't be read as syntax, and the last one matches as a prefix
+/// so results show up while a word is still being typed.
+fn fts_query(words: &[String]) -> String {
+    let terms: Vec<&str> = words.iter().flat_map(|w| w.split_whitespace()).collect();
+    terms
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let quoted = format!("\"{}\"", t.replace('"', "\"\""));
+            if i == terms.len() - 1 {
+                quoted + "*"
+            } else {
+                quoted
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn highlight_snippet(snippet: &str) -> String {
+    let mut out = String::new();
+    let mut rest = snippet.replace('\n', " ");
+    while let Some(start) = rest.find(SEARCH_MATCH_START) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + SEARCH_MATCH_START.len_utf8()..];
+        let end = after.find(SEARCH_MATCH_END).unwrap_or(after.len());
+        out.push_str(&after[..end].yellow().bold().to_string());
+        rest = after[(end + SEARCH_MATCH_END.len_utf8()).min(after.len())..].to_string();
+    }
+    out.push_str(&rest);
+    out
+}
+
+fn print_explanation(parsed: &QuickAdd) -> Result<()> {
+    for piece in &parsed.pieces {
+        let meaning = match &piece.meaning {
+            Meaning::Title => "title".to_string(),
+            Meaning::Priority(p) => format!("priority = {}", p),
+            Meaning::Tag(t) => format!("tag = {}", t),
+            Meaning::Due(d) => format!("due = {}", d.format("%Y-%m-%d %H:%M")),
+            Meaning::Project(p) => format!("project = {}", p),
+            Meaning::Every(e) => match Recurrence::parse(e) {
+                Ok((rule, after)) => format!("repeats {}{}", rule.describe(), if after { " after completion" } else { "" }),
+                Err(err) => format!("invalid repeat rule: {}", err),
+            },
+        };
+        outln!("{:<30} {} {}", format!("\"{}\"", piece.text), "→".dimmed(), meaning)?;
+    }
+    Ok(())
+}
+
+fn run_db_command(repo: &mut TaskRepository, cmd: cli::DbCommands) -> Result<()> {
+    match cmd {
+        cli::DbCommands::Status => {
+            let pending = repo.pending_migrations()?;
+            outln!("Schema version: {}", repo.schema_version()?)?;
+            outln!("Latest version: {}", migrations::latest_version())?;
+            if pending.is_empty() {
+                outln!("{} Database is up to date.", "✔".green())?;
+            } else {
+                outln!("Pending migrations:")?;
+                for m in pending {
+                    outln!("  {:>3}  {}", m.version, m.description)?;
+                }
+            }
+        }
+        cli::DbCommands::Migrate { dry_run } => {
+            let applied = repo.migrate(dry_run)?;
+            if applied.is_empty() {
+                outln!("{} Database is up to date.", "✔".green())?;
+                return Ok(());
+            }
+            for m in &applied {
+                outln!("  {:>3}  {}", m.version, m.description)?;
+            }
+            if dry_run {
+                outln!("{} {} migration(s) would be applied (dry run, nothing changed).", "✔".green(), applied.len())?;
+            } else {
+                outln!("{} Applied {} migration(s), schema is now at version {}.", "✔".green(), applied.len(), repo.schema_version()?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        text.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn search_words_become_quoted_terms() {
+        assert_eq!(fts_query(&words("fix login")), r#""fix" "login"*"#);
+        // FTS5 operators and quotes in the input are taken literally.
+        assert_eq!(fts_query(&words("say \"hi\" OR")), r#""say" """hi""" "OR"*"#);
+    }
+}
@@ -0,0 +1,526 @@
+use colored::{ColoredString, Colorize};
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+
+use crate::lexer;
+
+/// What a piece of rendered text is, leaving the actual colors to whoever
+/// draws it (the terminal here, ratatui in the TUI).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Heading,
+    /// List bullets and numbers.
+    Marker,
+    Code,
+    Link,
+    Keyword,
+    Str,
+    Comment,
+    Number,
+    /// Quote bars, rules and link targets.
+    Muted,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub tone: Option<Tone>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike: bool,
+}
+
+impl Style {
+    fn tone(tone: Tone) -> Style {
+        Style { tone: Some(tone), ..Style::default() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+pub type Line = Vec<Span>;
+
+const RULE_WIDTH: usize = 40;
+
+/// Lays out markdown as styled lines. Line breaks inside paragraphs are
+/// kept as written, since notes are usually typed with them on purpose.
+pub fn render(text: &str) -> Vec<Line> {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer::default();
+    for event in Parser::new_ext(text, options) {
+        renderer.event(event);
+    }
+    renderer.finish()
+}
+
+/// The lines with terminal colors, ready to print.
+pub fn to_ansi(lines: &[Line]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        for span in line {
+            out.push_str(&paint(span).to_string());
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn paint(span: &Span) -> ColoredString {
+    let style = span.style;
+    let mut text = match style.tone {
+        Some(Tone::Heading) => span.text.bright_cyan(),
+        Some(Tone::Marker) => span.text.cyan(),
+        Some(Tone::Code) => span.text.yellow(),
+        Some(Tone::Link) => span.text.blue(),
+        Some(Tone::Keyword) => span.text.magenta(),
+        Some(Tone::Str) => span.text.green(),
+        Some(Tone::Comment) | Some(Tone::Muted) => span.text.bright_black(),
+        Some(Tone::Number) => span.text.cyan(),
+        None => span.text.normal(),
+    };
+    if style.bold {
+        text = text.bold();
+    }
+    if style.italic {
+        text = text.italic();
+    }
+    if style.underline {
+        text = text.underline();
+    }
+    if style.strike {
+        text = text.strikethrough();
+    }
+    text
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Line>,
+    current: Line,
+    /// Text repeated at the start of every line: quote bars and the
+    /// indentation of list items.
+    prefixes: Vec<String>,
+    /// A list item's bullet, shown in place of its indentation on the
+    /// item's first line.
+    marker: Option<String>,
+    /// The next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    heading: Option<HeadingLevel>,
+    strong: usize,
+    emphasis: usize,
+    strike: usize,
+    /// Target and text so far of the link being rendered.
+    link: Option<(String, String)>,
+    code: Option<Highlighter>,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => match self.code.take() {
+                Some(mut highlighter) => {
+                    for line in text.lines() {
+                        let spans = highlighter.highlight(line);
+                        self.push_line(spans);
+                    }
+                    self.code = Some(highlighter);
+                }
+                None => self.text(&text, self.style()),
+            },
+            Event::Code(code) => self.text(&code, Style::tone(Tone::Code)),
+            Event::Html(html) => {
+                for line in html.lines() {
+                    self.text(line, Style::tone(Tone::Muted));
+                    self.flush();
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.push_line(vec![Span { text: "─".repeat(RULE_WIDTH), style: Style::tone(Tone::Muted) }]);
+                self.blank();
+            }
+            Event::TaskListMarker(done) => {
+                let marker = if done { "[x] " } else { "[ ] " };
+                if let Some(indent) = self.prefixes.last_mut() {
+                    *indent = " ".repeat(marker.len());
+                }
+                self.marker = Some(marker.to_string());
+            }
+            Event::FootnoteReference(name) => self.text(&format!("[^{}]", name), Style::tone(Tone::Muted)),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.flush(),
+            Tag::Heading(level, _, _) => {
+                self.flush();
+                self.heading = Some(level);
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                self.prefixes.push("│ ".to_string());
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info.split([' ', ',', '{']).next().unwrap_or("").to_lowercase(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.prefixes.push("  ".to_string());
+                self.code = Some(Highlighter::new(&lang));
+            }
+            Tag::List(first) => {
+                self.flush();
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.prefixes.push(" ".repeat(marker.chars().count()));
+                self.marker = Some(marker);
+            }
+            Tag::Emphasis => self.emphasis += 1,
+            Tag::Strong => self.strong += 1,
+            Tag::Strikethrough => self.strike += 1,
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => self.link = Some((url.to_string(), String::new())),
+            Tag::FootnoteDefinition(name) => {
+                self.flush();
+                self.text(&format!("[^{}]: ", name), Style::tone(Tone::Muted));
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::FootnoteDefinition(_) => {
+                self.flush();
+                self.blank();
+            }
+            Tag::Heading(..) => {
+                self.flush();
+                self.heading = None;
+                self.blank();
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                // The quote's own closing blank line shouldn't carry its bar.
+                if self.lines.last().is_some_and(is_blank) {
+                    self.lines.pop();
+                }
+                self.prefixes.pop();
+                self.blank();
+            }
+            Tag::CodeBlock(_) => {
+                self.code = None;
+                self.prefixes.pop();
+                self.blank();
+            }
+            Tag::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            Tag::Item => {
+                self.flush();
+                self.prefixes.pop();
+                self.marker = None;
+            }
+            Tag::Emphasis => self.emphasis -= 1,
+            Tag::Strong => self.strong -= 1,
+            Tag::Strikethrough => self.strike -= 1,
+            Tag::Link(..) | Tag::Image(..) => {
+                // Autolinks and bare URLs already show their target.
+                if let Some((url, text)) = self.link.take() {
+                    if !url.is_empty() && url != text && format!("mailto:{}", text) != url {
+                        self.text(&format!(" ({})", url), Style::tone(Tone::Muted));
+                    }
+                }
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    fn style(&self) -> Style {
+        let mut style = Style {
+            bold: self.strong > 0,
+            italic: self.emphasis > 0,
+            strike: self.strike > 0,
+            ..Style::default()
+        };
+        if let Some(level) = self.heading {
+            style.tone = Some(Tone::Heading);
+            style.bold = true;
+            style.underline = level == HeadingLevel::H1;
+        } else if self.link.is_some() {
+            style.tone = Some(Tone::Link);
+            style.underline = true;
+        }
+        if self.prefixes.iter().any(|p| p.starts_with('│')) {
+            style.italic = true;
+        }
+        style
+    }
+
+    fn text(&mut self, text: &str, style: Style) {
+        if let Some((_, link_text)) = self.link.as_mut() {
+            link_text.push_str(text);
+        }
+        if self.current.is_empty() {
+            self.current = self.prefix();
+        }
+        self.current.push(Span { text: text.to_string(), style });
+    }
+
+    // The quote bars and indentation for a new line, with the pending list
+    // marker standing in for the innermost indentation.
+    fn prefix(&mut self) -> Line {
+        let mut spans = Vec::new();
+        let count = self.prefixes.len();
+        for (i, prefix) in self.prefixes.iter().enumerate() {
+            let (text, tone) = match &self.marker {
+                Some(marker) if i + 1 == count => (marker.clone(), Tone::Marker),
+                _ => (prefix.clone(), Tone::Muted),
+            };
+            spans.push(Span { text, style: Style::tone(tone) });
+        }
+        self.marker = None;
+        spans
+    }
+
+    fn push_line(&mut self, spans: Vec<Span>) {
+        let mut line = self.prefix();
+        line.extend(spans);
+        self.lines.push(line);
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            let line = std::mem::take(&mut self.current);
+            self.lines.push(line);
+        }
+    }
+
+    // Separates blocks. Runs of blank lines collapse into one, and inside a
+    // quote the bar carries on.
+    fn blank(&mut self) {
+        if self.lines.last().is_none_or(is_blank) {
+            return;
+        }
+        let bars: String = self.prefixes.iter().filter(|p| p.starts_with('│')).map(|p| p.trim_end()).collect();
+        if bars.is_empty() {
+            self.lines.push(Vec::new());
+        } else {
+            self.lines.push(vec![Span { text: bars, style: Style::tone(Tone::Muted) }]);
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line> {
+        self.flush();
+        while self.lines.last().is_some_and(is_blank) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+fn is_blank(line: &Line) -> bool {
+    line.iter().all(|s| s.text.trim().is_empty() || s.text.trim() == "│")
+}
+
+/// Just enough of a language to color its keywords, strings, numbers and
+/// comments.
+struct Syntax {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    ignore_case: bool,
+}
+
+const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let",
+            "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+            "use", "where", "while",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else", "except", "False", "finally", "for", "from",
+            "global", "if", "import", "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "self", "True", "try", "while",
+            "with", "yield",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["javascript", "js", "jsx", "typescript", "ts", "tsx"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "do", "else", "enum", "export", "extends", "false",
+            "finally", "for", "from", "function", "if", "implements", "import", "in", "instanceof", "interface", "let", "new", "null", "of", "return",
+            "super", "switch", "this", "throw", "true", "try", "type", "typeof", "undefined", "var", "void", "while", "yield",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["go", "golang"],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "false", "for", "func", "go", "goto", "if", "import",
+            "interface", "map", "nil", "package", "range", "return", "select", "struct", "switch", "true", "type", "var",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["c", "h", "cpp", "c++", "cc", "hpp", "java", "kotlin", "kt", "csharp", "cs", "swift"],
+        keywords: &[
+            "auto", "bool", "break", "case", "char", "class", "const", "continue", "default", "do", "double", "else", "enum", "extends", "extern", "false",
+            "final", "float", "for", "fun", "func", "goto", "if", "implements", "import", "int", "let", "long", "namespace", "new", "null", "nullptr",
+            "package", "private", "protected", "public", "return", "short", "signed", "sizeof", "static", "struct", "switch", "template", "this", "true",
+            "typedef", "union", "unsigned", "using", "val", "var", "void", "volatile", "while",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["sh", "bash", "zsh", "shell", "console", "fish"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "exit", "export", "fi", "for", "function", "if", "in", "local", "return", "then", "until", "while",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["sql", "sqlite", "postgres", "psql", "mysql"],
+        keywords: &[
+            "add", "all", "alter", "and", "as", "asc", "between", "by", "case", "create", "delete", "desc", "distinct", "drop", "else", "end", "exists",
+            "from", "group", "having", "in", "index", "inner", "insert", "into", "is", "join", "key", "left", "like", "limit", "not", "null", "offset", "on",
+            "or", "order", "outer", "primary", "right", "select", "set", "table", "then", "union", "update", "values", "when", "where", "with",
+        ],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\'', '"'],
+        ignore_case: true,
+    },
+    Syntax {
+        names: &["json", "jsonc"],
+        keywords: &["true", "false", "null"],
+        line_comments: &[],
+        block_comment: None,
+        quotes: &['"'],
+        ignore_case: false,
+    },
+    Syntax {
+        names: &["toml", "yaml", "yml", "ini", "conf", "dockerfile", "make", "makefile"],
+        keywords: &["true", "false", "null", "yes", "no"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        ignore_case: false,
+    },
+];
+
+struct Highlighter {
+    syntax: Option<&'static Syntax>,
+    in_comment: bool,
+}
+
+impl Highlighter {
+    fn new(lang: &str) -> Highlighter {
+        let syntax = SYNTAXES.iter().find(|s| s.names.contains(&lang));
+        Highlighter { syntax, in_comment: false }
+    }
+
+    fn highlight(&mut self, line: &str) -> Vec<Span> {
+        let Some(syntax) = self.syntax else {
+            return vec![Span { text: line.to_string(), style: Style::tone(Tone::Code) }];
+        };
+        let mut spans: Vec<Span> = Vec::new();
+        let mut push = |text: &str, tone: Option<Tone>| match spans.last_mut() {
+            Some(last) if last.style.tone == tone => last.text.push_str(text),
+            _ => spans.push(Span { text: text.to_string(), style: Style { tone, ..Style::default() } }),
+        };
+
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            if self.in_comment {
+                let close = syntax.block_comment.map_or("", |(_, close)| close);
+                let end = rest.find(close).map_or(rest.len(), |p| p + close.len());
+                self.in_comment = end == rest.len() && !rest.ends_with(close);
+                push(&rest[..end], Some(Tone::Comment));
+                i += end;
+                continue;
+            }
+            if syntax.line_comments.iter().any(|c| rest.starts_with(c)) {
+                push(rest, Some(Tone::Comment));
+                break;
+            }
+            if let Some((open, _)) = syntax.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+                push(open, Some(Tone::Comment));
+                self.in_comment = true;
+                i += open.len();
+                continue;
+            }
+
+            let c = rest.chars().next().expect("rest is not empty");
+            let len = if syntax.quotes.contains(&c) {
+                let len = lexer::string_len(rest, c);
+                push(&rest[..len], Some(Tone::Str));
+                len
+            } else if c.is_alphanumeric() || c == '_' {
+                let len = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '.' && c.is_ascii_digit())).unwrap_or(rest.len());
+                let word = &rest[..len];
+                let tone = if c.is_ascii_digit() {
+                    Some(Tone::Number)
+                } else if syntax.keywords.iter().any(|k| if syntax.ignore_case { k.eq_ignore_ascii_case(word) } else { *k == word }) {
+                    Some(Tone::Keyword)
+                } else {
+                    None
+                };
+                push(word, tone);
+                len
+            } else {
+                push(&rest[..c.len_utf8()], None);
+                c.len_utf8()
+            };
+            i += len;
+        }
+        spans
+    }
+}
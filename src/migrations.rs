@@ -0,0 +1,588 @@
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+
+/// A single schema upgrade step. Versions are stored in `PRAGMA user_version`,
+/// so they must be strictly increasing and never renumbered once released.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create tasks and notes tables",
+        up: create_base_tables,
+    },
+    Migration {
+        version: 2,
+        description: "move task tags into the task_tags table",
+        up: create_task_tags,
+    },
+    Migration {
+        version: 3,
+        description: "add full-text search indexes for tasks and notes",
+        up: create_search_index,
+    },
+    Migration {
+        version: 4,
+        description: "add task dependencies",
+        up: create_task_dependencies,
+    },
+    Migration {
+        version: 5,
+        description: "add parent tasks for subtasks",
+        up: add_task_parents,
+    },
+    Migration {
+        version: 6,
+        description: "add recurring tasks",
+        up: add_task_recurrence,
+    },
+    Migration {
+        version: 7,
+        description: "add time tracking entries",
+        up: create_time_entries,
+    },
+    Migration {
+        version: 8,
+        description: "record git branches on tasks",
+        up: add_task_branches,
+    },
+    Migration {
+        version: 9,
+        description: "link tasks to TODO comments found by flow scan",
+        up: create_code_comments,
+    },
+    Migration {
+        version: 10,
+        description: "add projects with a stable identity",
+        up: create_projects,
+    },
+    Migration {
+        version: 11,
+        description: "move notes taken in subdirectories to their project",
+        up: rehome_notes,
+    },
+    Migration {
+        version: 12,
+        description: "record when tasks are completed",
+        up: add_task_completed_at,
+    },
+    Migration {
+        version: 13,
+        description: "scope tasks to the branch they were added on",
+        up: add_task_scope_branch,
+    },
+    Migration {
+        version: 14,
+        description: "link tasks and notes to projects by ID",
+        up: add_project_ids,
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    let current = current_version(conn)?;
+    if current > latest_version() {
+        bail!(
+            "Database schema version {} is newer than this build of flow supports ({})",
+            current,
+            latest_version()
+        );
+    }
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+}
+
+/// Applies every pending migration, each in its own transaction, and returns
+/// the ones that ran.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<&'static Migration>> {
+    let pending = pending(conn)?;
+    for migration in &pending {
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    Ok(pending)
+}
+
+/// Runs every pending migration inside a single transaction that is always
+/// rolled back, so the database is left untouched.
+pub fn dry_run(conn: &mut Connection) -> Result<Vec<&'static Migration>> {
+    let pending = pending(conn)?;
+    let tx = conn.transaction()?;
+    for migration in &pending {
+        (migration.up)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+    }
+    tx.rollback()?;
+    Ok(pending)
+}
+
+// Version 1 mirrors the tables created before migrations existed, so the
+// statements stay idempotent for databases that predate `user_version`.
+fn create_base_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT,
+            status TEXT NOT NULL,
+            priority TEXT NOT NULL,
+            due_date TEXT,
+            project_path TEXT,
+            created_at TEXT NOT NULL,
+            tags TEXT
+        );
+        CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            content TEXT,
+            project_path TEXT,
+            created_at TEXT NOT NULL,
+            tags TEXT
+        );",
+    )
+}
+
+// Tags were stored as a JSON array in `tasks.tags`; they now live in a join
+// table so filtering by tag can use an index. The old column is left in place
+// but no longer read or written.
+fn create_task_tags(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE task_tags (
+            task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (task_id, tag)
+        );
+        CREATE INDEX idx_task_tags_tag ON task_tags(tag);
+        INSERT OR IGNORE INTO task_tags (task_id, tag)
+            SELECT tasks.id, lower(trim(value))
+            FROM tasks, json_each(tasks.tags)
+            WHERE json_valid(tasks.tags) AND trim(value) != '';",
+    )
+}
+
+// External-content FTS5 tables; the triggers keep them in step with the base
+// tables and the final 'rebuild' indexes rows that already exist.
+fn create_search_index(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE notes_fts USING fts5(
+            title, content, tags, content='notes', content_rowid='id'
+        );
+        CREATE TRIGGER notes_fts_insert AFTER INSERT ON notes BEGIN
+            INSERT INTO notes_fts (rowid, title, content, tags)
+            VALUES (new.id, new.title, new.content, new.tags);
+        END;
+        CREATE TRIGGER notes_fts_delete AFTER DELETE ON notes BEGIN
+            INSERT INTO notes_fts (notes_fts, rowid, title, content, tags)
+            VALUES ('delete', old.id, old.title, old.content, old.tags);
+        END;
+        CREATE TRIGGER notes_fts_update AFTER UPDATE ON notes BEGIN
+            INSERT INTO notes_fts (notes_fts, rowid, title, content, tags)
+            VALUES ('delete', old.id, old.title, old.content, old.tags);
+            INSERT INTO notes_fts (rowid, title, content, tags)
+            VALUES (new.id, new.title, new.content, new.tags);
+        END;
+        INSERT INTO notes_fts (notes_fts) VALUES ('rebuild');
+
+        CREATE VIRTUAL TABLE tasks_fts USING fts5(
+            title, description, content='tasks', content_rowid='id'
+        );
+        CREATE TRIGGER tasks_fts_insert AFTER INSERT ON tasks BEGIN
+            INSERT INTO tasks_fts (rowid, title, description)
+            VALUES (new.id, new.title, new.description);
+        END;
+        CREATE TRIGGER tasks_fts_delete AFTER DELETE ON tasks BEGIN
+            INSERT INTO tasks_fts (tasks_fts, rowid, title, description)
+            VALUES ('delete', old.id, old.title, old.description);
+        END;
+        CREATE TRIGGER tasks_fts_update AFTER UPDATE OF title, description ON tasks BEGIN
+            INSERT INTO tasks_fts (tasks_fts, rowid, title, description)
+            VALUES ('delete', old.id, old.title, old.description);
+            INSERT INTO tasks_fts (rowid, title, description)
+            VALUES (new.id, new.title, new.description);
+        END;
+        INSERT INTO tasks_fts (tasks_fts) VALUES ('rebuild');",
+    )
+}
+
+fn create_task_dependencies(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE task_dependencies (
+            task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            depends_on INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            PRIMARY KEY (task_id, depends_on),
+            CHECK (task_id != depends_on)
+        );
+        CREATE INDEX idx_task_dependencies_depends_on ON task_dependencies(depends_on);",
+    )
+}
+
+fn add_task_parents(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks(id) ON DELETE CASCADE;
+        CREATE INDEX idx_tasks_parent_id ON tasks(parent_id);",
+    )
+}
+
+fn add_task_recurrence(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN recurrence TEXT;
+        ALTER TABLE tasks ADD COLUMN recur_from_completion INTEGER NOT NULL DEFAULT 0;",
+    )
+}
+
+// At most one entry may be running (ended_at IS NULL) at any time.
+fn create_time_entries(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE time_entries (
+            id INTEGER PRIMARY KEY,
+            task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            started_at TEXT NOT NULL,
+            ended_at TEXT
+        );
+        CREATE INDEX idx_time_entries_task_id ON time_entries(task_id);
+        CREATE UNIQUE INDEX idx_time_entries_running ON time_entries((ended_at IS NULL)) WHERE ended_at IS NULL;",
+    )
+}
+
+fn add_task_branches(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN branch TEXT;
+        CREATE INDEX idx_tasks_branch ON tasks(branch);",
+    )
+}
+
+// resolved_at is set when a scan closes the task because its comment is
+// gone, so the task can be reopened if the comment comes back.
+fn create_code_comments(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE code_comments (
+            task_id INTEGER PRIMARY KEY REFERENCES tasks(id) ON DELETE CASCADE,
+            fingerprint TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            file TEXT NOT NULL,
+            line INTEGER NOT NULL,
+            resolved_at TEXT
+        );
+        CREATE INDEX idx_code_comments_fingerprint ON code_comments(fingerprint);",
+    )
+}
+
+// Every path already used by a task or note becomes a project. Their identity
+// is the path itself until flow next runs inside them and can ask git. Paths
+// with tasks pick names first: they are the real project roots.
+fn create_projects(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE projects (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            identity TEXT NOT NULL UNIQUE,
+            path TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX idx_tasks_project_path ON tasks(project_path);
+        CREATE INDEX idx_notes_project_path ON notes(project_path);",
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT project_path, MIN(created_at) FROM (
+            SELECT project_path, created_at, 1 AS task FROM tasks
+            UNION ALL
+            SELECT project_path, created_at, 0 AS task FROM notes
+        )
+        WHERE project_path IS NOT NULL
+        GROUP BY project_path
+        ORDER BY MAX(task) DESC, MIN(created_at)",
+    )?;
+    let paths = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (path, created_at) in paths {
+        let name = project_name(conn, &path)?;
+        conn.execute(
+            "INSERT INTO projects (name, identity, path, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, format!("path:{}", path), path, created_at],
+        )?;
+    }
+    Ok(())
+}
+
+// The naming rule of `db::unused_project_name` as it was when the projects
+// migrations shipped, copied so later changes to that helper can't change
+// what they do.
+fn project_name(conn: &Connection, path: &str) -> rusqlite::Result<String> {
+    let base = path.rsplit(['/', '\\']).find(|part| !part.is_empty()).unwrap_or("project");
+    let base = if base.parse::<i64>().is_ok() { format!("project-{}", base) } else { base.to_string() };
+
+    let mut name = base.clone();
+    let mut n = 1;
+    loop {
+        let taken: bool =
+            conn.query_row("SELECT EXISTS (SELECT 1 FROM projects WHERE name = ?1)", params![name], |row| row.get(0))?;
+        if !taken {
+            return Ok(name);
+        }
+        n += 1;
+        name = format!("{}-{}", base, n);
+    }
+}
+
+// Notes used to be filed under the working directory rather than the project
+// root, so those taken in a subdirectory (or at the root, minus the trailing
+// slash) go to the innermost project with tasks containing them. A directory
+// with tasks of its own was a project root all along. Projects that only held
+// such notes are dropped.
+fn rehome_notes(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE note_homes AS
+            SELECT n.id AS note_id, (
+                SELECT p.path FROM projects p
+                WHERE p.path != n.project_path
+                  AND EXISTS (SELECT 1 FROM tasks t WHERE t.project_path = p.path)
+                  AND (rtrim(n.project_path, '/') = rtrim(p.path, '/')
+                       OR substr(n.project_path, 1, length(rtrim(p.path, '/')) + 1) = rtrim(p.path, '/') || '/')
+                ORDER BY length(p.path) DESC LIMIT 1
+            ) AS home
+            FROM notes n
+            WHERE n.project_path IS NOT NULL
+              AND NOT EXISTS (SELECT 1 FROM tasks t WHERE t.project_path = n.project_path);
+        UPDATE notes SET project_path = (SELECT home FROM note_homes WHERE note_id = notes.id)
+            WHERE id IN (SELECT note_id FROM note_homes WHERE home IS NOT NULL);
+        DROP TABLE note_homes;
+        DELETE FROM projects
+            WHERE NOT EXISTS (SELECT 1 FROM tasks WHERE project_path = projects.path)
+              AND NOT EXISTS (SELECT 1 FROM notes WHERE project_path = projects.path)
+              AND EXISTS (SELECT 1 FROM projects outer_project
+                          WHERE outer_project.id != projects.id
+                            AND (rtrim(projects.path, '/') = rtrim(outer_project.path, '/')
+                                 OR substr(projects.path, 1, length(rtrim(outer_project.path, '/')) + 1)
+                                    = rtrim(outer_project.path, '/') || '/'));",
+    )
+}
+
+// Tasks finished before this have no recorded completion time; the end of
+// their last timer is the best guess there is.
+fn add_task_completed_at(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+        UPDATE tasks SET completed_at = (
+            SELECT e.ended_at FROM time_entries e
+            WHERE e.task_id = tasks.id AND e.ended_at IS NOT NULL
+            ORDER BY julianday(e.ended_at) DESC LIMIT 1
+        ) WHERE status = 'Done';",
+    )
+}
+
+// Tasks that existed before branches were scoped were added without one, so
+// they stay NULL and are listed on every branch.
+fn add_task_scope_branch(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ALTER TABLE tasks ADD COLUMN scope_branch TEXT;")
+}
+
+// Tasks and notes found their project by its path, so moving a project had
+// to rewrite all of them. They now point at the project row itself; the
+// project_path columns are left in place but no longer read or written. Every
+// path in use should already be a project, but one that isn't is registered
+// rather than leaving its tasks without a project.
+fn add_project_ids(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT project_path, MIN(created_at) FROM (
+            SELECT project_path, created_at FROM tasks
+            UNION ALL
+            SELECT project_path, created_at FROM notes
+        )
+        WHERE project_path IS NOT NULL AND project_path NOT IN (SELECT path FROM projects)
+        GROUP BY project_path
+        ORDER BY MIN(created_at)",
+    )?;
+    let paths = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (path, created_at) in paths {
+        let name = project_name(conn, &path)?;
+        conn.execute(
+            "INSERT INTO projects (name, identity, path, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, format!("path:{}", path), path, created_at],
+        )?;
+    }
+
+    conn.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN project_id INTEGER REFERENCES projects(id);
+        ALTER TABLE notes ADD COLUMN project_id INTEGER REFERENCES projects(id);
+        UPDATE tasks SET project_id = (SELECT id FROM projects WHERE path = tasks.project_path);
+        UPDATE notes SET project_id = (SELECT id FROM projects WHERE path = notes.project_path);
+        CREATE INDEX idx_tasks_project_id ON tasks(project_id);
+        CREATE INDEX idx_notes_project_id ON notes(project_id);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Brings a fresh database up to `version` the way an older build would.
+    fn database_at(version: u32) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            (migration.up)(&conn).unwrap();
+            conn.pragma_update(None, "user_version", migration.version).unwrap();
+        }
+        conn
+    }
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = ?1)", params![name], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn every_version_upgrades_to_the_latest() {
+        for version in 0..latest_version() {
+            let mut conn = database_at(version);
+            if version >= 1 {
+                conn.execute(
+                    "INSERT INTO tasks (title, status, priority, created_at, tags)
+                     VALUES ('Ship it', 'Todo', 'High', '2024-01-01T00:00:00+00:00', '[\"Release\"]')",
+                    [],
+                )
+                .unwrap();
+            }
+
+            let applied = migrate(&mut conn).unwrap();
+            assert_eq!(applied.len() as u32, latest_version() - version, "from version {}", version);
+            assert_eq!(current_version(&conn).unwrap(), latest_version());
+            assert!(pending(&conn).unwrap().is_empty());
+            let tasks: i64 = conn.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0)).unwrap();
+            assert_eq!(tasks, (version >= 1) as i64);
+            if version == 1 {
+                // The legacy JSON tags were only carried over by version 2.
+                let tag: String = conn.query_row("SELECT tag FROM task_tags", [], |row| row.get(0)).unwrap();
+                assert_eq!(tag, "release");
+            }
+        }
+    }
+
+    #[test]
+    fn dry_run_rolls_back() {
+        let mut conn = database_at(1);
+        let applied = dry_run(&mut conn).unwrap();
+        assert_eq!(applied.len() as u32, latest_version() - 1);
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        assert!(!table_exists(&conn, "task_tags"));
+        assert!(!table_exists(&conn, "projects"));
+    }
+
+    #[test]
+    fn newer_databases_are_refused() {
+        let mut conn = database_at(latest_version());
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+        let error = migrate(&mut conn).map(|_| ()).unwrap_err().to_string();
+        assert!(error.contains("newer than this build"), "{}", error);
+    }
+
+    #[test]
+    fn notes_move_to_the_project_containing_them() {
+        let conn = database_at(10);
+        conn.execute_batch(
+            "INSERT INTO projects (name, identity, path, created_at) VALUES
+                ('app', 'path:/src/app/', '/src/app/', '2024-01-01T00:00:00+00:00'),
+                ('docs', 'path:/src/app/docs', '/src/app/docs', '2024-01-01T00:00:00+00:00'),
+                ('other', 'path:/other', '/other', '2024-01-01T00:00:00+00:00');
+             INSERT INTO tasks (title, status, priority, created_at, project_path)
+             VALUES ('Ship it', 'Todo', 'Medium', '2024-01-01T00:00:00+00:00', '/src/app/');
+             INSERT INTO notes (title, created_at, tags, project_path) VALUES
+                ('At the root', '2024-01-01T00:00:00+00:00', '[]', '/src/app'),
+                ('In docs', '2024-01-01T00:00:00+00:00', '[]', '/src/app/docs'),
+                ('Elsewhere', '2024-01-01T00:00:00+00:00', '[]', '/other'),
+                ('Global', '2024-01-01T00:00:00+00:00', '[]', NULL);",
+        )
+        .unwrap();
+        let migration = MIGRATIONS.iter().find(|m| m.version == 11).unwrap();
+        (migration.up)(&conn).unwrap();
+
+        let homes: Vec<Option<String>> = conn
+            .prepare("SELECT project_path FROM notes ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let path = |p: &str| Some(p.to_string());
+        assert_eq!(homes, [path("/src/app/"), path("/src/app/"), path("/other"), None]);
+        let projects: Vec<String> = conn
+            .prepare("SELECT name FROM projects ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(projects, ["app", "other"]);
+    }
+
+    #[test]
+    fn scope_branch_starts_empty_for_existing_tasks() {
+        let mut conn = database_at(12);
+        conn.execute(
+            "INSERT INTO tasks (title, status, priority, created_at, branch)
+             VALUES ('Started on a branch', 'In Progress', 'Medium', '2024-01-01T00:00:00+00:00', '1-started')",
+            [],
+        )
+        .unwrap();
+        migrate(&mut conn).unwrap();
+        let scope: Option<String> = conn.query_row("SELECT scope_branch FROM tasks", [], |row| row.get(0)).unwrap();
+        assert_eq!(scope, None);
+    }
+
+    #[test]
+    fn tasks_and_notes_are_linked_to_projects_by_id() {
+        let mut conn = database_at(13);
+        conn.execute_batch(
+            "INSERT INTO projects (name, identity, path, created_at)
+             VALUES ('app', 'path:/src/app', '/src/app', '2024-01-01T00:00:00+00:00');
+             INSERT INTO tasks (title, status, priority, created_at, project_path)
+             VALUES ('In app', 'Todo', 'Medium', '2024-01-02T00:00:00+00:00', '/src/app'),
+                    ('Stray', 'Todo', 'Medium', '2024-01-03T00:00:00+00:00', '/src/lib'),
+                    ('Global', 'Todo', 'Medium', '2024-01-04T00:00:00+00:00', NULL);
+             INSERT INTO notes (title, created_at, tags, project_path)
+             VALUES ('App note', '2024-01-05T00:00:00+00:00', '[]', '/src/app');",
+        )
+        .unwrap();
+        migrate(&mut conn).unwrap();
+
+        let linked: Vec<(String, Option<String>)> = conn
+            .prepare(
+                "SELECT title, (SELECT path FROM projects WHERE id = project_id) FROM tasks
+                 UNION ALL
+                 SELECT title, (SELECT path FROM projects WHERE id = project_id) FROM notes",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let path = |p: &str| Some(p.to_string());
+        assert_eq!(
+            linked,
+            [
+                ("In app".to_string(), path("/src/app")),
+                ("Stray".to_string(), path("/src/lib")),
+                ("Global".to_string(), None),
+                ("App note".to_string(), path("/src/app")),
+            ]
+        );
+        let name: String = conn.query_row("SELECT name FROM projects WHERE path = '/src/lib'", [], |row| row.get(0)).unwrap();
+        assert_eq!(name, "lib");
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Status {
@@ -19,17 +20,36 @@ impl fmt::Display for Status {
     }
 }
 
-impl From<String> for Status {
-    fn from(s: String) -> Self {
+impl Status {
+    /// Position in the workflow, used for ordering and comparisons.
+    pub fn rank(&self) -> i64 {
+        match self {
+            Status::Todo => 0,
+            Status::InProgress => 1,
+            Status::Done => 2,
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "todo" => Status::Todo,
-            "inprogress" | "in-progress" | "doing" => Status::InProgress,
-            "done" | "completed" => Status::Done,
-            _ => Status::Todo,
+            "todo" => Ok(Status::Todo),
+            "inprogress" | "in-progress" | "in progress" | "doing" => Ok(Status::InProgress),
+            "done" | "completed" => Ok(Status::Done),
+            _ => Err(format!("unknown status '{}'", s)),
         }
     }
 }
 
+impl From<String> for Status {
+    fn from(s: String) -> Self {
+        s.parse().unwrap_or(Status::Todo)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
     Low,
@@ -49,18 +69,48 @@ impl fmt::Display for Priority {
     }
 }
 
-impl From<String> for Priority {
-    fn from(s: String) -> Self {
+impl Priority {
+    pub fn rank(&self) -> i64 {
+        match self {
+            Priority::Low => 0,
+            Priority::Medium => 1,
+            Priority::High => 2,
+            Priority::Critical => 3,
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "low" => Priority::Low,
-            "medium" => Priority::Medium,
-            "high" => Priority::High,
-            "critical" => Priority::Critical,
-            _ => Priority::Medium,
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "critical" => Ok(Priority::Critical),
+            _ => Err(format!("unknown priority '{}'", s)),
         }
     }
 }
 
+impl From<String> for Priority {
+    fn from(s: String) -> Self {
+        s.parse().unwrap_or(Priority::Medium)
+    }
+}
+
+/// Canonical form of a tag: trimmed, lowercase, without a leading `@`, `+`
+/// or `#`. Returns `None` for tags that are empty once cleaned up.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches(['@', '+', '#']).trim();
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag.to_lowercase())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: Option<i64>,
@@ -72,11 +122,131 @@ pub struct Task {
     pub project_path: Option<String>,
     pub created_at: DateTime<Local>,
     pub tags: Vec<String>,
+    /// IDs of unfinished tasks this one depends on. Derived, never stored.
+    #[serde(default)]
+    pub blocked_by: Vec<i64>,
+    pub parent_id: Option<i64>,
+    /// Number of direct subtasks, and how many of them are done. Derived.
+    #[serde(default)]
+    pub subtasks_total: i64,
+    #[serde(default)]
+    pub subtasks_done: i64,
+    /// Repeat rule in RRULE form, e.g. `FREQ=WEEKLY;INTERVAL=1;BYDAY=MO`.
+    pub recurrence: Option<String>,
+    /// Schedule the next instance from the completion date rather than the
+    /// due date.
+    #[serde(default)]
+    pub recur_from_completion: bool,
+    /// Git branch created for the task by `flow start --branch`.
+    pub branch: Option<String>,
+    /// When the task was last marked done; cleared when it is reopened.
+    pub completed_at: Option<DateTime<Local>>,
+    /// The branch the task was added on with `worktrees = branch`; it is
+    /// only listed there. `None` on the default branch.
+    pub scope_branch: Option<String>,
 }
 
 impl Task {
     // Constructor removed as it is currently unused
 
+    pub fn is_blocked(&self) -> bool {
+        self.status != Status::Done && !self.blocked_by.is_empty()
+    }
+}
+
+/// A partial update to a task. `None` leaves a field unchanged; for nullable
+/// columns `Some(None)` clears the value.
+#[derive(Debug, Clone, Default)]
+pub struct TaskUpdate {
+    pub title: Option<String>,
+    pub description: Option<Option<String>>,
+    pub status: Option<Status>,
+    pub priority: Option<Priority>,
+    pub due_date: Option<Option<DateTime<Local>>>,
+    pub tags: Option<Vec<String>>,
+    pub parent_id: Option<Option<i64>>,
+    pub recurrence: Option<Option<String>>,
+    pub recur_from_completion: Option<bool>,
+}
+
+impl TaskUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.status.is_none()
+            && self.priority.is_none()
+            && self.due_date.is_none()
+            && self.tags.is_none()
+            && self.parent_id.is_none()
+            && self.recurrence.is_none()
+            && self.recur_from_completion.is_none()
+    }
+}
+
+/// Everything that happened as a consequence of completing a task.
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    /// Parent tasks completed because all of their subtasks are done.
+    pub parents: Vec<i64>,
+    /// Next instances of recurring tasks, as `(completed, spawned)` IDs.
+    pub spawned: Vec<(i64, i64)>,
+}
+
+/// A stretch of tracked work on a task. `ended_at` is `None` while the
+/// timer is running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub id: i64,
+    pub task_id: i64,
+    pub started_at: DateTime<Local>,
+    pub ended_at: Option<DateTime<Local>>,
+}
+
+impl TimeEntry {
+    pub fn duration(&self, now: DateTime<Local>) -> chrono::Duration {
+        self.ended_at.unwrap_or(now) - self.started_at
+    }
+}
+
+/// What `flow time report` adds up tracked time by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimeGrouping {
+    Project,
+    Tag,
+    Task,
+}
+
+/// Tracked time for one project, tag or task in a report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeTotal {
+    pub group: String,
+    pub seconds: i64,
+    pub hours: f64,
+}
+
+/// A marker comment (see `scan::MARKERS`) found by `flow scan`. The fingerprint is
+/// derived from the file, marker and text, not the line number.
+#[derive(Debug, Clone)]
+pub struct CodeComment {
+    pub fingerprint: String,
+    pub kind: String,
+    pub text: String,
+    pub file: String,
+    pub line: i64,
+}
+
+/// What `flow scan` changed, as task IDs.
+#[derive(Debug, Clone, Default)]
+pub struct ScanSummary {
+    /// New tasks with the comment each was made from.
+    pub created: Vec<(i64, CodeComment)>,
+    /// Tasks whose comment moved to another line.
+    pub moved: Vec<i64>,
+    /// Tasks closed by an earlier scan whose comment is back.
+    pub reopened: Vec<i64>,
+    /// Tasks whose comment has disappeared.
+    pub closed: Vec<i64>,
+    pub unchanged: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,3 +258,69 @@ pub struct Note {
     pub created_at: DateTime<Local>,
     pub tags: Vec<String>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub tasks: i64,
+    pub projects: i64,
+}
+
+/// A directory or repository that tasks and notes belong to. `identity`
+/// stays the same when the checkout moves or is cloned again; `path` is
+/// where it was last seen and what tasks and notes store as `project_path`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: Option<i64>,
+    pub name: String,
+    pub identity: String,
+    pub path: String,
+    pub created_at: DateTime<Local>,
+    pub tasks: i64,
+    pub notes: i64,
+}
+
+/// A full-text search match. `snippet` marks matched terms with
+/// `SEARCH_MATCH_START` and `SEARCH_MATCH_END`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub kind: String,
+    pub id: i64,
+    pub title: String,
+    pub snippet: String,
+    pub rank: f64,
+    pub project_path: Option<String>,
+}
+
+pub const SEARCH_MATCH_START: char = '\u{2}';
+pub const SEARCH_MATCH_END: char = '\u{3}';
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_and_priority_parse_their_spellings() {
+        assert_eq!("In Progress".parse::<Status>(), Ok(Status::InProgress));
+        assert_eq!("in-progress".parse::<Status>(), Ok(Status::InProgress));
+        assert_eq!("DONE".parse::<Status>(), Ok(Status::Done));
+        assert_eq!(Status::InProgress.to_string().parse::<Status>(), Ok(Status::InProgress));
+        assert_eq!("critical".parse::<Priority>(), Ok(Priority::Critical));
+    }
+
+    #[test]
+    fn unknown_status_and_priority_are_errors() {
+        assert_eq!("dne".parse::<Status>(), Err("unknown status 'dne'".to_string()));
+        assert_eq!("urgent".parse::<Priority>(), Err("unknown priority 'urgent'".to_string()));
+        assert_eq!("".parse::<Priority>(), Err("unknown priority ''".to_string()));
+    }
+
+    #[test]
+    fn tags_are_normalized() {
+        assert_eq!(normalize_tag(" #Work "), Some("work".to_string()));
+        assert_eq!(normalize_tag("@home"), Some("home".to_string()));
+        assert_eq!(normalize_tag("+Errand"), Some("errand".to_string()));
+        assert_eq!(normalize_tag("# "), None);
+        assert_eq!(normalize_tag(""), None);
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// How listing commands print their results. Everything except `Table` is
+/// meant for scripts; the schema is documented in `docs/output_formats.md`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Jsonl,
+    Csv,
+    Tsv,
+}
+
+/// Like `println!`, but hands write errors back instead of panicking, so a
+/// reader that quits early (`flow ls | head`) ends the command quietly.
+macro_rules! outln {
+    () => {
+        std::io::Write::write_all(&mut std::io::stdout(), b"\n")
+    };
+    ($($arg:tt)*) => {
+        std::io::Write::write_fmt(&mut std::io::stdout(), format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+pub(crate) use outln;
+
+pub const TASK_FIELDS: &[&str] = &[
+    "id", "title", "description", "status", "priority", "due_date", "project_path", "created_at", "tags",
+    "blocked_by", "parent_id", "subtasks_total", "subtasks_done", "recurrence", "recur_from_completion",
+    "branch", "completed_at", "scope_branch",
+];
+
+pub const NOTE_FIELDS: &[&str] = &["id", "title", "content", "project_path", "created_at", "tags"];
+
+pub const PROJECT_FIELDS: &[&str] = &["id", "name", "path", "identity", "tasks", "notes", "created_at"];
+
+pub const TAG_FIELDS: &[&str] = &["tag", "tasks", "projects"];
+
+pub const TIME_FIELDS: &[&str] = &["group", "seconds", "hours"];
+
+pub const SEARCH_FIELDS: &[&str] = &["kind", "id", "title", "snippet", "rank", "project_path"];
+
+/// Prints `records` in a machine-readable format. `fields` fixes the column
+/// order for CSV and TSV; JSON output carries every serialized field.
+///
+/// Write errors are returned rather than panicking, so `main` can tell a
+/// reader that quit early (`flow --format json ls | head`) from a failure.
+pub fn print_records<T: Serialize>(format: Format, records: &[T], fields: &[&str]) -> Result<()> {
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Table => unreachable!("table output is rendered by each command"),
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(records)?)?,
+        Format::Jsonl => {
+            for record in records {
+                writeln!(out, "{}", serde_json::to_string(record)?)?;
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let sep = if format == Format::Csv { "," } else { "\t" };
+            let escape = |field: &str| match format {
+                Format::Csv => csv_escape(field),
+                _ => tsv_escape(field),
+            };
+            writeln!(out, "{}", fields.join(sep))?;
+            for record in records {
+                let value = serde_json::to_value(record)?;
+                let row: Vec<String> = fields
+                    .iter()
+                    .map(|f| escape(&flatten(value.get(*f).unwrap_or(&Value::Null))))
+                    .collect();
+                writeln!(out, "{}", row.join(sep))?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Prints a single record; JSON gets a bare object rather than an array.
+pub fn print_record<T: Serialize>(format: Format, record: &T, fields: &[&str]) -> Result<()> {
+    match format {
+        Format::Json => {
+            let mut out = io::stdout().lock();
+            writeln!(out, "{}", serde_json::to_string_pretty(record)?)?;
+            out.flush()?;
+            Ok(())
+        }
+        _ => print_records(format, std::slice::from_ref(record), fields),
+    }
+}
+
+// Lists (tags) become comma separated values, nulls become empty cells.
+fn flatten(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(flatten).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn tsv_escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Prints `text`, through `$PAGER` (`less` by default) when it is taller
+/// than the terminal. Setting `PAGER` to an empty string or `cat` turns
+/// paging off.
+pub fn page(text: &str) -> Result<()> {
+    let fits = match crossterm::terminal::size() {
+        Ok((cols, rows)) if cols > 0 && rows > 0 => screen_rows(text, cols as usize) < rows as usize,
+        _ => true,
+    };
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    if fits || !io::stdout().is_terminal() || pager.trim().is_empty() || pager.trim() == "cat" {
+        return print_all(text);
+    }
+
+    let mut command = if cfg!(windows) {
+        let mut parts = pager.split_whitespace();
+        let mut command = Command::new(parts.next().unwrap_or("more"));
+        command.args(parts);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&pager);
+        command
+    };
+    // Like git: keep colors, and let less quit on its own for short output.
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let Ok(mut child) = command.stdin(Stdio::piped()).spawn() else {
+        return print_all(text);
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closes its input when the user quits early.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+fn print_all(text: &str) -> Result<()> {
+    let mut out = io::stdout().lock();
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+// Terminal rows `text` takes up once long lines wrap, ignoring color codes.
+fn screen_rows(text: &str, cols: usize) -> usize {
+    text.lines()
+        .map(|line| {
+            let mut width: usize = 0;
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                if c == '\x1b' {
+                    chars.by_ref().find(|c| c.is_ascii_alphabetic());
+                } else {
+                    width += 1;
+                }
+            }
+            width.max(1).div_ceil(cols)
+        })
+        .sum()
+}
@@ -0,0 +1,227 @@
+use crate::dates;
+use crate::models::{normalize_tag, Priority};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+
+/// Longest trailing phrase considered as a natural language due date.
+const MAX_DATE_WORDS: usize = 4;
+
+/// Prepositions swallowed together with a trailing date ("ship by friday").
+const DATE_PREPOSITIONS: &[&str] = &["by", "on", "at", "due", "before"];
+
+/// What a piece of quick-add input was understood as.
+#[derive(Debug, Clone)]
+pub enum Meaning {
+    Title,
+    Priority(Priority),
+    Tag(String),
+    Due(DateTime<Local>),
+    Project(String),
+    Every(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Piece {
+    pub text: String,
+    pub meaning: Meaning,
+}
+
+/// The result of parsing `flow add` input such as
+/// `Fix login bug tomorrow !high @backend`.
+#[derive(Debug, Clone, Default)]
+pub struct QuickAdd {
+    pub title: String,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub due: Option<DateTime<Local>>,
+    pub project: Option<String>,
+    /// Repeat rule phrase from `every:`, not yet validated.
+    pub every: Option<String>,
+    pub pieces: Vec<Piece>,
+}
+
+impl QuickAdd {
+    /// Uses the input verbatim as the title.
+    pub fn literal(input: &str) -> Self {
+        QuickAdd {
+            title: input.to_string(),
+            pieces: vec![Piece {
+                text: input.to_string(),
+                meaning: Meaning::Title,
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+pub fn parse(input: &str, now: DateTime<Local>) -> Result<QuickAdd> {
+    let mut parsed = QuickAdd::default();
+    // Title words, remembering whether they were escaped so an escaped word
+    // is never mistaken for part of a trailing date.
+    let mut words: Vec<(String, bool)> = Vec::new();
+
+    for token in input.split_whitespace() {
+        if let Some(escaped) = token.strip_prefix('\\') {
+            words.push((escaped.to_string(), true));
+        } else if let Some(priority) = parse_priority(token) {
+            parsed.priority = Some(priority.clone());
+            parsed.pieces.push(piece(token, Meaning::Priority(priority)));
+        } else if let Some(tag) = parse_tag(token) {
+            if !parsed.tags.contains(&tag) {
+                parsed.tags.push(tag.clone());
+            }
+            parsed.pieces.push(piece(token, Meaning::Tag(tag)));
+        } else if let Some(value) = strip_key(token, "due:") {
+            let due = dates::parse_due(&value.replace('_', " "), now)?;
+            parsed.due = Some(due);
+            parsed.pieces.push(piece(token, Meaning::Due(due)));
+        } else if let Some(value) = strip_key(token, "project:") {
+            parsed.project = Some(value.to_string());
+            parsed.pieces.push(piece(token, Meaning::Project(value.to_string())));
+        } else if let Some(value) = strip_key(token, "every:") {
+            let every = value.replace('_', " ");
+            parsed.every = Some(every.clone());
+            parsed.pieces.push(piece(token, Meaning::Every(every)));
+        } else {
+            words.push((token.to_string(), false));
+        }
+    }
+
+    if parsed.due.is_none() {
+        if let Some((start, due)) = trailing_date(&words, now) {
+            let phrase: Vec<String> = words.drain(start..).map(|(w, _)| w).collect();
+            parsed.due = Some(due);
+            parsed.pieces.push(piece(&phrase.join(" "), Meaning::Due(due)));
+        }
+    }
+
+    parsed.title = words
+        .into_iter()
+        .map(|(w, _)| w)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if parsed.title.is_empty() {
+        bail!("Task title is empty after parsing \"{}\" (use --literal to keep it as-is)", input);
+    }
+    parsed.pieces.insert(0, piece(&parsed.title.clone(), Meaning::Title));
+
+    Ok(parsed)
+}
+
+fn piece(text: &str, meaning: Meaning) -> Piece {
+    Piece {
+        text: text.to_string(),
+        meaning,
+    }
+}
+
+fn parse_priority(token: &str) -> Option<Priority> {
+    match token.to_lowercase().as_str() {
+        "!!!" => Some(Priority::Critical),
+        "!!" => Some(Priority::High),
+        "!low" | "!l" => Some(Priority::Low),
+        "!medium" | "!med" | "!m" => Some(Priority::Medium),
+        "!high" | "!h" => Some(Priority::High),
+        "!critical" | "!crit" | "!c" => Some(Priority::Critical),
+        _ => None,
+    }
+}
+
+fn parse_tag(token: &str) -> Option<String> {
+    let rest = token.strip_prefix('@').or_else(|| token.strip_prefix('+'))?;
+    let first = rest.chars().next()?;
+    if !(first.is_alphabetic() || first == '_') {
+        return None;
+    }
+    normalize_tag(rest)
+}
+
+fn strip_key<'a>(token: &'a str, key: &str) -> Option<&'a str> {
+    let value = token.get(..key.len()).filter(|p| p.eq_ignore_ascii_case(key))?;
+    let value = &token[value.len()..];
+    (!value.is_empty()).then_some(value)
+}
+
+/// Finds the longest run of date words at the end of the title that
+/// parses as a date, and returns where it starts. At least one title word is
+/// always left behind.
+fn trailing_date(words: &[(String, bool)], now: DateTime<Local>) -> Option<(usize, DateTime<Local>)> {
+    let max = MAX_DATE_WORDS.min(words.len().saturating_sub(1));
+    for len in (1..=max).rev() {
+        let start = words.len() - len;
+        let candidate = &words[start..];
+        if candidate.iter().any(|(_, escaped)| *escaped) {
+            continue;
+        }
+        let phrase: Vec<&str> = candidate.iter().map(|(w, _)| w.as_str()).collect();
+        if !dates::looks_like_date(&phrase) {
+            continue;
+        }
+        if let Ok(due) = dates::parse_due(&phrase.join(" "), now) {
+            let start = match words.get(start.wrapping_sub(1)) {
+                Some((w, false))
+                    if start > 1 && DATE_PREPOSITIONS.contains(&w.to_lowercase().as_str()) =>
+                {
+                    start - 1
+                }
+                _ => start,
+            };
+            return Some((start, due));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone};
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn may_alone_is_a_word() {
+        let parsed = parse("Ask if we may", now()).unwrap();
+        assert_eq!(parsed.title, "Ask if we may");
+        assert!(parsed.due.is_none());
+    }
+
+    #[test]
+    fn may_with_a_day_is_a_date() {
+        let parsed = parse("Renew passport may 3", now()).unwrap();
+        assert_eq!(parsed.title, "Renew passport");
+        let due = parsed.due.unwrap();
+        assert_eq!((due.month(), due.day()), (5, 3));
+
+        let parsed = parse("Renew passport 3 may", now()).unwrap();
+        assert_eq!(parsed.title, "Renew passport");
+        assert!(parsed.due.is_some());
+    }
+
+    #[test]
+    fn now_is_not_a_trailing_date() {
+        let parsed = parse("Fix it now", now()).unwrap();
+        assert_eq!(parsed.title, "Fix it now");
+        assert!(parsed.due.is_none());
+    }
+
+    #[test]
+    fn other_trailing_dates_still_parse() {
+        let parsed = parse("Ship release by friday", now()).unwrap();
+        assert_eq!(parsed.title, "Ship release");
+        assert_eq!(parsed.due.unwrap().weekday(), chrono::Weekday::Fri);
+
+        let parsed = parse("Call the bank in 2 days", now()).unwrap();
+        assert_eq!(parsed.title, "Call the bank");
+        assert_eq!(parsed.due.unwrap().day(), 12);
+    }
+
+    #[test]
+    fn explicit_due_now_still_works() {
+        let parsed = parse("Fix it due:now", now()).unwrap();
+        assert_eq!(parsed.title, "Fix it");
+        assert_eq!(parsed.due, Some(now()));
+    }
+}
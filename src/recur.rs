@@ -0,0 +1,674 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Duration, Local, Month, Months, NaiveDate, NaiveTime, TimeZone, Weekday};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Which day of the month a monthly rule lands on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonthDay {
+    /// Day number; -1 is the last day of the month.
+    Day(i32),
+    /// The nth weekday of the month; -1 is the last one.
+    Nth(i32, Weekday),
+}
+
+/// A repeat rule, a subset of RFC 5545 RRULE: FREQ, INTERVAL, BYDAY,
+/// BYMONTH and BYMONTHDAY. Stored in its RRULE form, e.g.
+/// `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub freq: Freq,
+    pub interval: u32,
+    pub weekdays: Vec<Weekday>,
+    /// The month a yearly rule lands in, 1 to 12.
+    pub month: Option<u32>,
+    pub month_day: Option<MonthDay>,
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// The largest INTERVAL accepted, far beyond any real schedule but small
+/// enough that stepping by it can't overflow a date.
+const MAX_INTERVAL: u32 = 1000;
+
+/// How far ahead `next_date` looks for a monthly occurrence before giving
+/// up. The calendar repeats every 400 years, so a rule with no occurrence
+/// in that span has none at all.
+const SEARCH_MONTHS: u32 = 400 * 12;
+
+/// Phrase suffixes that switch a rule to counting from the completion date.
+const AFTER_COMPLETION: &[&str] = &["after completion", "from completion", "after done"];
+
+impl Recurrence {
+    /// Parses phrases like "weekly on monday", "every 2 weeks",
+    /// "monthly on the last friday" or a raw RRULE. Also reports whether the
+    /// phrase asked for "after completion" scheduling.
+    pub fn parse(phrase: &str) -> Result<(Recurrence, bool)> {
+        let mut text = phrase.trim().to_lowercase();
+        let mut after_completion = false;
+        for suffix in AFTER_COMPLETION {
+            if let Some(rest) = text.strip_suffix(suffix) {
+                text = rest.trim().to_string();
+                after_completion = true;
+            }
+        }
+
+        if text.starts_with("freq=") || text.starts_with("rrule:") {
+            return Ok((text.parse()?, after_completion));
+        }
+        Ok((parse_phrase(&text).map_err(|e| anyhow!("Could not parse \"{}\": {}", phrase, e))?, after_completion))
+    }
+
+    /// Pins a monthly or yearly rule that doesn't say which day it falls on
+    /// to the day of `start`. Left open, each occurrence would follow the one
+    /// before it, so a single short month (Jan 31 to Feb 28) would move every
+    /// later one for good.
+    pub fn anchored(mut self, start: NaiveDate) -> Recurrence {
+        match self.freq {
+            Freq::Monthly => {
+                self.month_day.get_or_insert(MonthDay::Day(start.day() as i32));
+            }
+            Freq::Yearly => {
+                self.month.get_or_insert(start.month());
+                self.month_day.get_or_insert(MonthDay::Day(start.day() as i32));
+            }
+            Freq::Daily | Freq::Weekly => {}
+        }
+        self
+    }
+
+    /// The first occurrence strictly after `after`, at `time` of day.
+    pub fn next_after(&self, after: DateTime<Local>, time: NaiveTime) -> Result<DateTime<Local>> {
+        let date = self.next_date(after.date_naive())?;
+        Ok(Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .unwrap_or_else(|| after + Duration::days(1)))
+    }
+
+    fn next_date(&self, after: NaiveDate) -> Result<NaiveDate> {
+        let step = self.interval.max(1);
+        let day = match self.freq {
+            Freq::Daily => after.checked_add_signed(Duration::days(step as i64)),
+            Freq::Weekly if self.weekdays.is_empty() => after.checked_add_signed(Duration::weeks(step as i64)),
+            Freq::Weekly => {
+                let week_start = |d: NaiveDate| d - Duration::days(d.weekday().num_days_from_monday() as i64);
+                let anchor = week_start(after);
+                let mut day = after + Duration::days(1);
+                loop {
+                    let week = (week_start(day) - anchor).num_weeks();
+                    if week % step as i64 == 0 && self.weekdays.contains(&day.weekday()) {
+                        break Some(day);
+                    }
+                    day += Duration::days(1);
+                }
+            }
+            Freq::Monthly => {
+                let month_day = self.month_day.unwrap_or(MonthDay::Day(after.day() as i32));
+                let first = after.with_day(1).unwrap_or(after);
+                (0..=SEARCH_MONTHS / step + 1)
+                    .filter_map(|i| first.checked_add_months(Months::new(i * step)))
+                    .filter_map(|month| resolve_month_day(month, month_day))
+                    .find(|day| *day > after)
+            }
+            Freq::Yearly => {
+                let month = self.month.unwrap_or(after.month());
+                let month_day = self.month_day.unwrap_or(MonthDay::Day(after.day() as i32));
+                (0..=SEARCH_MONTHS / 12 / step + 1)
+                    .filter_map(|i| NaiveDate::from_ymd_opt(after.year() + (i * step) as i32, month, 1))
+                    .filter_map(|first| resolve_month_day(first, month_day))
+                    .find(|day| *day > after)
+            }
+        };
+        day.ok_or_else(|| anyhow!("No date {} after {}", self.describe(), after))
+    }
+
+    /// Rejects rules that could never produce a date or would step past
+    /// the end of the calendar.
+    fn validate(&self) -> Result<()> {
+        if self.interval == 0 {
+            bail!("interval must be at least 1");
+        }
+        if self.interval > MAX_INTERVAL {
+            bail!("interval must be at most {}", MAX_INTERVAL);
+        }
+        if self.freq != Freq::Weekly && !self.weekdays.is_empty() {
+            bail!("weekdays only apply to weekly rules, e.g. \"weekly on monday\"");
+        }
+        match self.month {
+            Some(_) if self.freq != Freq::Yearly => bail!("a month only applies to yearly rules"),
+            Some(m) if !(1..=12).contains(&m) => bail!("month must be 1 to 12, not {}", m),
+            _ => {}
+        }
+        match self.month_day {
+            Some(MonthDay::Day(n)) if n != -1 && !(1..=31).contains(&n) => {
+                bail!("day of the month must be 1 to 31 or -1 (the last day), not {}", n)
+            }
+            Some(MonthDay::Nth(n, _)) if n != -1 && !(1..=5).contains(&n) => {
+                bail!("weekday of the month must be the 1st to 5th or -1 (the last), not {}", n)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// A short human description, e.g. "every 2 weeks on Mon, Thu".
+    pub fn describe(&self) -> String {
+        let unit = match self.freq {
+            Freq::Daily => "day",
+            Freq::Weekly => "week",
+            Freq::Monthly => "month",
+            Freq::Yearly => "year",
+        };
+        let mut text = if self.interval <= 1 {
+            format!("every {}", unit)
+        } else {
+            format!("every {} {}s", self.interval, unit)
+        };
+        if self.weekdays == WEEKDAYS[..5] {
+            text.push_str(" on weekdays");
+        } else if !self.weekdays.is_empty() {
+            let days: Vec<String> = self.weekdays.iter().map(|d| d.to_string()).collect();
+            text.push_str(&format!(" on {}", days.join(", ")));
+        }
+        match self.month_day {
+            Some(MonthDay::Day(-1)) => text.push_str(" on the last day"),
+            Some(MonthDay::Day(n)) => text.push_str(&format!(" on the {}", ordinal(n))),
+            Some(MonthDay::Nth(n, day)) => {
+                let nth = if n == -1 { "last".to_string() } else { ordinal(n) };
+                text.push_str(&format!(" on the {} {}", nth, day));
+            }
+            None => {}
+        }
+        if let Some(name) = self.month.and_then(|m| Month::try_from(m as u8).ok()).map(|m| m.name()) {
+            text.push_str(if self.month_day.is_some() { " of " } else { " in " });
+            text.push_str(name);
+        }
+        text
+    }
+}
+
+fn resolve_month_day(month: NaiveDate, month_day: MonthDay) -> Option<NaiveDate> {
+    let next_month = month.checked_add_months(Months::new(1))?;
+    let last = (next_month - Duration::days(1)).day() as i32;
+    match month_day {
+        MonthDay::Day(n) => {
+            let day = if n == -1 { last } else { n.min(last) };
+            month.with_day(day as u32)
+        }
+        MonthDay::Nth(n, weekday) => {
+            if n == -1 {
+                let last_day = month.with_day(last as u32)?;
+                let back = (last_day.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+                Some(last_day - Duration::days(back as i64))
+            } else {
+                let ahead = (weekday.num_days_from_monday() + 7 - month.weekday().num_days_from_monday()) % 7;
+                let day = month + Duration::days(ahead as i64 + 7 * (n as i64 - 1));
+                (day.month() == month.month()).then_some(day)
+            }
+        }
+    }
+}
+
+fn parse_phrase(text: &str) -> Result<Recurrence> {
+    const FILLER: &[&str] = &["every", "each", "on", "the", "and", "of", "a"];
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty() && !FILLER.contains(w))
+        .collect();
+    let mentions_month = words.iter().any(|w| matches!(*w, "month" | "months" | "monthly"));
+
+    let mut freq = None;
+    let mut interval = None;
+    let mut weekdays = Vec::new();
+    let mut month_day = None;
+
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i];
+        let next = words.get(i + 1).copied();
+        match word {
+            "other" => set_interval(&mut interval, 2, word)?,
+            "day" | "days" | "daily" => set_freq(&mut freq, Freq::Daily, word)?,
+            "week" | "weeks" | "weekly" => set_freq(&mut freq, Freq::Weekly, word)?,
+            "biweekly" | "fortnightly" => {
+                set_freq(&mut freq, Freq::Weekly, word)?;
+                set_interval(&mut interval, 2, word)?;
+            }
+            "month" | "months" | "monthly" => set_freq(&mut freq, Freq::Monthly, word)?,
+            "year" | "years" | "yearly" | "annually" => set_freq(&mut freq, Freq::Yearly, word)?,
+            "weekday" | "weekdays" => {
+                set_freq(&mut freq, Freq::Weekly, word)?;
+                weekdays.extend_from_slice(&WEEKDAYS[..5]);
+            }
+            _ if word.chars().all(|c| c.is_ascii_digit()) => {
+                let n = word.parse().map_err(|_| anyhow!("invalid interval '{}'", word))?;
+                set_interval(&mut interval, n, word)?;
+            }
+            _ => {
+                if let Some(day) = parse_weekday(word) {
+                    set_freq(&mut freq, Freq::Weekly, word)?;
+                    weekdays.push(day);
+                } else if let Some(n) = parse_ordinal(word) {
+                    match next.and_then(parse_weekday) {
+                        Some(day) => {
+                            month_day = Some(MonthDay::Nth(n, day));
+                            i += 1;
+                        }
+                        // "the 2nd day" is a day of the month only in a
+                        // phrase about months; "every 2nd day" means days.
+                        None if next == Some("day") && mentions_month => {
+                            month_day = Some(MonthDay::Day(n));
+                            i += 1;
+                        }
+                        None if next.is_some_and(|w| UNITS.contains(&w)) => bail!(
+                            "\"{} {}\" is ambiguous; say \"every {} {}s\" or \"monthly on the {}\"",
+                            word,
+                            next.unwrap_or_default(),
+                            n,
+                            next.unwrap_or_default().trim_end_matches('s'),
+                            word
+                        ),
+                        None if n > 0 => month_day = Some(MonthDay::Day(n)),
+                        None => bail!("'last' must be followed by a weekday or 'day'"),
+                    }
+                    set_freq(&mut freq, Freq::Monthly, word)?;
+                } else {
+                    bail!("unexpected '{}'", word);
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let freq = freq.ok_or_else(|| anyhow!("no frequency (daily, weekly, monthly, yearly or a weekday)"))?;
+    if freq == Freq::Monthly && !weekdays.is_empty() {
+        // "monthly on the first monday" folds the weekday into month_day
+        // above; a bare weekday is ambiguous.
+        bail!("say which one, e.g. \"monthly on the first monday\"");
+    }
+    if freq != Freq::Monthly && month_day.is_some() {
+        bail!("a day of the month only applies to monthly rules");
+    }
+    weekdays.sort_by_key(|d| d.num_days_from_monday());
+    weekdays.dedup();
+
+    let rule = Recurrence {
+        freq,
+        interval: interval.unwrap_or(1),
+        weekdays,
+        month: None,
+        month_day,
+    };
+    rule.validate()?;
+    Ok(rule)
+}
+
+const UNITS: &[&str] = &["day", "days", "week", "weeks", "month", "months", "year", "years"];
+
+// A phrase names one frequency; "daily on the 15th" or "daily on monday"
+// mixes two, and going with either would drop part of what was asked for.
+fn set_freq(freq: &mut Option<Freq>, value: Freq, word: &str) -> Result<()> {
+    match *freq {
+        Some(current) if current != value => bail!("'{}' conflicts with the rest of the rule", word),
+        _ => {
+            *freq = Some(value);
+            Ok(())
+        }
+    }
+}
+
+fn set_interval(interval: &mut Option<u32>, value: u32, word: &str) -> Result<()> {
+    if interval.replace(value).is_some() {
+        bail!("'{}' is a second interval", word);
+    }
+    Ok(())
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let word = word.strip_suffix('s').filter(|w| w.len() > 2).unwrap_or(word);
+    match word {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" | "tues" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" | "thur" | "thurs" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_ordinal(word: &str) -> Option<i32> {
+    match word {
+        "first" => return Some(1),
+        "second" => return Some(2),
+        "third" => return Some(3),
+        "fourth" => return Some(4),
+        "fifth" => return Some(5),
+        "last" => return Some(-1),
+        _ => {}
+    }
+    let digits = word
+        .strip_suffix("st")
+        .or_else(|| word.strip_suffix("nd"))
+        .or_else(|| word.strip_suffix("rd"))
+        .or_else(|| word.strip_suffix("th"))?;
+    digits.parse().ok().filter(|n| (1..=31).contains(n))
+}
+
+fn ordinal(n: i32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let freq = match self.freq {
+            Freq::Daily => "DAILY",
+            Freq::Weekly => "WEEKLY",
+            Freq::Monthly => "MONTHLY",
+            Freq::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={};INTERVAL={}", freq, self.interval)?;
+        if !self.weekdays.is_empty() {
+            let days: Vec<&str> = self.weekdays.iter().map(|d| weekday_code(*d)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(month) = self.month {
+            write!(f, ";BYMONTH={}", month)?;
+        }
+        match self.month_day {
+            Some(MonthDay::Day(n)) => write!(f, ";BYMONTHDAY={}", n)?,
+            Some(MonthDay::Nth(n, day)) => write!(f, ";BYDAY={}{}", n, weekday_code(day))?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let body = s.trim();
+        let body = body
+            .get(..6)
+            .filter(|p| p.eq_ignore_ascii_case("rrule:"))
+            .map_or(body, |_| &body[6..]);
+
+        let mut rule = Recurrence {
+            freq: Freq::Daily,
+            interval: 1,
+            weekdays: Vec::new(),
+            month: None,
+            month_day: None,
+        };
+        let mut has_freq = false;
+
+        for part in body.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid RRULE part '{}'", part))?;
+            let value = value.to_uppercase();
+            match key.to_uppercase().as_str() {
+                "FREQ" => {
+                    rule.freq = match value.as_str() {
+                        "DAILY" => Freq::Daily,
+                        "WEEKLY" => Freq::Weekly,
+                        "MONTHLY" => Freq::Monthly,
+                        "YEARLY" => Freq::Yearly,
+                        _ => bail!("Unsupported FREQ '{}'", value),
+                    };
+                    has_freq = true;
+                }
+                "INTERVAL" => {
+                    rule.interval = value.parse().map_err(|_| anyhow!("Invalid INTERVAL '{}'", value))?;
+                }
+                "BYMONTH" => {
+                    rule.month = Some(value.parse().map_err(|_| anyhow!("Invalid BYMONTH '{}'", value))?);
+                }
+                "BYMONTHDAY" => {
+                    rule.month_day = Some(MonthDay::Day(
+                        value.parse().map_err(|_| anyhow!("Invalid BYMONTHDAY '{}'", value))?,
+                    ));
+                }
+                "BYDAY" => {
+                    for item in value.split(',') {
+                        let split = item.len().saturating_sub(2);
+                        let (nth, code) = item.split_at(split);
+                        let day = WEEKDAYS
+                            .iter()
+                            .copied()
+                            .find(|d| weekday_code(*d) == code)
+                            .ok_or_else(|| anyhow!("Invalid BYDAY '{}'", item))?;
+                        if nth.is_empty() {
+                            rule.weekdays.push(day);
+                        } else {
+                            let n = nth.parse().map_err(|_| anyhow!("Invalid BYDAY '{}'", item))?;
+                            rule.month_day = Some(MonthDay::Nth(n, day));
+                        }
+                    }
+                }
+                other => bail!("Unsupported RRULE part '{}'", other),
+            }
+        }
+
+        if !has_freq {
+            bail!("RRULE is missing FREQ");
+        }
+        rule.validate().map_err(|e| anyhow!("Invalid RRULE: {}", e))?;
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn rule(text: &str) -> Recurrence {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn rejects_out_of_range_rrules() {
+        for text in [
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=-2",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=MONTHLY;BYDAY=-2FR",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=MONTHLY;BYDAY=6MO",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=4000000000",
+            "FREQ=MONTHLY;INTERVAL=1001",
+        ] {
+            assert!(text.parse::<Recurrence>().is_err(), "{} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_rrules() {
+        assert!("INTERVAL=2".parse::<Recurrence>().is_err());
+        assert!("FREQ=HOURLY".parse::<Recurrence>().is_err());
+        assert!("FREQ=WEEKLY;BYDAY=XX".parse::<Recurrence>().is_err());
+        assert!("FREQ=WEEKLY;COUNT=3".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_phrases() {
+        assert!(Recurrence::parse("monthly on the 6th monday").is_err());
+        assert!(Recurrence::parse("every 0 days").is_err());
+        assert!(Recurrence::parse("every 5000 years").is_err());
+    }
+
+    #[test]
+    fn accepts_edge_values() {
+        assert_eq!(rule("FREQ=MONTHLY;BYMONTHDAY=-1").month_day, Some(MonthDay::Day(-1)));
+        assert_eq!(rule("FREQ=MONTHLY;BYMONTHDAY=31").month_day, Some(MonthDay::Day(31)));
+        assert_eq!(rule("FREQ=MONTHLY;BYDAY=5FR").month_day, Some(MonthDay::Nth(5, Weekday::Fri)));
+        assert_eq!(rule("FREQ=MONTHLY;BYDAY=-1FR").month_day, Some(MonthDay::Nth(-1, Weekday::Fri)));
+    }
+
+    #[test]
+    fn monthly_day_31_falls_back_to_the_last_day() {
+        let rule = rule("FREQ=MONTHLY;BYMONTHDAY=31");
+        assert_eq!(rule.next_date(date(2026, 1, 31)).unwrap(), date(2026, 2, 28));
+        assert_eq!(rule.next_date(date(2026, 2, 28)).unwrap(), date(2026, 3, 31));
+        assert_eq!(rule.next_date(date(2026, 4, 10)).unwrap(), date(2026, 4, 30));
+    }
+
+    #[test]
+    fn monthly_last_weekday() {
+        let rule = rule("FREQ=MONTHLY;BYDAY=-1FR");
+        assert_eq!(rule.next_date(date(2026, 10, 1)).unwrap(), date(2026, 10, 30));
+        assert_eq!(rule.next_date(date(2026, 10, 30)).unwrap(), date(2026, 11, 27));
+    }
+
+    #[test]
+    fn monthly_fifth_weekday_skips_months_without_one() {
+        let rule = rule("FREQ=MONTHLY;BYDAY=5FR");
+        assert_eq!(rule.next_date(date(2026, 1, 30)).unwrap(), date(2026, 5, 29));
+    }
+
+    #[test]
+    fn leap_year_february() {
+        let last = rule("FREQ=MONTHLY;BYMONTHDAY=-1");
+        assert_eq!(last.next_date(date(2028, 1, 31)).unwrap(), date(2028, 2, 29));
+        assert_eq!(last.next_date(date(2027, 1, 31)).unwrap(), date(2027, 2, 28));
+
+        let day_30 = rule("FREQ=MONTHLY;BYMONTHDAY=30");
+        assert_eq!(day_30.next_date(date(2028, 1, 30)).unwrap(), date(2028, 2, 29));
+
+        let yearly = rule("FREQ=YEARLY");
+        assert_eq!(yearly.next_date(date(2028, 2, 29)).unwrap(), date(2029, 2, 28));
+    }
+
+    #[test]
+    fn fifth_weekday_in_a_rare_month_is_found() {
+        // February has a fifth Sunday only when it is a leap month starting
+        // on a Sunday, next in 2032.
+        let rule = rule("FREQ=MONTHLY;INTERVAL=12;BYDAY=5SU");
+        assert_eq!(rule.next_date(date(2026, 2, 1)).unwrap(), date(2032, 2, 29));
+    }
+
+    #[test]
+    fn impossible_monthly_rule_gives_up() {
+        let rule = Recurrence {
+            freq: Freq::Monthly,
+            interval: 1,
+            weekdays: Vec::new(),
+            month: None,
+            month_day: Some(MonthDay::Day(0)),
+        };
+        assert!(rule.next_date(date(2026, 1, 1)).is_err());
+    }
+
+    #[test]
+    fn anchored_monthly_rule_returns_to_its_day_after_a_short_month() {
+        let (monthly, _) = Recurrence::parse("monthly").unwrap();
+        let rule = monthly.anchored(date(2026, 1, 31));
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;INTERVAL=1;BYMONTHDAY=31");
+        assert_eq!(rule.next_date(date(2026, 1, 31)).unwrap(), date(2026, 2, 28));
+        assert_eq!(rule.next_date(date(2026, 2, 28)).unwrap(), date(2026, 3, 31));
+        assert_eq!(rule.next_date(date(2026, 3, 31)).unwrap(), date(2026, 4, 30));
+    }
+
+    #[test]
+    fn anchored_yearly_rule_returns_to_leap_day() {
+        let (yearly, _) = Recurrence::parse("yearly").unwrap();
+        let rule = yearly.anchored(date(2028, 2, 29));
+        assert_eq!(rule.to_string(), "FREQ=YEARLY;INTERVAL=1;BYMONTH=2;BYMONTHDAY=29");
+        assert_eq!(rule.describe(), "every year on the 29th of February");
+        assert_eq!(rule.next_date(date(2028, 2, 29)).unwrap(), date(2029, 2, 28));
+        assert_eq!(rule.next_date(date(2031, 2, 28)).unwrap(), date(2032, 2, 29));
+        assert_eq!(rule.to_string().parse::<Recurrence>().unwrap(), rule);
+    }
+
+    #[test]
+    fn anchoring_keeps_a_day_the_rule_already_names() {
+        let (rule, _) = Recurrence::parse("monthly on the last friday").unwrap();
+        assert_eq!(rule.clone().anchored(date(2026, 1, 10)), rule);
+        let (weekly, _) = Recurrence::parse("weekly").unwrap();
+        assert_eq!(weekly.clone().anchored(date(2026, 1, 10)), weekly);
+    }
+
+    #[test]
+    fn yearly_rule_finds_a_later_day_in_the_same_year() {
+        let rule = rule("FREQ=YEARLY;BYMONTH=11;BYDAY=4TH");
+        assert_eq!(rule.next_date(date(2026, 1, 1)).unwrap(), date(2026, 11, 26));
+        assert_eq!(rule.next_date(date(2026, 11, 26)).unwrap(), date(2027, 11, 25));
+    }
+
+    #[test]
+    fn rejects_phrases_it_only_partly_understands() {
+        for phrase in [
+            "every 2nd day",
+            "every 3rd week",
+            "daily on monday",
+            "daily on weekdays",
+            "yearly on friday",
+            "daily on the 15th",
+            "monthly weekly",
+            "every 2 3 days",
+        ] {
+            assert!(Recurrence::parse(phrase).is_err(), "{} should be rejected", phrase);
+        }
+    }
+
+    #[test]
+    fn rejects_rrules_that_mix_parts() {
+        assert!("FREQ=DAILY;BYDAY=MO".parse::<Recurrence>().is_err());
+        assert!("FREQ=MONTHLY;BYMONTH=2".parse::<Recurrence>().is_err());
+        assert!("FREQ=YEARLY;BYMONTH=13".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn parses_phrases() {
+        let parse = |phrase: &str| Recurrence::parse(phrase).unwrap().0.to_string();
+        assert_eq!(parse("weekly on monday and thursday"), "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,TH");
+        assert_eq!(parse("every other week"), "FREQ=WEEKLY;INTERVAL=2");
+        assert_eq!(parse("every weekday"), "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,TU,WE,TH,FR");
+        assert_eq!(parse("monthly on the 2nd day"), "FREQ=MONTHLY;INTERVAL=1;BYMONTHDAY=2");
+        assert_eq!(parse("the last day of the month"), "FREQ=MONTHLY;INTERVAL=1;BYMONTHDAY=-1");
+        assert_eq!(parse("every 3 months on the first monday"), "FREQ=MONTHLY;INTERVAL=3;BYDAY=1MO");
+        assert!(Recurrence::parse("daily after completion").unwrap().1);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lexer;
+use crate::models::CodeComment;
+
+/// Comment markers picked up by `flow scan`.
+pub const MARKERS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// Tokens that start a comment in common languages. A marker only counts
+/// as the first word after one of these, so prose that merely mentions
+/// "TODO" is left alone.
+const COMMENT_TOKENS: &[&str] = &["//", "//!", "#", "/*", "*", "--", ";", "<!--", "%"];
+
+/// Files larger than this are skipped; they are rarely hand-written.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+const MAX_TITLE_LEN: usize = 100;
+
+/// Index entries with this mode are submodules, not files.
+const GITLINK_MODE: u32 = 0o160000;
+
+/// Finds marker comments in every file tracked by the repository containing
+/// `dir`, reading the working copy so uncommitted edits count. Returns the
+/// repository root, the number of files read and the comments found.
+pub fn scan_repository(dir: &Path) -> Result<(PathBuf, usize, Vec<CodeComment>)> {
+    let repo = Repository::discover(dir).context("`flow scan` must be run inside a git repository")?;
+    let workdir = repo.workdir().context("Cannot scan a bare repository")?.to_path_buf();
+    let index = repo.index()?;
+
+    let mut files = 0;
+    let mut comments = Vec::new();
+    for entry in index.iter() {
+        if entry.mode == GITLINK_MODE {
+            continue;
+        }
+        let Ok(rel) = std::str::from_utf8(&entry.path) else { continue };
+        let path = workdir.join(rel);
+        match fs::metadata(&path) {
+            Ok(meta) if meta.is_file() && meta.len() <= MAX_FILE_SIZE => {}
+            _ => continue,
+        }
+        let Ok(bytes) = fs::read(&path) else { continue };
+        if bytes[..bytes.len().min(8000)].contains(&0) {
+            continue;
+        }
+        files += 1;
+        comments.extend(scan_text(rel, &String::from_utf8_lossy(&bytes)));
+    }
+    Ok((workdir, files, comments))
+}
+
+fn scan_text(file: &str, content: &str) -> Vec<CodeComment> {
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    let mut comments = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let Some((kind, text)) = find_marker(line) else { continue };
+        // Identical comments in one file are told apart by their order, so
+        // moving code around doesn't change any fingerprint.
+        let occurrence = seen.entry((kind.to_string(), text.clone())).or_insert(0);
+        let fingerprint = fingerprint(&[file, kind, &text, &occurrence.to_string()]);
+        *occurrence += 1;
+
+        comments.push(CodeComment {
+            fingerprint,
+            kind: kind.to_string(),
+            text,
+            file: file.to_string(),
+            line: i as i64 + 1,
+        });
+    }
+    comments
+}
+
+/// The marker in a comment on `line` and the text following it.
+fn find_marker(line: &str) -> Option<(&'static str, String)> {
+    for marker in MARKERS {
+        let mut from = 0;
+        while let Some(pos) = line[from..].find(marker).map(|p| p + from) {
+            from = pos + marker.len();
+            let before = &line[..pos];
+            let after = &line[from..];
+            let bounded = !before.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                && !after.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+            let lead = before.trim_end();
+            if !bounded || !COMMENT_TOKENS.iter().any(|t| lead.ends_with(t)) || in_string(line, pos) {
+                continue;
+            }
+            return Some((marker, clean_text(after)));
+        }
+    }
+    None
+}
+
+/// Whether `pos` falls inside a string literal on `line`. Quotes are paired
+/// up from the start of the line; one that isn't closed on the line is
+/// taken as an apostrophe or a multi-line string and doesn't count.
+fn in_string(line: &str, pos: usize) -> bool {
+    let mut i = 0;
+    while i < pos {
+        let rest = &line[i..];
+        let Some(c) = rest.chars().next() else { break };
+        if matches!(c, '"' | '\'' | '`') {
+            let len = lexer::string_len(rest, c);
+            if len > 1 && rest[..len].ends_with(c) {
+                if i + len > pos {
+                    return true;
+                }
+                i += len;
+                continue;
+            }
+        }
+        i += c.len_utf8();
+    }
+    false
+}
+
+// "(alice): fix this */" becomes "fix this", and so does a "fix this";
+// that ends a line of code.
+fn clean_text(after: &str) -> String {
+    let mut text = after.trim_start();
+    if text.starts_with('(') {
+        if let Some(end) = text.find(')') {
+            text = &text[end + 1..];
+        }
+    }
+    let mut text = text.trim_start_matches([':', '-', ' ', '\t']).trim();
+    loop {
+        let before = text.len();
+        text = text.trim_end_matches("*/").trim_end_matches("-->").trim_end_matches(';').trim_end();
+        // A quote without a partner closed a string the comment sat in.
+        if let Some(quote) = text.chars().last().filter(|c| matches!(c, '"' | '\'' | '`')) {
+            if text.matches(quote).count() % 2 == 1 {
+                text = text[..text.len() - 1].trim_end();
+            }
+        }
+        if text.len() == before {
+            break;
+        }
+    }
+    text.chars().take(MAX_TITLE_LEN).collect()
+}
+
+// FNV-1a: stable across Rust releases, unlike `DefaultHasher`.
+fn fingerprint(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_markers_in_comments() {
+        assert_eq!(find_marker("    // TODO: fix this"), Some(("TODO", "fix this".to_string())));
+        assert_eq!(find_marker("x = 1  # FIXME(alice): off by one"), Some(("FIXME", "off by one".to_string())));
+        assert_eq!(find_marker("/* HACK: until the API settles */"), Some(("HACK", "until the API settles".to_string())));
+        assert_eq!(find_marker("<!-- TODO add a logo -->"), Some(("TODO", "add a logo".to_string())));
+        assert_eq!(find_marker("let s = \"a\"; // TODO: don't keep \"this\""), Some(("TODO", "don't keep \"this\"".to_string())));
+    }
+
+    #[test]
+    fn ignores_markers_in_strings() {
+        assert_eq!(find_marker("let s = \"// TODO not a comment\";"), None);
+        assert_eq!(find_marker("s = '# FIXME not a comment'"), None);
+        assert_eq!(find_marker("const s = `/* HACK */`;"), None);
+        assert_eq!(find_marker("let s = \"escaped \\\" // TODO still a string\";"), None);
+    }
+
+    #[test]
+    fn ignores_markers_in_prose() {
+        assert_eq!(find_marker("We still have a TODO list somewhere."), None);
+        assert_eq!(find_marker("// not a TODO"), None);
+        assert_eq!(find_marker("// TODOS are fine"), None);
+    }
+
+    #[test]
+    fn unclosed_quotes_do_not_hide_markers() {
+        assert_eq!(find_marker("fn f<'a>() {} // TODO: lifetimes"), Some(("TODO", "lifetimes".to_string())));
+    }
+
+    #[test]
+    fn cleans_trailing_code() {
+        assert_eq!(clean_text(": not a comment\";"), "not a comment");
+        assert_eq!(clean_text(" fix this */"), "fix this");
+        assert_eq!(clean_text(" quote \"this\""), "quote \"this\"");
+        assert_eq!(clean_text(" don't drop it"), "don't drop it");
+    }
+}
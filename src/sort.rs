@@ -0,0 +1,240 @@
+use crate::models::Task;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Duration, Local};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Priority,
+    Due,
+    Created,
+    Completed,
+    Status,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Parses `field[:asc|:desc]`. Priority sorts highest first unless asked
+    /// otherwise; every other field defaults to ascending.
+    pub fn parse(spec: &str) -> Result<SortKey> {
+        let (name, direction) = match spec.split_once(':') {
+            Some((name, dir)) => (name, Some(dir)),
+            None => (spec, None),
+        };
+        let field = match name.trim().to_lowercase().as_str() {
+            "priority" | "pri" => SortField::Priority,
+            "due" => SortField::Due,
+            "created" => SortField::Created,
+            "completed" => SortField::Completed,
+            "status" => SortField::Status,
+            "title" => SortField::Title,
+            other => bail!("Unknown sort key '{}' (expected priority, due, created, completed, status or title)", other),
+        };
+        let descending = match direction.map(|d| d.trim().to_lowercase()) {
+            None => field == SortField::Priority,
+            Some(d) if d == "asc" => false,
+            Some(d) if d == "desc" => true,
+            Some(d) => bail!("Unknown sort direction '{}' (expected asc or desc)", d),
+        };
+        Ok(SortKey { field, descending })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Status,
+    Priority,
+    Project,
+    Tag,
+    Due,
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "status" => Ok(GroupBy::Status),
+            "priority" | "pri" => Ok(GroupBy::Priority),
+            "project" => Ok(GroupBy::Project),
+            "tag" | "tags" => Ok(GroupBy::Tag),
+            "due" => Ok(GroupBy::Due),
+            _ => Err(anyhow!(
+                "Unknown grouping '{}' (expected status, priority, project, tag or due)",
+                s
+            )),
+        }
+    }
+}
+
+/// Splits already sorted tasks into labelled sections, keeping the order of
+/// tasks inside each section. A task with several tags appears under each.
+pub fn group_tasks(tasks: &[Task], group_by: GroupBy, now: DateTime<Local>) -> Vec<(String, Vec<Task>)> {
+    // (section order, label) for every section a task belongs to
+    let keys = |task: &Task| -> Vec<(i64, String)> {
+        match group_by {
+            GroupBy::Status => vec![(task.status.rank(), task.status.to_string())],
+            GroupBy::Priority => vec![(-task.priority.rank(), task.priority.to_string())],
+            GroupBy::Project => vec![(0, task.project_path.clone().unwrap_or_else(|| "(no project)".to_string()))],
+            GroupBy::Tag if task.tags.is_empty() => vec![(1, "(untagged)".to_string())],
+            GroupBy::Tag => task.tags.iter().map(|t| (0, format!("#{}", t))).collect(),
+            GroupBy::Due => {
+                let bucket = DueBucket::of(task.due_date, now);
+                vec![(bucket as i64, bucket.label().to_string())]
+            }
+        }
+    };
+
+    let mut groups: Vec<((i64, String), Vec<Task>)> = Vec::new();
+    for task in tasks {
+        for key in keys(task) {
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, members)) => members.push(task.clone()),
+                None => groups.push((key, vec![task.clone()])),
+            }
+        }
+    }
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    groups.into_iter().map(|((_, label), tasks)| (label, tasks)).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DueBucket {
+    Overdue,
+    Today,
+    Tomorrow,
+    ThisWeek,
+    Later,
+    NoDueDate,
+}
+
+impl DueBucket {
+    pub fn of(due: Option<DateTime<Local>>, now: DateTime<Local>) -> DueBucket {
+        let Some(due) = due else {
+            return DueBucket::NoDueDate;
+        };
+        let today = now.date_naive();
+        let day = due.date_naive();
+        let end_of_week = today + Duration::days(6 - today.weekday().num_days_from_monday() as i64);
+        if day < today {
+            DueBucket::Overdue
+        } else if day == today {
+            DueBucket::Today
+        } else if day == today + Duration::days(1) {
+            DueBucket::Tomorrow
+        } else if day <= end_of_week {
+            DueBucket::ThisWeek
+        } else {
+            DueBucket::Later
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DueBucket::Overdue => "Overdue",
+            DueBucket::Today => "Today",
+            DueBucket::Tomorrow => "Tomorrow",
+            DueBucket::ThisWeek => "This week",
+            DueBucket::Later => "Later",
+            DueBucket::NoDueDate => "No due date",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, Status};
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Local> {
+        // A Wednesday.
+        Local.with_ymd_and_hms(2026, 3, 11, 12, 0, 0).unwrap()
+    }
+
+    fn task(title: &str, priority: Priority, tags: &[&str]) -> Task {
+        Task {
+            id: None,
+            title: title.to_string(),
+            description: None,
+            status: Status::Todo,
+            priority,
+            due_date: None,
+            project_path: None,
+            created_at: now(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            blocked_by: Vec::new(),
+            parent_id: None,
+            subtasks_total: 0,
+            subtasks_done: 0,
+            recurrence: None,
+            recur_from_completion: false,
+            branch: None,
+            completed_at: None,
+            scope_branch: None,
+        }
+    }
+
+    fn sections(groups: &[(String, Vec<Task>)]) -> Vec<(String, Vec<String>)> {
+        groups.iter().map(|(label, tasks)| (label.clone(), tasks.iter().map(|t| t.title.clone()).collect())).collect()
+    }
+
+    #[test]
+    fn sort_keys_default_their_direction() {
+        let key = SortKey::parse("pri").unwrap();
+        assert_eq!((key.field, key.descending), (SortField::Priority, true));
+        let key = SortKey::parse("priority:asc").unwrap();
+        assert_eq!((key.field, key.descending), (SortField::Priority, false));
+        let key = SortKey::parse("Due:DESC").unwrap();
+        assert_eq!((key.field, key.descending), (SortField::Due, true));
+        assert!(!SortKey::parse("title").unwrap().descending);
+    }
+
+    #[test]
+    fn unknown_sort_keys_are_errors() {
+        assert!(SortKey::parse("size").unwrap_err().to_string().starts_with("Unknown sort key 'size'"));
+        assert!(SortKey::parse("due:up").unwrap_err().to_string().starts_with("Unknown sort direction 'up'"));
+        assert!("colour".parse::<GroupBy>().is_err());
+    }
+
+    #[test]
+    fn groups_follow_their_natural_order() {
+        let tasks = [
+            task("Low", Priority::Low, &[]),
+            task("Critical", Priority::Critical, &[]),
+            task("Also low", Priority::Low, &[]),
+        ];
+        assert_eq!(
+            sections(&group_tasks(&tasks, GroupBy::Priority, now())),
+            [
+                ("Critical".to_string(), vec!["Critical".to_string()]),
+                ("Low".to_string(), vec!["Low".to_string(), "Also low".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_task_appears_under_each_of_its_tags() {
+        let tasks = [task("Both", Priority::Medium, &["bug", "ui"]), task("None", Priority::Medium, &[])];
+        let groups = sections(&group_tasks(&tasks, GroupBy::Tag, now()));
+        let labels: Vec<&str> = groups.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["#bug", "#ui", "(untagged)"]);
+        assert_eq!(groups[1].1, ["Both"]);
+    }
+
+    #[test]
+    fn due_dates_fall_into_buckets() {
+        let day = |d: u32| Some(Local.with_ymd_and_hms(2026, 3, d, 9, 0, 0).unwrap());
+        assert_eq!(DueBucket::of(day(10), now()), DueBucket::Overdue);
+        assert_eq!(DueBucket::of(day(11), now()), DueBucket::Today);
+        assert_eq!(DueBucket::of(day(12), now()), DueBucket::Tomorrow);
+        assert_eq!(DueBucket::of(day(15), now()), DueBucket::ThisWeek);
+        assert_eq!(DueBucket::of(day(16), now()), DueBucket::Later);
+        assert_eq!(DueBucket::of(None, now()), DueBucket::NoDueDate);
+    }
+}
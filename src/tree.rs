@@ -0,0 +1,124 @@
+use crate::models::Task;
+use std::collections::{HashMap, HashSet};
+
+/// One visible line of a task tree.
+pub struct TreeRow<'a> {
+    pub task: &'a Task,
+    /// Box-drawing prefix such as "│  ├─ ".
+    pub prefix: String,
+    pub has_children: bool,
+    pub collapsed: bool,
+}
+
+/// Arranges `tasks` into a depth-first tree, keeping their order among
+/// siblings. Tasks whose parent isn't in the list are shown as roots, and the
+/// children of any ID in `collapsed` are hidden.
+pub fn build_tree<'a>(tasks: &'a [Task], collapsed: &HashSet<i64>) -> Vec<TreeRow<'a>> {
+    let ids: HashSet<i64> = tasks.iter().filter_map(|t| t.id).collect();
+    let mut children: HashMap<i64, Vec<&Task>> = HashMap::new();
+    let mut roots = Vec::new();
+    for task in tasks {
+        match task.parent_id.filter(|p| ids.contains(p)) {
+            Some(parent) => children.entry(parent).or_default().push(task),
+            None => roots.push(task),
+        }
+    }
+
+    let mut rows = Vec::new();
+    for task in roots {
+        walk(task, String::new(), None, &children, collapsed, &mut rows);
+    }
+    rows
+}
+
+fn walk<'a>(
+    task: &'a Task,
+    indent: String,
+    is_last: Option<bool>,
+    children: &HashMap<i64, Vec<&'a Task>>,
+    collapsed: &HashSet<i64>,
+    rows: &mut Vec<TreeRow<'a>>,
+) {
+    let id = task.id.unwrap_or(0);
+    let kids = children.get(&id).map(Vec::as_slice).unwrap_or(&[]);
+    let is_collapsed = collapsed.contains(&id);
+    let prefix = match is_last {
+        None => String::new(),
+        Some(true) => format!("{}└─ ", indent),
+        Some(false) => format!("{}├─ ", indent),
+    };
+    rows.push(TreeRow {
+        task,
+        prefix,
+        has_children: !kids.is_empty(),
+        collapsed: is_collapsed,
+    });
+
+    if is_collapsed {
+        return;
+    }
+    let child_indent = match is_last {
+        None => String::new(),
+        Some(true) => format!("{}   ", indent),
+        Some(false) => format!("{}│  ", indent),
+    };
+    for (i, child) in kids.iter().enumerate() {
+        walk(child, child_indent.clone(), Some(i == kids.len() - 1), children, collapsed, rows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, Status};
+    use chrono::Local;
+
+    fn task(id: i64, parent_id: Option<i64>) -> Task {
+        Task {
+            id: Some(id),
+            title: format!("Task {}", id),
+            description: None,
+            status: Status::Todo,
+            priority: Priority::Medium,
+            due_date: None,
+            project_path: None,
+            created_at: Local::now(),
+            tags: Vec::new(),
+            blocked_by: Vec::new(),
+            parent_id,
+            subtasks_total: 0,
+            subtasks_done: 0,
+            recurrence: None,
+            recur_from_completion: false,
+            branch: None,
+            completed_at: None,
+            scope_branch: None,
+        }
+    }
+
+    fn lines(rows: &[TreeRow]) -> Vec<String> {
+        rows.iter().map(|r| format!("{}{}", r.prefix, r.task.id.unwrap())).collect()
+    }
+
+    #[test]
+    fn children_follow_their_parent() {
+        let tasks = [task(1, None), task(2, Some(1)), task(3, Some(2)), task(4, Some(1)), task(5, None)];
+        let rows = build_tree(&tasks, &HashSet::new());
+        assert_eq!(lines(&rows), ["1", "├─ 2", "│  └─ 3", "└─ 4", "5"]);
+        assert!(rows[0].has_children && !rows[4].has_children);
+    }
+
+    #[test]
+    fn subtasks_of_missing_parents_are_roots() {
+        let tasks = [task(2, Some(1)), task(3, Some(2))];
+        assert_eq!(lines(&build_tree(&tasks, &HashSet::new())), ["2", "└─ 3"]);
+    }
+
+    #[test]
+    fn collapsed_tasks_hide_their_children() {
+        let tasks = [task(1, None), task(2, Some(1)), task(3, Some(2))];
+        let rows = build_tree(&tasks, &HashSet::from([2]));
+        assert_eq!(lines(&rows), ["1", "└─ 2"]);
+        assert!(rows[1].collapsed);
+    }
+}
@@ -0,0 +1,225 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::models::{Priority, Status, Task};
+
+pub const COLUMNS: [Status; 3] = [Status::Todo, Status::InProgress, Status::Done];
+
+/// The Kanban board: one column per status, with the cursor on a card in
+/// one of them.
+pub struct Board {
+    column: usize,
+    states: [ListState; 3],
+}
+
+impl Board {
+    pub fn new() -> Board {
+        Board { column: 0, states: Default::default() }
+    }
+
+    fn cards(tasks: &[Task], column: usize) -> Vec<&Task> {
+        tasks.iter().filter(|t| t.status == COLUMNS[column]).collect()
+    }
+
+    pub fn selected_id(&self, tasks: &[Task]) -> Option<i64> {
+        let i = self.states[self.column].selected()?;
+        Self::cards(tasks, self.column).get(i).and_then(|t| t.id)
+    }
+
+    /// Puts the cursor on the card for `id`, following it to its column,
+    /// or keeps every column's cursor in range if it is gone.
+    pub fn select_id(&mut self, tasks: &[Task], id: Option<i64>) {
+        for column in 0..COLUMNS.len() {
+            let cards = Self::cards(tasks, column);
+            let state = &mut self.states[column];
+            match id.and_then(|id| cards.iter().position(|t| t.id == Some(id))) {
+                Some(i) => {
+                    state.select(Some(i));
+                    self.column = column;
+                }
+                None if cards.is_empty() => state.select(None),
+                None => state.select(Some(state.selected().unwrap_or(0).min(cards.len() - 1))),
+            }
+        }
+    }
+
+    pub fn next(&mut self, tasks: &[Task]) {
+        let len = Self::cards(tasks, self.column).len();
+        if len > 0 {
+            let state = &mut self.states[self.column];
+            state.select(Some(state.selected().map_or(0, |i| (i + 1) % len)));
+        }
+    }
+
+    pub fn previous(&mut self, tasks: &[Task]) {
+        let len = Self::cards(tasks, self.column).len();
+        if len > 0 {
+            let state = &mut self.states[self.column];
+            state.select(Some(state.selected().map_or(0, |i| (i + len - 1) % len)));
+        }
+    }
+
+    /// Moves the cursor to the column on the left (`-1`) or right (`1`).
+    pub fn focus(&mut self, step: isize) {
+        self.column = self.column.saturating_add_signed(step).min(COLUMNS.len() - 1);
+    }
+
+    /// The selected card and the status it would get moving one column to
+    /// the left (`-1`) or right (`1`), if there is a column there.
+    pub fn move_target(&self, tasks: &[Task], step: isize) -> Option<(i64, Status)> {
+        let column = self.column.checked_add_signed(step).filter(|c| *c < COLUMNS.len())?;
+        Some((self.selected_id(tasks)?, COLUMNS[column].clone()))
+    }
+
+    pub fn draw(&mut self, f: &mut Frame, area: Rect, tasks: &[Task], limits: &[Option<usize>; 3]) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 3); 3].as_ref())
+            .split(area);
+
+        for (column, chunk) in chunks.iter().enumerate() {
+            let cards = Self::cards(tasks, column);
+            let over = limits[column].is_some_and(|limit| cards.len() > limit);
+            let count = match limits[column] {
+                Some(limit) => format!("{}/{}", cards.len(), limit),
+                None => cards.len().to_string(),
+            };
+            let title = format!("{} ({})", column_name(column), count);
+            let (border, title_style) = if over {
+                (Style::default().fg(Color::Red), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+            } else if column == self.column {
+                (Style::default().fg(Color::Yellow), Style::default().add_modifier(Modifier::BOLD))
+            } else {
+                (Style::default(), Style::default())
+            };
+
+            let items: Vec<ListItem> = cards.iter().map(|t| card(t)).collect();
+            let mut list = List::new(items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border)
+                    .title(Span::styled(title, title_style)),
+            );
+            if column == self.column {
+                list = list
+                    .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
+                    .highlight_symbol("> ");
+            }
+            f.render_stateful_widget(list, *chunk, &mut self.states[column]);
+        }
+    }
+}
+
+pub fn column_name(column: usize) -> &'static str {
+    match COLUMNS[column] {
+        Status::Todo => "Todo",
+        Status::InProgress => "In Progress",
+        Status::Done => "Done",
+    }
+}
+
+// A card is the title with a priority dot, then its ID, due date and tags.
+fn card(task: &Task) -> ListItem<'static> {
+    let color = match task.priority {
+        Priority::High | Priority::Critical => Color::Red,
+        Priority::Medium => Color::Yellow,
+        Priority::Low => Color::Blue,
+    };
+    let title = Line::from(vec![Span::styled("● ", Style::default().fg(color)), Span::raw(task.title.clone())]);
+
+    let mut details = vec![format!("#{}", task.id.unwrap_or(0))];
+    if let Some(due) = task.due_date {
+        details.push(format!("due {}", due.format("%b %d")));
+    }
+    if task.is_blocked() {
+        details.push("blocked".to_string());
+    }
+    details.extend(task.tags.iter().map(|t| format!("#{}", t)));
+    let details = Line::from(Span::styled(format!("  {}", details.join(" · ")), Style::default().fg(Color::DarkGray)));
+    ListItem::new(vec![title, details])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn task(id: i64, status: Status) -> Task {
+        Task {
+            id: Some(id),
+            title: format!("Task {}", id),
+            description: None,
+            status,
+            priority: Priority::Medium,
+            due_date: None,
+            project_path: None,
+            created_at: Local::now(),
+            tags: Vec::new(),
+            blocked_by: Vec::new(),
+            parent_id: None,
+            subtasks_total: 0,
+            subtasks_done: 0,
+            recurrence: None,
+            recur_from_completion: false,
+            branch: None,
+            completed_at: None,
+            scope_branch: None,
+        }
+    }
+
+    #[test]
+    fn selection_follows_a_card_to_its_column() {
+        let mut tasks = vec![task(1, Status::Todo), task(2, Status::Todo), task(3, Status::InProgress)];
+        let mut board = Board::new();
+        board.select_id(&tasks, Some(2));
+        assert_eq!(board.selected_id(&tasks), Some(2));
+
+        tasks[1].status = Status::Done;
+        board.select_id(&tasks, Some(2));
+        assert_eq!(board.selected_id(&tasks), Some(2));
+        assert_eq!(board.move_target(&tasks, 1), None);
+        assert_eq!(board.move_target(&tasks, -1), Some((2, Status::InProgress)));
+
+        // A card that is gone leaves the cursor in range.
+        tasks.truncate(1);
+        board.select_id(&tasks, Some(2));
+        assert_eq!(board.selected_id(&tasks), None);
+        board.focus(-2);
+        assert_eq!(board.selected_id(&tasks), Some(1));
+        assert_eq!(board.move_target(&tasks, -1), None);
+        assert_eq!(board.move_target(&tasks, 1), Some((1, Status::InProgress)));
+    }
+
+    #[test]
+    fn cursor_wraps_within_a_column() {
+        let tasks = vec![task(1, Status::Todo), task(2, Status::InProgress), task(3, Status::Todo)];
+        let mut board = Board::new();
+        board.next(&tasks);
+        assert_eq!(board.selected_id(&tasks), Some(1));
+        board.next(&tasks);
+        assert_eq!(board.selected_id(&tasks), Some(3));
+        board.next(&tasks);
+        assert_eq!(board.selected_id(&tasks), Some(1));
+        board.previous(&tasks);
+        assert_eq!(board.selected_id(&tasks), Some(3));
+
+        board.focus(5);
+        assert_eq!(board.selected_id(&tasks), None);
+        board.next(&tasks);
+        assert_eq!(board.selected_id(&tasks), None);
+        board.focus(-1);
+        board.next(&tasks);
+        assert_eq!(board.selected_id(&tasks), Some(2));
+    }
+
+    #[test]
+    fn columns_are_named_after_their_status() {
+        let names: Vec<&str> = (0..COLUMNS.len()).map(column_name).collect();
+        assert_eq!(names, ["Todo", "In Progress", "Done"]);
+    }
+}
@@ -0,0 +1,494 @@
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::dates;
+use crate::models::{normalize_tag, Priority, Status, Task, TaskUpdate};
+
+const PRIORITIES: [Priority; 4] = [Priority::Low, Priority::Medium, Priority::High, Priority::Critical];
+
+/// Width of the label column, "Description: ".
+const LABEL_WIDTH: u16 = 13;
+
+const DUE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Priority,
+    Due,
+    Tags,
+    Description,
+}
+
+const FIELDS: [Field; 5] = [Field::Title, Field::Priority, Field::Due, Field::Tags, Field::Description];
+
+/// What a key press asks of the form's owner.
+pub enum FormAction {
+    None,
+    Cancel,
+    Submit,
+}
+
+/// A validated form, ready to be stored.
+pub enum FormOutput {
+    /// A new task; the caller fills in where it belongs.
+    Add(Task),
+    /// Changes to an existing task. May be empty.
+    Update(i64, TaskUpdate),
+}
+
+/// The modal for adding a task (`a`) or editing the selected one (`e`).
+pub struct TaskForm {
+    /// The task being edited, `None` when adding.
+    task: Option<Task>,
+    focus: Field,
+    title: Input,
+    priority: Priority,
+    due: Input,
+    tags: Input,
+    description: Input,
+    /// Problems found by the last attempt to save, cleared field by field
+    /// as they are edited.
+    errors: Vec<(Field, String)>,
+}
+
+impl TaskForm {
+    pub fn add() -> TaskForm {
+        TaskForm {
+            task: None,
+            focus: Field::Title,
+            title: Input::default(),
+            priority: Priority::Medium,
+            due: Input::default(),
+            tags: Input::default(),
+            description: Input::default(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn edit(task: &Task) -> TaskForm {
+        TaskForm {
+            task: Some(task.clone()),
+            focus: Field::Title,
+            title: Input::new(&task.title),
+            priority: task.priority.clone(),
+            due: Input::new(&task.due_date.map(|d| d.format(DUE_FORMAT).to_string()).unwrap_or_default()),
+            tags: Input::new(&task.tags.join(", ")),
+            description: Input::new(task.description.as_deref().unwrap_or("")),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormAction {
+        match key.code {
+            KeyCode::Esc => return FormAction::Cancel,
+            // Ctrl+J is the only way to type a newline; Enter saves.
+            KeyCode::Char('j') if key.modifiers.contains(KeyModifiers::CONTROL) && self.focus == Field::Description => {
+                self.description.insert('\n');
+            }
+            KeyCode::Enter => return FormAction::Submit,
+            KeyCode::Tab | KeyCode::Down => self.focus = FIELDS[(self.focus_index() + 1) % FIELDS.len()],
+            KeyCode::BackTab | KeyCode::Up => self.focus = FIELDS[(self.focus_index() + FIELDS.len() - 1) % FIELDS.len()],
+            _ if self.focus == Field::Priority => self.handle_priority_key(key.code),
+            _ => {
+                let focus = self.focus;
+                let input = match focus {
+                    Field::Title => &mut self.title,
+                    Field::Due => &mut self.due,
+                    Field::Tags => &mut self.tags,
+                    _ => &mut self.description,
+                };
+                if input.handle_key(key) {
+                    self.errors.retain(|(field, _)| *field != focus);
+                }
+            }
+        }
+        FormAction::None
+    }
+
+    fn handle_priority_key(&mut self, code: KeyCode) {
+        let current = PRIORITIES.iter().position(|p| *p == self.priority).unwrap_or(1);
+        self.priority = match code {
+            KeyCode::Left => PRIORITIES[current.saturating_sub(1)].clone(),
+            KeyCode::Right | KeyCode::Char(' ') => PRIORITIES[(current + 1).min(PRIORITIES.len() - 1)].clone(),
+            KeyCode::Char(c) => match PRIORITIES.iter().find(|p| p.to_string().to_lowercase().starts_with(c.to_ascii_lowercase())) {
+                Some(p) => p.clone(),
+                None => return,
+            },
+            _ => return,
+        };
+    }
+
+    fn focus_index(&self) -> usize {
+        FIELDS.iter().position(|f| *f == self.focus).unwrap_or(0)
+    }
+
+    /// Checks the fields and turns them into a task or an update. On
+    /// failure the errors are shown next to their fields and the first bad
+    /// field gets the focus.
+    pub fn submit(&mut self, now: DateTime<Local>) -> Option<FormOutput> {
+        self.errors.clear();
+        let title = self.title.text.trim().to_string();
+        if title.is_empty() {
+            self.errors.push((Field::Title, "A title is required".to_string()));
+        }
+        let due = match self.due_preview(now) {
+            Ok(due) => due,
+            Err(err) => {
+                self.errors.push((Field::Due, err));
+                None
+            }
+        };
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.text.split(',').filter(|t| !t.trim().is_empty()) {
+            match normalize_tag(tag) {
+                // `#tag` filters couldn't match it.
+                Some(tag) if tag.contains(char::is_whitespace) => {
+                    self.errors.push((Field::Tags, format!("\"{}\": tags can't contain spaces", tag)));
+                }
+                Some(tag) if !tags.contains(&tag) => tags.push(tag),
+                _ => {}
+            }
+        }
+        if let Some((field, _)) = self.errors.first() {
+            self.focus = *field;
+            return None;
+        }
+
+        let description = Some(self.description.text.trim_end().to_string()).filter(|d| !d.is_empty());
+        let Some(task) = &self.task else {
+            return Some(FormOutput::Add(Task {
+                id: None,
+                title,
+                description,
+                status: Status::Todo,
+                priority: self.priority.clone(),
+                due_date: due,
+                project_path: None,
+                created_at: now,
+                tags,
+                blocked_by: Vec::new(),
+                parent_id: None,
+                subtasks_total: 0,
+                subtasks_done: 0,
+                recurrence: None,
+                recur_from_completion: false,
+                branch: None,
+                scope_branch: None,
+                completed_at: None,
+            }));
+        };
+
+        // An untouched due date keeps its seconds instead of being re-parsed.
+        let due_date = if self.due.text == task.due_date.map(|d| d.format(DUE_FORMAT).to_string()).unwrap_or_default() {
+            None
+        } else {
+            Some(due).filter(|d| *d != task.due_date)
+        };
+        let update = TaskUpdate {
+            title: Some(title).filter(|t| *t != task.title),
+            description: Some(description).filter(|d| *d != task.description),
+            priority: Some(self.priority.clone()).filter(|p| *p != task.priority),
+            due_date,
+            tags: Some(tags).filter(|t| *t != task.tags),
+            ..TaskUpdate::default()
+        };
+        Some(FormOutput::Update(task.id.unwrap_or(0), update))
+    }
+
+    // The due date as typed, or why it can't be read.
+    fn due_preview(&self, now: DateTime<Local>) -> Result<Option<DateTime<Local>>, String> {
+        let text = self.due.text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        dates::parse_due(text, now).map(Some).map_err(|e| e.to_string())
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(4).clamp(40, 90);
+        let height = 16.min(area.height);
+        let popup = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width: width.min(area.width),
+            height,
+        };
+        let title = match &self.task {
+            Some(task) => format!("Edit task #{}", task.id.unwrap_or(0)),
+            None => "Add task".to_string(),
+        };
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let inner = block.inner(popup);
+        f.render_widget(Clear, popup);
+        f.render_widget(block, popup);
+
+        let value_width = inner.width.saturating_sub(LABEL_WIDTH + 1) as usize;
+        let dim = Style::default().fg(Color::DarkGray);
+        let red = Style::default().fg(Color::Red);
+        let error = |field: Field| self.errors.iter().find(|(f, _)| *f == field).map(|(_, e)| e.clone());
+        let note = |field: Field, hint: Line<'static>| match error(field) {
+            Some(err) => Line::from(Span::styled(format!("{:w$}{}", "", err, w = LABEL_WIDTH as usize), red)),
+            None => hint,
+        };
+
+        let mut lines = Vec::new();
+        let mut cursor = None;
+        let (text, x) = self.title.view(value_width);
+        lines.push(self.row(Field::Title, "Title", text));
+        cursor = cursor.or((self.focus == Field::Title).then_some((x, 0)));
+        lines.push(note(Field::Title, Line::default()));
+
+        let priority = PRIORITIES
+            .iter()
+            .map(|p| if *p == self.priority { format!("[{}]", p) } else { format!(" {} ", p) })
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(self.row(Field::Priority, "Priority", priority));
+        lines.push(Line::default());
+
+        let (text, x) = self.due.view(value_width);
+        lines.push(self.row(Field::Due, "Due", text));
+        cursor = cursor.or((self.focus == Field::Due).then_some((x, 4)));
+        let preview = match self.due_preview(Local::now()) {
+            Ok(Some(due)) => Line::from(Span::styled(format!("{:w$}→ {}", "", due.format("%a %Y-%m-%d %H:%M"), w = LABEL_WIDTH as usize), dim)),
+            Ok(None) => Line::from(Span::styled(format!("{:w$}e.g. tomorrow, next friday 9am, in 3 days", "", w = LABEL_WIDTH as usize), dim)),
+            Err(err) => Line::from(Span::styled(format!("{:w$}{}", "", err, w = LABEL_WIDTH as usize), red)),
+        };
+        lines.push(note(Field::Due, preview));
+
+        let (text, x) = self.tags.view(value_width);
+        lines.push(self.row(Field::Tags, "Tags", text));
+        cursor = cursor.or((self.focus == Field::Tags).then_some((x, 6)));
+        lines.push(note(Field::Tags, Line::from(Span::styled(format!("{:w$}comma separated", "", w = LABEL_WIDTH as usize), dim))));
+
+        let description_rows = inner.height.saturating_sub(9).max(1) as usize;
+        let (rows, (x, y)) = self.description.view_lines(value_width, description_rows);
+        for (i, row) in rows.into_iter().enumerate() {
+            if i == 0 {
+                lines.push(self.row(Field::Description, "Description", row));
+            } else {
+                lines.push(Line::from(format!("{:w$}{}", "", row, w = LABEL_WIDTH as usize)));
+            }
+        }
+        cursor = cursor.or((self.focus == Field::Description).then_some((x, 8 + y)));
+        while lines.len() < 8 + description_rows {
+            lines.push(Line::default());
+        }
+        lines.push(Line::from(Span::styled(
+            "Tab/↑↓: field | ←/→: priority | Ctrl+J: new line | Enter: save | Esc: cancel",
+            dim,
+        )));
+
+        f.render_widget(Paragraph::new(lines), inner);
+        if let Some((x, y)) = cursor {
+            f.set_cursor(inner.x + LABEL_WIDTH + x as u16, inner.y + y as u16);
+        }
+    }
+
+    fn row(&self, field: Field, label: &str, value: String) -> Line<'static> {
+        let label_style = if self.focus == field {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        Line::from(vec![
+            Span::styled(format!("{:<w$}", format!("{}:", label), w = LABEL_WIDTH as usize), label_style),
+            Span::raw(value),
+        ])
+    }
+}
+
+/// A single text field with a cursor, counted in characters.
+#[derive(Default)]
+pub(super) struct Input {
+    pub(super) text: String,
+    cursor: usize,
+}
+
+impl Input {
+    pub(super) fn new(text: &str) -> Input {
+        Input { text: text.to_string(), cursor: text.chars().count() }
+    }
+
+    fn insert(&mut self, c: char) {
+        let at = self.byte_index(self.cursor);
+        self.text.insert(at, c);
+        self.cursor += 1;
+    }
+
+    /// Applies an editing key. Returns whether the text changed.
+    pub(super) fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let len = self.text.chars().count();
+        match key.code {
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.text.clear();
+                self.cursor = 0;
+                return true;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.insert(c);
+                return true;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.byte_index(self.cursor);
+                self.text.remove(at);
+                return true;
+            }
+            KeyCode::Delete if self.cursor < len => {
+                let at = self.byte_index(self.cursor);
+                self.text.remove(at);
+                return true;
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            _ => {}
+        }
+        false
+    }
+
+    fn byte_index(&self, chars: usize) -> usize {
+        self.text.char_indices().nth(chars).map_or(self.text.len(), |(i, _)| i)
+    }
+
+    /// The part of a one-line field that fits in `width`, scrolled to keep
+    /// the cursor visible, and the cursor's column within it.
+    pub(super) fn view(&self, width: usize) -> (String, usize) {
+        let width = width.max(1);
+        let skip = (self.cursor + 1).saturating_sub(width);
+        (self.text.chars().skip(skip).take(width).collect(), self.cursor - skip)
+    }
+
+    /// Like `view`, for multi-line text: the visible lines and the cursor's
+    /// column and row among them.
+    fn view_lines(&self, width: usize, rows: usize) -> (Vec<String>, (usize, usize)) {
+        let before: String = self.text.chars().take(self.cursor).collect();
+        let row = before.matches('\n').count();
+        let col = before.chars().rev().take_while(|c| *c != '\n').count();
+        let first = (row + 1).saturating_sub(rows.max(1));
+        let skip = (col + 1).saturating_sub(width.max(1));
+        let lines = self
+            .text
+            .split('\n')
+            .skip(first)
+            .take(rows.max(1))
+            .map(|line| line.chars().skip(skip).take(width).collect())
+            .collect();
+        (lines, (col - skip, row - first))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap()
+    }
+
+    fn press(form: &mut TaskForm, code: KeyCode) -> FormAction {
+        form.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(form: &mut TaskForm, text: &str) {
+        for c in text.chars() {
+            press(form, KeyCode::Char(c));
+        }
+    }
+
+    fn existing() -> Task {
+        let mut form = TaskForm::add();
+        type_text(&mut form, "Write docs");
+        let Some(FormOutput::Add(task)) = form.submit(now()) else { panic!("form was valid") };
+        Task { id: Some(7), due_date: Some(now() + Duration::seconds(90)), tags: vec!["docs".to_string()], ..task }
+    }
+
+    #[test]
+    fn filled_in_form_adds_a_task() {
+        let mut form = TaskForm::add();
+        type_text(&mut form, "Write docs");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "h");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "tomorrow");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "Docs, #docs, release");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "First line");
+        form.handle_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::CONTROL));
+        type_text(&mut form, "Second line");
+        assert!(matches!(press(&mut form, KeyCode::Enter), FormAction::Submit));
+
+        let Some(FormOutput::Add(task)) = form.submit(now()) else { panic!("form was valid") };
+        assert_eq!(task.title, "Write docs");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.due_date.map(|d| d.date_naive()), Some((now() + Duration::days(1)).date_naive()));
+        assert_eq!(task.tags, ["docs", "release"]);
+        assert_eq!(task.description.as_deref(), Some("First line\nSecond line"));
+    }
+
+    #[test]
+    fn invalid_fields_keep_the_form_open() {
+        let mut form = TaskForm::add();
+        press(&mut form, KeyCode::Tab);
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "someday maybe");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "two words");
+        assert!(form.submit(now()).is_none());
+        let fields: Vec<Field> = form.errors.iter().map(|(field, _)| *field).collect();
+        assert_eq!(fields, [Field::Title, Field::Due, Field::Tags]);
+        assert_eq!(form.focus, Field::Title);
+
+        // Editing a field clears its error.
+        type_text(&mut form, "Title");
+        assert_eq!(form.errors.len(), 2);
+        assert!(matches!(press(&mut form, KeyCode::Esc), FormAction::Cancel));
+    }
+
+    #[test]
+    fn editing_only_reports_what_changed() {
+        let task = existing();
+        let mut form = TaskForm::edit(&task);
+        let Some(FormOutput::Update(id, update)) = form.submit(now()) else { panic!("form was valid") };
+        assert_eq!(id, 7);
+        assert!(update.is_empty(), "{:?}", update);
+
+        press(&mut form, KeyCode::Tab);
+        press(&mut form, KeyCode::Left);
+        press(&mut form, KeyCode::Tab);
+        press(&mut form, KeyCode::Tab);
+        form.handle_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        let Some(FormOutput::Update(_, update)) = form.submit(now()) else { panic!("form was valid") };
+        assert_eq!(update.priority, Some(Priority::Low));
+        assert_eq!(update.tags, Some(Vec::new()));
+        assert!(update.title.is_none() && update.due_date.is_none() && update.description.is_none());
+    }
+
+    #[test]
+    fn input_edits_at_the_cursor() {
+        let mut input = Input::new("héllo");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Backspace));
+        input.handle_key(key(KeyCode::Char('L')));
+        input.handle_key(key(KeyCode::Home));
+        input.handle_key(key(KeyCode::Delete));
+        assert_eq!(input.text, "élLo");
+        assert!(!input.handle_key(key(KeyCode::Backspace)));
+        assert_eq!(input.view(2), ("él".to_string(), 0));
+        input.handle_key(key(KeyCode::End));
+        assert_eq!(input.view(2), ("o".to_string(), 1));
+    }
+}
@@ -1,32 +1,182 @@
+mod board;
+mod form;
+mod notes;
+mod search;
+
 use anyhow::Result;
+use chrono::Local;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs},
     Frame, Terminal,
 };
-use std::{io, time::Duration};
+use std::{collections::{HashMap, HashSet}, io, time::Duration};
+use crate::config::Config;
 use crate::db::TaskRepository;
-use crate::models::{Task, Status, Priority};
-use crate::context::ContextManager;
+use crate::editor::{self, Document};
+use crate::filter::Filter;
+use crate::models::{Note, Task, Status, Priority};
+use crate::context::{Context, ContextManager, Target};
+use crate::git;
+use crate::tree::{build_tree, TreeRow};
+use board::Board;
+use form::{FormAction, FormOutput, TaskForm};
+use notes::NotesView;
+use search::{Command, Prompt, PromptAction, PromptKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Tasks,
+    Notes,
+}
+
+const TABS: [Tab; 2] = [Tab::Tasks, Tab::Notes];
 
 struct App {
+    tab: Tab,
     tasks: Vec<Task>,
     state: ListState,
+    /// The `:filter` expression in effect, as typed and parsed.
+    filter: Option<(String, Filter)>,
+    /// The `/` search. Only tasks whose titles match it are shown.
+    search: String,
+    /// The tasks in the list: the matches and, for context, their
+    /// ancestors. Everything when there's no search.
+    shown: Vec<Task>,
+    /// The tasks on the board: just the matches.
+    cards: Vec<Task>,
+    context: Context,
+    context_path: String,
+    /// Narrows the list to the current branch with per-branch scoping.
+    scope: Option<Filter>,
+    /// Parent tasks whose subtasks are hidden.
+    collapsed: HashSet<i64>,
+    /// The checked-out git branch and the task it belongs to, if any.
+    branch: Option<(String, i64)>,
+    /// The Kanban board, shown instead of the task list while toggled on.
+    board: Option<Board>,
+    /// WIP limits for the board's Todo, In Progress and Done columns.
+    wip_limits: [Option<usize>; 3],
+    /// The add/edit form, when open. It takes all key presses.
+    form: Option<TaskForm>,
+    /// The search or command prompt, when open. It also takes all key
+    /// presses.
+    prompt: Option<Prompt>,
+    notes: NotesView,
+    /// A note waiting for `y` to confirm that it should be deleted.
+    confirm_delete: Option<i64>,
+    /// Shown in place of the key help until the next key press.
+    message: Option<String>,
 }
 
 impl App {
+    /// Subtasks of collapsed tasks stay visible while searching, so no
+    /// match is hidden.
+    fn rows(&self) -> Vec<TreeRow<'_>> {
+        if self.search.is_empty() {
+            build_tree(&self.shown, &self.collapsed)
+        } else {
+            build_tree(&self.shown, &HashSet::new())
+        }
+    }
+
+    fn is_match(&self, task: &Task) -> bool {
+        search::fuzzy_match(&self.search, &task.title).is_some()
+    }
+
+    fn selected_id(&self) -> Option<i64> {
+        if let Some(board) = &self.board {
+            return board.selected_id(&self.cards);
+        }
+        let i = self.state.selected()?;
+        self.rows().get(i).and_then(|row| row.task.id)
+    }
+
+    fn reload(&mut self, repo: &TaskRepository) -> Result<()> {
+        let selected = self.selected_id();
+        let filter = Filter::all(self.filter.clone().map(|(_, f)| f).into_iter().chain(self.scope.clone()));
+        self.tasks = repo.get_tasks(Some(&self.context_path), filter.as_ref(), &[])?;
+        self.narrow();
+        self.select_id(selected);
+        Ok(())
+    }
+
+    /// Works out `shown` and `cards` from the tasks and the search.
+    fn narrow(&mut self) {
+        let matches: HashSet<i64> = self.tasks.iter().filter(|t| self.is_match(t)).filter_map(|t| t.id).collect();
+        let parents: HashMap<i64, i64> =
+            self.tasks.iter().filter_map(|t| Some((t.id?, t.parent_id?))).collect();
+        let mut shown = matches.clone();
+        for id in &matches {
+            let mut id = *id;
+            while let Some(parent) = parents.get(&id) {
+                if !shown.insert(*parent) {
+                    break;
+                }
+                id = *parent;
+            }
+        }
+        self.cards = self.tasks.iter().filter(|t| t.id.is_some_and(|id| matches.contains(&id))).cloned().collect();
+        self.shown = self.tasks.iter().filter(|t| t.id.is_some_and(|id| shown.contains(&id))).cloned().collect();
+    }
+
+    /// Changes the search, keeping the cursor on a match if there is one.
+    fn set_search(&mut self, search: &str) {
+        let selected = self.selected_id();
+        self.search = search.to_string();
+        self.narrow();
+        self.select_id(selected);
+        let on_match = self.selected_id().and_then(|id| self.tasks.iter().find(|t| t.id == Some(id))).is_some_and(|t| self.is_match(t));
+        if !on_match && self.board.is_none() {
+            self.state.select(None);
+            self.jump_match(true);
+        }
+    }
+
+    /// Moves the cursor to the next (or previous) task matching the search,
+    /// wrapping around.
+    fn jump_match(&mut self, forward: bool) {
+        let rows = self.rows();
+        let len = rows.len();
+        let start = self.state.selected().unwrap_or(if forward { len.saturating_sub(1) } else { 0 });
+        let found = (1..=len)
+            .map(|step| if forward { (start + step) % len } else { (start + len - step % len) % len })
+            .find(|i| self.is_match(rows[*i].task));
+        drop(rows);
+        if found.is_some() {
+            self.state.select(found);
+        }
+    }
+
+    /// Selects the row showing `id`, or keeps the cursor in range if it is
+    /// no longer visible.
+    fn select_id(&mut self, id: Option<i64>) {
+        if let Some(board) = self.board.as_mut() {
+            board.select_id(&self.cards, id);
+        }
+        let rows = self.rows();
+        let index = id
+            .and_then(|id| rows.iter().position(|row| row.task.id == Some(id)))
+            .or_else(|| self.state.selected().map(|i| i.min(rows.len().saturating_sub(1))));
+        self.state.select(if rows.is_empty() { None } else { index.or(Some(0)) });
+    }
+
     fn next(&mut self) {
+        let len = self.rows().len();
+        if len == 0 {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.tasks.len() - 1 {
+                if i >= len - 1 {
                     0
                 } else {
                     i + 1
@@ -38,10 +188,14 @@ impl App {
     }
 
     fn previous(&mut self) {
+        let len = self.rows().len();
+        if len == 0 {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.tasks.len() - 1
+                    len - 1
                 } else {
                     i - 1
                 }
@@ -50,9 +204,37 @@ impl App {
         };
         self.state.select(Some(i));
     }
+
+    fn expand(&mut self) {
+        if let Some(id) = self.selected_id() {
+            self.collapsed.remove(&id);
+        }
+    }
+
+    /// Collapses the selected task, or jumps to its parent if there is
+    /// nothing to collapse.
+    fn collapse(&mut self) {
+        let Some(i) = self.state.selected() else { return };
+        let rows = self.rows();
+        let Some(row) = rows.get(i) else { return };
+        let (id, parent, can_collapse) = (row.task.id, row.task.parent_id, row.has_children && !row.collapsed);
+        drop(rows);
+        match id {
+            Some(id) if can_collapse => {
+                self.collapsed.insert(id);
+            }
+            _ => {
+                if parent.is_some() {
+                    self.select_id(parent);
+                }
+            }
+        }
+    }
 }
 
-pub fn run(repo: &TaskRepository) -> Result<()> {
+/// `filter` is the expression the TUI starts out filtered by, as typed and
+/// parsed.
+pub fn run(repo: &TaskRepository, config: &Config, target: &Target, filter: Option<(String, Filter)>) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -62,15 +244,38 @@ pub fn run(repo: &TaskRepository) -> Result<()> {
 
     // Create app state
     // Filter by current context by default
-    let context_path = ContextManager::get_context()?;
-    let tasks = repo.get_tasks(Some(context_path.to_str().unwrap()))?;
+    let context = ContextManager::select(repo, config, target)?;
+    let context_path = context.scope();
+    let branch = match git::current_branch(&context.root) {
+        Some(name) => repo
+            .task_for_branch(&name, Some(&context_path))?
+            .and_then(|t| t.id)
+            .map(|id| (name, id)),
+        None => None,
+    };
     let mut app = App {
-        tasks,
+        tab: Tab::Tasks,
+        tasks: Vec::new(),
         state: ListState::default(),
+        filter,
+        search: String::new(),
+        shown: Vec::new(),
+        cards: Vec::new(),
+        scope: context.branch_filter(),
+        context,
+        context_path,
+        collapsed: HashSet::new(),
+        branch,
+        board: None,
+        wip_limits: [config.wip_todo, config.wip_in_progress, config.wip_done],
+        form: None,
+        prompt: None,
+        notes: NotesView::new(),
+        confirm_delete: None,
+        message: None,
     };
-    if !app.tasks.is_empty() {
-        app.state.select(Some(0));
-    }
+    app.reload(repo)?;
+    app.notes.reload(repo, &app.context_path)?;
 
     let res = run_app(&mut terminal, &mut app, repo);
 
@@ -96,46 +301,342 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, repo: &TaskRep
 
         if event::poll(Duration::from_millis(250))? {
             if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(form) = app.form.as_mut() {
+                    match form.handle_key(key) {
+                        FormAction::None => {}
+                        FormAction::Cancel => app.form = None,
+                        FormAction::Submit => save_form(app, repo)?,
+                    }
+                    continue;
+                }
+                if app.prompt.is_some() {
+                    handle_prompt_key(app, repo, key)?;
+                    continue;
+                }
+                app.message = None;
+                if let Some(id) = app.confirm_delete.take() {
+                    if key.code == KeyCode::Char('y') {
+                        repo.delete_note(id)?;
+                        app.notes.reload(repo, &app.context_path)?;
+                        app.message = Some(format!("Note {} deleted.", id));
+                    }
+                    continue;
+                }
                 match key.code {
                     KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Char('j') | KeyCode::Down => app.next(),
-                    KeyCode::Char('k') | KeyCode::Up => app.previous(),
-                    KeyCode::Enter | KeyCode::Char(' ') => {
-                        if let Some(i) = app.state.selected() {
-                            if let Some(task) = app.tasks.get(i) {
-                                // Toggle status (Todo -> Done -> Todo)
-                                // For now just mark done
-                                if task.status != Status::Done {
-                                    repo.complete_task(task.id.unwrap())?;
-                                } else {
-                                    // Re-open? We didn't implement re-open in DB yet.
-                                    // Just ignore for now.
-                                }
-                                // Reload tasks
-                                let context_path = ContextManager::get_context()?;
-                                app.tasks = repo.get_tasks(Some(context_path.to_str().unwrap()))?;
-                            }
-                        }
+                    KeyCode::Tab => app.tab = TABS[(app.tab as usize + 1) % TABS.len()],
+                    KeyCode::Char('1') => app.tab = Tab::Tasks,
+                    KeyCode::Char('2') => app.tab = Tab::Notes,
+                    KeyCode::Char('/') if app.tab == Tab::Tasks => {
+                        app.prompt = Some(Prompt::new(PromptKind::Search { previous: app.search.clone() }, ""));
+                    }
+                    // Opens on the filter in effect, ready to be changed.
+                    KeyCode::Char(':') if app.tab == Tab::Tasks => {
+                        let text = match &app.filter {
+                            Some((query, _)) => format!("filter {}", query),
+                            None => "filter ".to_string(),
+                        };
+                        app.prompt = Some(Prompt::new(PromptKind::Command { previous: app.filter.clone() }, &text));
+                    }
+                    _ => match app.tab {
+                        Tab::Tasks if app.board.is_some() => handle_board_key(app, repo, key)?,
+                        Tab::Tasks => handle_task_key(app, repo, key.code)?,
+                        Tab::Notes => handle_note_key(terminal, app, repo, key.code)?,
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn handle_task_key(app: &mut App, repo: &TaskRepository, code: KeyCode) -> Result<()> {
+    match code {
+        KeyCode::Char('j') | KeyCode::Down => app.next(),
+        KeyCode::Char('k') | KeyCode::Up => app.previous(),
+        KeyCode::Char('l') | KeyCode::Right => app.expand(),
+        KeyCode::Char('h') | KeyCode::Left => app.collapse(),
+        KeyCode::Char('n') | KeyCode::Char('N') if !app.search.is_empty() => {
+            app.jump_match(code == KeyCode::Char('n'))
+        }
+        KeyCode::Esc if !app.search.is_empty() => app.set_search(""),
+        KeyCode::Char('b') => {
+            let selected = app.selected_id();
+            app.board = Some(Board::new());
+            app.select_id(selected);
+        }
+        KeyCode::Char('a') => app.form = Some(TaskForm::add()),
+        KeyCode::Char('e') => {
+            if let Some(task) = app.selected_id().and_then(|id| app.tasks.iter().find(|t| t.id == Some(id))) {
+                app.form = Some(TaskForm::edit(task));
+            }
+        }
+        // Cycle Todo -> In Progress -> Done -> Todo
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            if let Some(task) = app.selected_id().and_then(|id| app.tasks.iter().find(|t| t.id == Some(id))) {
+                let next = match task.status {
+                    Status::Todo => Status::InProgress,
+                    Status::InProgress => Status::Done,
+                    Status::Done => Status::Todo,
+                };
+                repo.set_status(task.id.unwrap_or(0), next)?;
+                app.reload(repo)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_board_key(app: &mut App, repo: &TaskRepository, key: KeyEvent) -> Result<()> {
+    let Some(board) = app.board.as_mut() else { return Ok(()) };
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let step = match key.code {
+        KeyCode::Char('h') => -1,
+        KeyCode::Char('l') => 1,
+        KeyCode::Left if shift => -1,
+        KeyCode::Right if shift => 1,
+        KeyCode::Left => {
+            board.focus(-1);
+            return Ok(());
+        }
+        KeyCode::Right => {
+            board.focus(1);
+            return Ok(());
+        }
+        // Only matches are on the board, so n/N step through its cards.
+        KeyCode::Char('j') | KeyCode::Char('n') | KeyCode::Down => {
+            board.next(&app.cards);
+            return Ok(());
+        }
+        KeyCode::Char('k') | KeyCode::Char('N') | KeyCode::Up => {
+            board.previous(&app.cards);
+            return Ok(());
+        }
+        KeyCode::Char('b') => {
+            let selected = app.selected_id();
+            app.board = None;
+            app.select_id(selected);
+            return Ok(());
+        }
+        // Adding, editing and cycling work as in the list.
+        _ => return handle_task_key(app, repo, key.code),
+    };
+
+    // Move the selected card and follow it.
+    let Some((id, status)) = board.move_target(&app.cards, step) else { return Ok(()) };
+    repo.set_status(id, status)?;
+    app.reload(repo)?;
+    app.select_id(Some(id));
+    let column = board::COLUMNS.iter().position(|s| app.tasks.iter().any(|t| t.id == Some(id) && t.status == *s));
+    if let Some(column) = column {
+        let count = app.tasks.iter().filter(|t| t.status == board::COLUMNS[column]).count();
+        if let Some(limit) = app.wip_limits[column].filter(|limit| count > *limit) {
+            app.message = Some(format!("{} is over its WIP limit ({}/{})", board::column_name(column), count, limit));
+        }
+    }
+    Ok(())
+}
+
+fn handle_prompt_key(app: &mut App, repo: &TaskRepository, key: KeyEvent) -> Result<()> {
+    let Some(prompt) = app.prompt.as_mut() else { return Ok(()) };
+    let action = prompt.handle_key(key);
+    let text = prompt.text().to_string();
+    match (&prompt.kind, action) {
+        (_, PromptAction::None) => {}
+        (PromptKind::Search { .. }, PromptAction::Changed) => app.set_search(&text),
+        (PromptKind::Search { .. }, PromptAction::Submit) => {
+            app.prompt = None;
+            if !app.search.is_empty() && app.cards.is_empty() {
+                app.message = Some(format!("No tasks match /{}", app.search));
+            }
+        }
+        (PromptKind::Search { previous }, PromptAction::Cancel) => {
+            let previous = previous.clone();
+            app.prompt = None;
+            app.set_search(&previous);
+        }
+        // The filter applies as soon as it parses; until then the list
+        // stays as it was and the prompt says what's wrong.
+        (PromptKind::Command { .. }, PromptAction::Changed | PromptAction::Submit) => {
+            match search::parse_command(&text, Local::now()) {
+                Ok(Command::Filter(filter)) => {
+                    prompt.error = None;
+                    if action == PromptAction::Submit {
+                        app.prompt = None;
                     }
-                    _ => {}
+                    app.filter = filter;
+                    app.reload(repo)?;
                 }
+                Err(err) => prompt.error = Some(format!("{:#}", err)),
+            }
+        }
+        (PromptKind::Command { previous }, PromptAction::Cancel) => {
+            app.filter = previous.clone();
+            app.prompt = None;
+            app.reload(repo)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_note_key<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, repo: &TaskRepository, code: KeyCode) -> Result<()> {
+    match code {
+        KeyCode::Char('j') | KeyCode::Down => app.notes.next(),
+        KeyCode::Char('k') | KeyCode::Up => app.notes.previous(),
+        KeyCode::PageDown | KeyCode::Char('J') => app.notes.scroll_preview(true),
+        KeyCode::PageUp | KeyCode::Char('K') => app.notes.scroll_preview(false),
+        KeyCode::Char('t') => {
+            app.notes.cycle_tag();
+            app.notes.reload(repo, &app.context_path)?;
+        }
+        // A note written under a tag filter starts out with that tag.
+        KeyCode::Char('a') => {
+            let doc = Document { tags: app.notes.tag().map(str::to_string).into_iter().collect(), ..Document::default() };
+            let Some(doc) = edit_in_editor(terminal, app, &doc)? else { return Ok(()) };
+            let note = Note {
+                id: None,
+                title: doc.title,
+                content: Some(doc.body).filter(|b| !b.is_empty()),
+                project_path: app.context.project_path(repo)?,
+                created_at: Local::now(),
+                tags: doc.tags,
+            };
+            let id = repo.add_note(&note)?;
+            app.notes.reload(repo, &app.context_path)?;
+            app.notes.select_id(Some(id));
+            app.message = Some(format!("Note {} added.", id));
+        }
+        KeyCode::Char('e') | KeyCode::Enter => {
+            let Some(note) = app.notes.selected() else { return Ok(()) };
+            let id = note.id.unwrap_or(0);
+            let doc = Document {
+                title: note.title.clone(),
+                tags: note.tags.clone(),
+                body: note.content.clone().unwrap_or_default(),
+            };
+            let Some(doc) = edit_in_editor(terminal, app, &doc)? else { return Ok(()) };
+            let body = Some(doc.body).filter(|b| !b.is_empty());
+            repo.update_note(id, Some(doc.title), Some(body), Some(doc.tags))?;
+            app.notes.reload(repo, &app.context_path)?;
+            app.notes.select_id(Some(id));
+            app.message = Some(format!("Note {} updated.", id));
+        }
+        KeyCode::Char('d') | KeyCode::Delete => {
+            if let Some(note) = app.notes.selected() {
+                app.message = Some(format!("Delete note {} \"{}\"? (y/n)", note.id.unwrap_or(0), note.title));
+                app.confirm_delete = note.id;
             }
         }
+        _ => {}
     }
+    Ok(())
+}
+
+/// Hands the terminal to `$EDITOR` and takes it back afterwards. Problems
+/// with the editor or the text are reported in the message line.
+fn edit_in_editor<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, doc: &Document) -> Result<Option<Document>> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    let edited = editor::edit(doc);
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    match edited {
+        Ok(Some(doc)) => Ok(Some(doc)),
+        Ok(None) => {
+            app.message = Some("No changes.".to_string());
+            Ok(None)
+        }
+        Err(err) => {
+            app.message = Some(format!("{:#}", err));
+            Ok(None)
+        }
+    }
+}
+
+/// Stores what the form holds, or leaves it open showing what's wrong.
+fn save_form(app: &mut App, repo: &TaskRepository) -> Result<()> {
+    let Some(form) = app.form.as_mut() else { return Ok(()) };
+    let id = match form.submit(Local::now()) {
+        None => return Ok(()),
+        Some(FormOutput::Add(mut task)) => {
+            task.project_path = app.context.project_path(repo)?;
+            task.scope_branch = app.context.scope_branch();
+            repo.add_task(&task)?
+        }
+        Some(FormOutput::Update(id, update)) => {
+            if !update.is_empty() {
+                repo.update_task(id, &update)?;
+            }
+            id
+        }
+    };
+    app.form = None;
+    app.reload(repo)?;
+    app.select_id(Some(id));
+    Ok(())
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+        .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(3)].as_ref())
         .split(f.size());
 
+    let tabs = Tabs::new(vec!["Tasks", "Notes"])
+        .select(app.tab as usize)
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, chunks[0]);
+
+    match app.tab {
+        Tab::Tasks => match app.board.as_mut() {
+            Some(board) => board.draw(f, chunks[1], &app.cards, &app.wip_limits),
+            None => draw_tasks(f, app, chunks[1]),
+        },
+        Tab::Notes => app.notes.draw(f, chunks[1]),
+    }
+
+    let help = match (&app.message, app.tab) {
+        _ if app.prompt.is_some() => Paragraph::new(""),
+        (Some(message), _) => Paragraph::new(message.clone()).style(Style::default().fg(Color::Yellow)),
+        (None, Tab::Tasks) if !app.search.is_empty() => Paragraph::new(
+            "n/N: next/previous match | esc: clear search | /: search | :: filter | space: cycle | e: edit | q: quit",
+        ),
+        (None, Tab::Tasks) if app.board.is_some() => Paragraph::new(
+            "←/→: column | j/k: card | h/l: move card | space: cycle status | a: add | e: edit | b: list | q: quit",
+        ),
+        (None, Tab::Tasks) => Paragraph::new(
+            "j/k: navigate | h/l: fold | space: cycle | a: add | e: edit | b: board | /: search | :: filter | q: quit",
+        ),
+        (None, Tab::Notes) => Paragraph::new(
+            "j/k: navigate | J/K: scroll | a: new | e: edit | d: delete | t: filter by tag | tab: tasks | q: quit",
+        ),
+    };
+    f.render_widget(help.block(Block::default().borders(Borders::ALL)), chunks[2]);
+    if let Some(prompt) = &app.prompt {
+        prompt.draw(f, chunks[2]);
+    }
+
+    if let Some(form) = &app.form {
+        form.draw(f, f.size());
+    }
+}
+
+fn draw_tasks(f: &mut Frame, app: &mut App, area: Rect) {
     let tasks: Vec<ListItem> = app
-        .tasks
+        .rows()
         .iter()
-        .map(|t| {
+        .map(|row| {
+            let t = row.task;
             let (icon, style) = if t.status == Status::Done {
                 ("✔", Style::default().fg(Color::Green))
+            } else if t.is_blocked() {
+                ("⛔", Style::default().fg(Color::Red))
+            } else if t.status == Status::InProgress {
+                ("▶", Style::default().fg(Color::Yellow))
             } else {
                 ("☐", Style::default())
             };
@@ -146,23 +647,59 @@ fn ui(f: &mut Frame, app: &mut App) {
                 Priority::Low => Color::Blue,
             };
 
-            let content = Line::from(vec![
+            let fold = match (row.has_children, row.collapsed) {
+                (true, true) => "▸ ",
+                (true, false) => "▾ ",
+                (false, _) => "",
+            };
+
+            let on_branch = app.branch.as_ref().is_some_and(|(_, id)| t.id == Some(*id));
+            let mut spans = vec![
+                Span::styled(row.prefix.clone(), Style::default().fg(Color::DarkGray)),
+                Span::raw(fold),
+                Span::styled(if on_branch { "⎇ " } else { "" }, Style::default().fg(Color::Cyan)),
                 Span::styled(format!("{} ", icon), style),
-                Span::styled(format!("{} ", t.title), if t.status == Status::Done { Style::default().add_modifier(Modifier::CROSSED_OUT) } else { Style::default() }),
-                Span::styled(format!("[{}]", t.priority), Style::default().fg(priority_color)),
-            ]);
+            ];
+            // While searching, ancestors shown for context are dimmed.
+            let mut title_style = if t.status == Status::Done { Style::default().add_modifier(Modifier::CROSSED_OUT) } else { Style::default() };
+            if !app.search.is_empty() && !app.is_match(t) {
+                title_style = title_style.fg(Color::DarkGray);
+            }
+            spans.extend(search::highlight(&t.title, &app.search, title_style));
+            spans.push(Span::raw(" "));
+            if t.subtasks_total > 0 {
+                spans.push(Span::styled(
+                    format!("{}/{} ", t.subtasks_done, t.subtasks_total),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            spans.push(Span::styled(format!("[{}]", t.priority), Style::default().fg(priority_color)));
+            for tag in &t.tags {
+                spans.push(Span::styled(format!(" #{}", tag), Style::default().fg(Color::Cyan)));
+            }
+            if t.is_blocked() {
+                let ids = t.blocked_by.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(",");
+                spans.push(Span::styled(format!(" blocked by {}", ids), Style::default().fg(Color::DarkGray)));
+            }
+            let content = Line::from(spans);
             ListItem::new(content)
         })
         .collect();
 
+    let mut title = "Tasks".to_string();
+    if let Some((name, id)) = &app.branch {
+        title.push_str(&format!(" ─ ⎇ {} (#{})", name, id));
+    }
+    if let Some((query, _)) = &app.filter {
+        title.push_str(&format!(" ─ filter: {}", query));
+    }
+    if !app.search.is_empty() {
+        title.push_str(&format!(" ─ /{} ({} matching)", app.search, app.cards.len()));
+    }
     let tasks_list = List::new(tasks)
-        .block(Block::default().borders(Borders::ALL).title("Tasks"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
         .highlight_symbol("> ");
 
-    f.render_stateful_widget(tasks_list, chunks[0], &mut app.state);
-
-    let help = Paragraph::new("j/k: navigate | space/enter: toggle | q: quit")
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(help, chunks[1]);
+    f.render_stateful_widget(tasks_list, area, &mut app.state);
 }
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::db::TaskRepository;
+use crate::markdown::{self, Tone};
+use crate::models::Note;
+
+/// The Notes tab: the context's notes, optionally narrowed to one tag, next
+/// to a rendered preview of the selected note.
+pub struct NotesView {
+    notes: Vec<Note>,
+    state: ListState,
+    /// Only notes with this tag are listed. Cycled with `t`.
+    tag: Option<String>,
+    /// Every tag on the context's notes, in the order `t` cycles through.
+    tags: Vec<String>,
+    /// Lines the preview is scrolled down by.
+    scroll: u16,
+}
+
+impl NotesView {
+    pub fn new() -> NotesView {
+        NotesView {
+            notes: Vec::new(),
+            state: ListState::default(),
+            tag: None,
+            tags: Vec::new(),
+            scroll: 0,
+        }
+    }
+
+    pub fn reload(&mut self, repo: &TaskRepository, scope: &str) -> Result<()> {
+        let selected = self.selected().and_then(|n| n.id);
+        let notes = repo.get_notes(Some(scope))?;
+        self.tags = notes.iter().flat_map(|n| n.tags.iter().map(|t| t.to_lowercase())).collect();
+        self.tags.sort();
+        self.tags.dedup();
+        if self.tag.as_ref().is_some_and(|tag| !self.tags.contains(tag)) {
+            self.tag = None;
+        }
+        self.notes = notes
+            .into_iter()
+            .filter(|n| self.tag.as_ref().is_none_or(|tag| n.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+            .collect();
+        self.select_id(selected);
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<&Note> {
+        self.notes.get(self.state.selected()?)
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Selects the note with `id`, or keeps the cursor in range if it is
+    /// gone.
+    pub fn select_id(&mut self, id: Option<i64>) {
+        let index = id
+            .and_then(|id| self.notes.iter().position(|n| n.id == Some(id)))
+            .or_else(|| self.state.selected().map(|i| i.min(self.notes.len().saturating_sub(1))));
+        if self.state.selected() != index {
+            self.scroll = 0;
+        }
+        self.state.select(if self.notes.is_empty() { None } else { index.or(Some(0)) });
+    }
+
+    pub fn next(&mut self) {
+        if self.notes.is_empty() {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| (i + 1) % self.notes.len());
+        self.state.select(Some(i));
+        self.scroll = 0;
+    }
+
+    pub fn previous(&mut self) {
+        if self.notes.is_empty() {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| (i + self.notes.len() - 1) % self.notes.len());
+        self.state.select(Some(i));
+        self.scroll = 0;
+    }
+
+    /// Moves the tag filter to the next tag, and back to all notes after
+    /// the last one. The caller reloads.
+    pub fn cycle_tag(&mut self) {
+        let next = match &self.tag {
+            None => 0,
+            Some(tag) => self.tags.iter().position(|t| t == tag).map_or(0, |i| i + 1),
+        };
+        self.tag = self.tags.get(next).cloned();
+    }
+
+    pub fn scroll_preview(&mut self, down: bool) {
+        self.scroll = if down { self.scroll.saturating_add(5) } else { self.scroll.saturating_sub(5) };
+    }
+
+    pub fn draw(&mut self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+            .split(area);
+
+        let items: Vec<ListItem> = self
+            .notes
+            .iter()
+            .map(|note| {
+                let mut spans = vec![Span::raw(note.title.clone())];
+                for tag in &note.tags {
+                    spans.push(Span::styled(format!(" #{}", tag), Style::default().fg(Color::Cyan)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let title = match &self.tag {
+            Some(tag) => format!("Notes ─ #{}", tag),
+            None => "Notes".to_string(),
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, chunks[0], &mut self.state);
+
+        let (title, lines) = match self.selected() {
+            Some(note) => {
+                let mut lines = vec![
+                    Line::from(Span::styled(
+                        format!("#{} · {}", note.id.unwrap_or(0), note.created_at.format("%Y-%m-%d %H:%M")),
+                        Style::default().fg(Color::DarkGray),
+                    )),
+                    Line::default(),
+                ];
+                lines.extend(preview_lines(note.content.as_deref().unwrap_or("")));
+                (note.title.clone(), lines)
+            }
+            None => ("Preview".to_string(), vec![Line::from(Span::styled("No notes here yet. Press a to write one.", Style::default().fg(Color::DarkGray)))]),
+        };
+        let preview = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        f.render_widget(preview, chunks[1]);
+    }
+}
+
+/// A note's markdown as ratatui lines, colored like `flow note show`.
+fn preview_lines(content: &str) -> Vec<Line<'static>> {
+    markdown::render(content)
+        .into_iter()
+        .map(|line| Line::from(line.into_iter().map(|span| Span::styled(span.text, style(span.style))).collect::<Vec<_>>()))
+        .collect()
+}
+
+fn style(text: markdown::Style) -> Style {
+    let mut style = Style::default();
+    if let Some(tone) = text.tone {
+        style = style.fg(match tone {
+            Tone::Heading => Color::LightCyan,
+            Tone::Marker | Tone::Number => Color::Cyan,
+            Tone::Code => Color::Yellow,
+            Tone::Link => Color::Blue,
+            Tone::Keyword => Color::Magenta,
+            Tone::Str => Color::Green,
+            Tone::Comment | Tone::Muted => Color::DarkGray,
+        });
+    }
+    for (on, modifier) in [
+        (text.bold, Modifier::BOLD),
+        (text.italic, Modifier::ITALIC),
+        (text.underline, Modifier::UNDERLINED),
+        (text.strike, Modifier::CROSSED_OUT),
+    ] {
+        if on {
+            style = style.add_modifier(modifier);
+        }
+    }
+    style
+}
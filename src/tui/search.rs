@@ -0,0 +1,197 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use super::form::Input;
+use crate::filter::Filter;
+
+/// What the prompt is for, and what `Esc` puts back.
+pub enum PromptKind {
+    /// `/`: narrows the tasks to titles matching the input.
+    Search { previous: String },
+    /// `:`: a command such as `filter status:todo`.
+    Command { previous: Option<(String, Filter)> },
+}
+
+/// What a key press asks of the prompt's owner.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PromptAction {
+    None,
+    Changed,
+    Cancel,
+    Submit,
+}
+
+/// The one-line prompt that takes over the key help while searching or
+/// typing a command. Both apply as you type.
+pub struct Prompt {
+    pub kind: PromptKind,
+    input: Input,
+    /// Why the input can't be applied yet.
+    pub error: Option<String>,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, text: &str) -> Prompt {
+        Prompt { kind, input: Input::new(text), error: None }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.input.text
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptAction {
+        match key.code {
+            KeyCode::Esc => PromptAction::Cancel,
+            KeyCode::Enter => PromptAction::Submit,
+            // Backspace on an empty prompt closes it, as in vim.
+            KeyCode::Backspace if self.input.text.is_empty() => PromptAction::Cancel,
+            _ if self.input.handle_key(key) => PromptAction::Changed,
+            _ => PromptAction::None,
+        }
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect) {
+        let lead = match self.kind {
+            PromptKind::Search { .. } => "/",
+            PromptKind::Command { .. } => ":",
+        };
+        let error = self.error.as_ref().map(|e| format!("  {}", e)).unwrap_or_default();
+        let width = (area.width.saturating_sub(3) as usize).saturating_sub(error.chars().count());
+        let (text, cursor) = self.input.view(width);
+        let line = Line::from(vec![
+            Span::styled(lead, Style::default().fg(Color::Yellow)),
+            Span::raw(text),
+            Span::styled(error, Style::default().fg(Color::Red)),
+        ]);
+        f.render_widget(Paragraph::new(line).block(Block::default().borders(Borders::ALL)), area);
+        f.set_cursor(area.x + 2 + cursor as u16, area.y + 1);
+    }
+}
+
+/// A command typed at the `:` prompt.
+pub enum Command {
+    /// Show only tasks matching the filter; `None` shows them all again.
+    Filter(Option<(String, Filter)>),
+}
+
+pub fn parse_command(input: &str, now: DateTime<Local>) -> Result<Command> {
+    let input = input.trim();
+    let (name, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    let rest = rest.trim();
+    match name {
+        "filter" | "f" if rest.is_empty() => Ok(Command::Filter(None)),
+        "filter" | "f" => Ok(Command::Filter(Some((rest.to_string(), Filter::parse(rest, now)?)))),
+        "" => bail!("Try filter <expression>"),
+        _ => bail!("Unknown command '{}'; try filter <expression>", name),
+    }
+}
+
+/// Matches each word of `query` against `text` as a subsequence, ignoring
+/// case, so "wrdoc" finds "Write docs". Returns the positions of the
+/// matched characters, or `None` if some word doesn't match.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Vec<usize>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut positions = Vec::new();
+    for word in query.split_whitespace() {
+        let mut at = 0;
+        for q in word.chars() {
+            let i = (at..chars.len()).find(|&i| chars[i].to_lowercase().eq(q.to_lowercase()))?;
+            positions.push(i);
+            at = i + 1;
+        }
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    Some(positions)
+}
+
+/// `text` in `style`, with the characters matching `query` picked out.
+pub fn highlight(text: &str, query: &str, style: Style) -> Vec<Span<'static>> {
+    let positions = fuzzy_match(query, text).unwrap_or_default();
+    let matched = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut in_match = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_match = positions.binary_search(&i).is_ok();
+        if is_match != in_match && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), if in_match { matched } else { style }));
+        }
+        in_match = is_match;
+        run.push(c);
+    }
+    spans.push(Span::styled(run, if in_match { matched } else { style }));
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{CmpOp, Condition};
+    use crate::models::Status;
+    use crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn words_match_as_subsequences() {
+        assert_eq!(fuzzy_match("wrdoc", "Write docs"), Some(vec![0, 1, 6, 7, 8]));
+        assert_eq!(fuzzy_match("DOCS wr", "Write docs"), Some(vec![0, 1, 6, 7, 8, 9]));
+        assert_eq!(fuzzy_match("", "Write docs"), Some(Vec::new()));
+        assert_eq!(fuzzy_match("docs zz", "Write docs"), None);
+        assert_eq!(fuzzy_match("sd", "Write docs"), None);
+    }
+
+    #[test]
+    fn highlight_splits_matched_runs() {
+        let style = Style::default();
+        let spans = highlight("Write docs", "doc", style);
+        let texts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(texts, ["Write ", "doc", "s"]);
+        assert_eq!(spans[0].style, style);
+        assert_eq!(spans[1].style.fg, Some(Color::Yellow));
+
+        let texts: Vec<String> = highlight("Write docs", "zz", style).into_iter().map(|s| s.content.into_owned()).collect();
+        assert_eq!(texts, ["Write docs"]);
+    }
+
+    #[test]
+    fn commands_parse_or_explain_themselves() {
+        let now = Local::now();
+        match parse_command("  filter  status:todo ", now).unwrap() {
+            Command::Filter(Some((text, filter))) => {
+                assert_eq!(text, "status:todo");
+                assert!(matches!(filter, Filter::Cond(Condition::Status(CmpOp::Eq, Status::Todo))));
+            }
+            Command::Filter(None) => panic!("expected a filter"),
+        }
+        assert!(matches!(parse_command("f", now).unwrap(), Command::Filter(None)));
+
+        let error = |input| parse_command(input, now).err().unwrap().to_string();
+        assert_eq!(error(""), "Try filter <expression>");
+        assert_eq!(error("sort due"), "Unknown command 'sort'; try filter <expression>");
+        assert!(parse_command("filter status:", now).is_err());
+    }
+
+    #[test]
+    fn prompt_keys_edit_cancel_and_submit() {
+        let mut prompt = Prompt::new(PromptKind::Search { previous: String::new() }, "");
+        assert!(prompt.handle_key(key(KeyCode::Char('d'))) == PromptAction::Changed);
+        assert_eq!(prompt.text(), "d");
+        assert!(prompt.handle_key(key(KeyCode::Enter)) == PromptAction::Submit);
+        assert!(prompt.handle_key(key(KeyCode::Backspace)) == PromptAction::Changed);
+        assert_eq!(prompt.text(), "");
+        assert!(prompt.handle_key(key(KeyCode::Backspace)) == PromptAction::Cancel);
+        assert!(prompt.handle_key(key(KeyCode::Esc)) == PromptAction::Cancel);
+    }
+}
@@ -0,0 +1,180 @@
+//! Runs the `flow` binary against a throwaway data and config directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Sandbox {
+        let dir = std::env::temp_dir().join(format!("flow-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("work")).unwrap();
+        Sandbox { dir }
+    }
+
+    fn work(&self) -> PathBuf {
+        self.dir.join("work")
+    }
+
+    fn flow_in(&self, cwd: &Path, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_flow"))
+            .args(args)
+            .current_dir(cwd)
+            .env("XDG_DATA_HOME", self.dir.join("data"))
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("GIT_CEILING_DIRECTORIES", &self.dir)
+            .output()
+            .unwrap()
+    }
+
+    fn flow(&self, args: &[&str]) -> Output {
+        self.flow_in(&self.work(), args)
+    }
+
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.flow(args);
+        assert!(output.status.success(), "flow {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn task(&self, id: i64) -> serde_json::Value {
+        let tasks: Vec<serde_json::Value> = serde_json::from_str(&self.ok(&["--format", "json", "ls"])).unwrap();
+        tasks.into_iter().find(|t| t["id"] == id).expect("task is listed")
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn edit_rejects_unknown_status_and_priority() {
+    let sandbox = Sandbox::new("edit-values");
+    sandbox.ok(&["add", "Write docs", "--priority", "high"]);
+    sandbox.ok(&["done", "1"]);
+
+    let output = sandbox.flow(&["edit", "1", "--status", "dne"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown status 'dne'"));
+
+    let output = sandbox.flow(&["edit", "1", "-p", "urgent", "--title", "Renamed"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown priority 'urgent'"));
+
+    let task = sandbox.task(1);
+    assert_eq!(task["status"], "Done");
+    assert_eq!(task["priority"], "High");
+    assert_eq!(task["title"], "Write docs");
+}
+
+#[test]
+fn add_rejects_unknown_priority() {
+    let sandbox = Sandbox::new("add-values");
+    let output = sandbox.flow(&["add", "Write docs", "-p", "urgent"]);
+    assert!(!output.status.success());
+    assert_eq!(sandbox.ok(&["ls"]).trim(), "No tasks found.");
+}
+
+#[test]
+fn hooks_never_fail_a_commit() {
+    let sandbox = Sandbox::new("hooks-broken");
+    fs::create_dir_all(sandbox.dir.join("config/flow")).unwrap();
+    fs::write(sandbox.dir.join("config/flow/config.json"), "{ not json").unwrap();
+    let message = sandbox.dir.join("COMMIT_EDITMSG");
+    fs::write(&message, "Fix login, closes flow#1\n").unwrap();
+
+    for hook in ["prepare-commit-msg", "commit-msg"] {
+        let output = sandbox.flow(&["hooks", "run", hook, message.to_str().unwrap()]);
+        assert!(output.status.success(), "{} hook failed the commit", hook);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid config file"));
+    }
+    assert!(sandbox.flow(&["hooks", "run", "post-commit"]).status.success());
+
+    // A database that can't be opened is no different.
+    fs::remove_dir_all(sandbox.dir.join("config")).unwrap();
+    fs::create_dir_all(sandbox.dir.join("data")).unwrap();
+    fs::write(sandbox.dir.join("data/flow"), "not a directory").unwrap();
+    let output = sandbox.flow(&["hooks", "run", "commit-msg", message.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("commit-msg hook failed"));
+
+    assert_eq!(fs::read_to_string(&message).unwrap(), "Fix login, closes flow#1\n");
+}
+
+#[test]
+fn closing_a_task_waits_for_post_commit() {
+    let sandbox = Sandbox::new("hooks-close");
+    let work = sandbox.work();
+    let git = git2::Repository::init(&work).unwrap();
+    sandbox.ok(&["config", "set", "close_tasks_on_commit", "true"]);
+    sandbox.ok(&["add", "Fix login"]);
+
+    let message = sandbox.dir.join("COMMIT_EDITMSG");
+    fs::write(&message, "Fix login, closes flow#1\n").unwrap();
+    sandbox.ok(&["hooks", "run", "commit-msg", message.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&message).unwrap(), "Fix login, closes flow#1\n\nFlow-Task: 1\n");
+    assert_eq!(sandbox.task(1)["status"], "Todo");
+
+    let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+    let tree = git.find_tree(git.index().unwrap().write_tree().unwrap()).unwrap();
+    let text = fs::read_to_string(&message).unwrap();
+    git.commit(Some("HEAD"), &signature, &signature, &text, &tree, &[]).unwrap();
+    let output = sandbox.ok(&["hooks", "run", "post-commit"]);
+    assert!(output.contains("Task 1 marked as done"));
+    assert_eq!(sandbox.task(1)["status"], "Done");
+
+    // Running it again, as after `git commit --amend`, changes nothing.
+    assert_eq!(sandbox.ok(&["hooks", "run", "post-commit"]), "");
+}
+
+#[test]
+fn moving_a_task_out_of_progress_stops_its_timer() {
+    let sandbox = Sandbox::new("edit-timer");
+    sandbox.ok(&["add", "Write docs"]);
+    sandbox.ok(&["start", "1"]);
+    sandbox.ok(&["edit", "1", "--status", "todo"]);
+    assert_eq!(sandbox.task(1)["status"], "Todo");
+    assert_eq!(sandbox.ok(&["stop"]).trim(), "No timer is running.");
+}
+
+#[test]
+fn table_output_tolerates_a_closed_pipe() {
+    let sandbox = Sandbox::new("closed-pipe");
+    sandbox.ok(&["add", "Write docs"]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_flow"))
+        .arg("ls")
+        .current_dir(sandbox.work())
+        .env("XDG_DATA_HOME", sandbox.dir.join("data"))
+        .env("XDG_CONFIG_HOME", sandbox.dir.join("config"))
+        .env("GIT_CEILING_DIRECTORIES", &sandbox.dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Like `flow ls | head -0`: the reader is gone before anything is written.
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn reopen_only_takes_done_tasks() {
+    let sandbox = Sandbox::new("reopen");
+    sandbox.ok(&["add", "Write docs"]);
+    let output = sandbox.flow(&["reopen", "1"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Task 1 is not done"));
+
+    sandbox.ok(&["done", "1"]);
+    assert!(!sandbox.task(1)["completed_at"].is_null());
+    assert!(sandbox.ok(&["reopen", "1"]).contains("Task 1 reopened: Write docs"));
+    let task = sandbox.task(1);
+    assert_eq!(task["status"], "Todo");
+    assert!(task["completed_at"].is_null());
+}